@@ -58,6 +58,7 @@ fn count_echo_pipeable(cmd: &ast::DefaultPipeableCommand) -> usize {
         ast::PipeableCommand::Simple(cmd) => count_echo_simple(cmd),
         ast::PipeableCommand::Compound(cmd) => count_echo_compound(cmd),
         ast::PipeableCommand::FunctionDef(_, cmd) => count_echo_compound(cmd),
+        ast::PipeableCommand::Coproc { body, .. } => count_echo_pipeable(body),
     }
 }
 
@@ -90,7 +91,8 @@ fn count_echo_compound(cmd: &ast::DefaultCompoundCommand) -> usize {
             num_echo_in_conditionals + num_echo_in_else
         }
 
-        ast::CompoundCommandKind::For { body, .. } => count_echo_top_level_array(&body),
+        ast::CompoundCommandKind::For { body, .. }
+        | ast::CompoundCommandKind::ArithFor { body, .. } => count_echo_top_level_array(body),
 
         ast::CompoundCommandKind::Case { arms, .. } => arms
             .iter()
@@ -104,7 +106,10 @@ fn count_echo_simple(cmd: &ast::DefaultSimpleCommand) -> usize {
         .iter()
         .filter_map(|redirect_or_word| match redirect_or_word {
             ast::RedirectOrCmdWord::CmdWord(w) => Some(&w.0),
-            ast::RedirectOrCmdWord::Redirect(_) => None,
+            ast::RedirectOrCmdWord::Redirect(_)
+            | ast::RedirectOrCmdWord::Assignment(..)
+            | ast::RedirectOrCmdWord::Declaration(_)
+            | ast::RedirectOrCmdWord::Attributes(_) => None,
         })
         .filter_map(|word| match word {
             ast::ComplexWord::Single(w) => Some(w),
@@ -119,6 +124,7 @@ fn count_echo_simple(cmd: &ast::DefaultSimpleCommand) -> usize {
                 get_simple_word_as_string(&words[0])
             }
             ast::Word::DoubleQuoted(_) => None, // Ignore all multi-word double quoted strings
+            ast::Word::BracketClass(..) => None,
         })
         .filter(|w| *w == "echo")
         .count()
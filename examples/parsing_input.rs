@@ -1,20 +1,16 @@
-use conch_parser::lexer::Lexer;
+use conch_parser::lexer::{Lexer, Utf8Chars};
 use conch_parser::parse::DefaultParser;
-use owned_chars::OwnedCharsExt;
 
-use std::io::{stdin, BufRead, BufReader};
+use std::io::stdin;
 
 fn main() {
-    let stdin = BufReader::new(stdin())
-        .lines()
-        .map(Result::unwrap)
-        .flat_map(|mut line| {
-            line.push_str("\n"); // BufRead::lines unfortunately strips \n and \r\n
-            line.into_chars()
-        });
+    // Characters are decoded from stdin as the parser needs them,
+    // so the entire input never has to be buffered in memory.
+    let stdin = stdin();
+    let chars = Utf8Chars::new(stdin.lock()).map(Result::unwrap);
 
     // Initialize our token lexer and shell parser with the program's input
-    let lex = Lexer::new(stdin);
+    let lex = Lexer::new(chars);
     let parser = DefaultParser::new(lex);
 
     // Parse our input!
@@ -1,9 +1,17 @@
 //! Defines abstract representations of the shell source.
+use crate::ast::builder::{AtomicDefaultBuilder, DefaultBuilder};
+use crate::lexer::Lexer;
+use crate::parse::{ParseError, Parser};
+use std::convert::TryFrom;
 use std::rc::Rc;
+use std::str::FromStr;
 use std::sync::Arc;
 use std::{fmt, ops};
+use void::Void;
 
 pub mod builder;
+pub mod fold;
+pub mod visit;
 
 /// Type alias for the default `Parameter` representation.
 pub type DefaultParameter = Parameter<String>;
@@ -11,7 +19,7 @@ pub type DefaultParameter = Parameter<String>;
 /// Represents reading a parameter (or variable) value, e.g. `$foo`.
 ///
 /// Generic over the representation of variable names.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Parameter<T> {
     /// $@
     At,
@@ -30,6 +38,9 @@ pub enum Parameter<T> {
     /// $0, $1, ..., $9, ${100}
     Positional(u32),
     /// $foo
+    ///
+    /// Special parameters which are also valid names, such as bash's `$_`,
+    /// are represented as regular variables, e.g. `Var("_")`.
     Var(T),
 }
 
@@ -45,7 +56,7 @@ pub type DefaultParameterSubstitution = ParameterSubstitution<
 ///
 /// Generic over the representations of parameters, shell words and
 /// commands, and arithmetic expansions.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum ParameterSubstitution<P, W, C, A> {
     /// Returns the standard output of running a command, e.g. `$(cmd)`
     Command(Vec<C>),
@@ -81,14 +92,21 @@ pub enum ParameterSubstitution<P, W, C, A> {
     RemoveSmallestPrefix(P, Option<W>),
     /// Remove largest prefix pattern from a parameter's value, e.g. `${param##pattern}`
     RemoveLargestPrefix(P, Option<W>),
+    /// Returns the names of all variables beginning with a prefix, e.g. `${!prefix*}`.
+    /// The boolean indicates the presence of a `@` rather than a `*`, and that each
+    /// name should expand to a separate field when double quoted.
+    NamesMatchingPrefix(String, bool),
+    /// Returns the value of a parameter transformed by a single letter operator,
+    /// e.g. `${param@Q}` for quoting the value so it can be reused as input.
+    Transform(P, char),
+    /// Returns the contents of a file, e.g. `$(< file)`, which is equivalent
+    /// to `$(cat file)` but without running an external command.
+    ReadFile(W),
 }
 
 /// A type alias for the default hiearchy for representing shell words.
-pub type ShellWord<T, W, C> = ComplexWord<
-    Word<
-        T,
-        SimpleWord<T, Parameter<T>, Box<ParameterSubstitution<Parameter<T>, W, C, Arithmetic<T>>>>,
-    >,
+pub type ShellWord<T, W, C, A = Arithmetic<T>> = ComplexWord<
+    Word<T, SimpleWord<T, Parameter<T>, Box<ParameterSubstitution<Parameter<T>, W, C, A>>>>,
 >;
 
 /// Type alias for the default `ComplexWord` representation.
@@ -97,7 +115,7 @@ pub type DefaultComplexWord = ComplexWord<DefaultWord>;
 /// Represents whitespace delimited text.
 ///
 /// Generic over the representation of a whitespace delimited word.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum ComplexWord<W> {
     /// Several distinct words concatenated together.
     Concat(Vec<W>),
@@ -111,7 +129,7 @@ pub type DefaultWord = Word<String, DefaultSimpleWord>;
 /// Represents whitespace delimited single, double, or non quoted text.
 ///
 /// Generic over the representation of single-quoted literals, and non-quoted words.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Word<L, W> {
     /// A regular word.
     Simple(W),
@@ -120,6 +138,10 @@ pub enum Word<L, W> {
     /// List of words concatenated within single quotes. Virtually
     /// identical as a literal, but makes a distinction between the two.
     SingleQuoted(L),
+    /// A bracket expression matching a single character in a pattern, e.g. `[a-z]`.
+    /// The boolean indicates the set was negated with a leading `!` or `^`, and the
+    /// literal holds the members of the set without the surrounding brackets.
+    BracketClass(bool, L),
 }
 
 /// Type alias for the default `SimpleWord` representation.
@@ -129,7 +151,7 @@ pub type DefaultSimpleWord =
 /// Represents the smallest fragment of any text.
 ///
 /// Generic over the representation of a literals, parameters, and substitutions.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum SimpleWord<L, P, S> {
     /// A non-special literal word.
     Literal(L),
@@ -160,7 +182,7 @@ pub type DefaultRedirect = Redirect<TopLevelWord<String>>;
 /// Represents redirecting a command's file descriptors.
 ///
 /// Generic over the representation of a shell word.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Redirect<W> {
     /// Open a file for reading, e.g. `[n]< file`.
     Read(Option<u16>, W),
@@ -178,10 +200,23 @@ pub enum Redirect<W> {
     DupRead(Option<u16>, W),
     /// Duplicate a file descriptor for writing, e.g. `[n]>& [n|-]`.
     DupWrite(Option<u16>, W),
+    /// Duplicate a file descriptor for reading, closing the original, e.g. `[n]<&n-`.
+    MoveRead(Option<u16>, W),
+    /// Duplicate a file descriptor for writing, closing the original, e.g. `[n]>&n-`.
+    MoveWrite(Option<u16>, W),
+    /// Open a file for writing after truncating, and redirect both standard output
+    /// and standard error to it, e.g. `&> file`.
+    WriteBoth(W),
+    /// Open a file for writing, appending to the end, and redirect both standard
+    /// output and standard error to it, e.g. `&>> file`.
+    AppendBoth(W),
+    /// Open a file for writing regardless of the `noclobber` shell option, and
+    /// redirect both standard output and standard error to it, e.g. `&>| file`.
+    ClobberBoth(W),
 }
 
 /// A grouping of guard and body commands.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct GuardBodyPair<C> {
     /// The guard commands, which if successful, should lead to the
     /// execution of the body commands.
@@ -191,19 +226,32 @@ pub struct GuardBodyPair<C> {
 }
 
 /// A grouping of patterns and body commands.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct PatternBodyPair<W, C> {
     /// Pattern alternatives to match against.
     pub patterns: Vec<W>,
     /// The body commands to execute if the pattern matches.
     pub body: Vec<C>,
+    /// What to do after the body commands have been executed.
+    pub terminator: CaseArmTerminator,
+}
+
+/// Indicates how an arm of a `case` command was terminated.
+#[derive(Debug, PartialEq, Eq, Clone, Copy, Hash)]
+pub enum CaseArmTerminator {
+    /// Stop executing the `case` command, e.g. `;;`, or no terminator on the last arm.
+    Break,
+    /// Execute the body of the next arm without matching its patterns, e.g. `;&`.
+    FallThrough,
+    /// Continue matching the patterns of the following arms, e.g. `;;&`.
+    Continue,
 }
 
 /// Type alias for the default `Command` representation.
 pub type DefaultCommand = Command<DefaultAndOrList>;
 
 /// Represents any valid shell command.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Command<T> {
     /// A command that runs asynchronously, that is, the shell will not wait
     /// for it to exit before running the next command, e.g. `foo &`.
@@ -214,37 +262,38 @@ pub enum Command<T> {
 
 /// A type alias over an and/or list of conventional shell commands.
 ///
-/// Generic over the representation of literals, shell words, commands, and redirects.
-/// Uses `Rc` wrappers around function declarations.
-pub type CommandList<T, W, C> = AndOrList<ListableCommand<ShellPipeableCommand<T, W, C>>>;
+/// Generic over the representation of literals, shell words, commands, and
+/// arithmetic expressions. Uses `Rc` wrappers around function declarations.
+pub type CommandList<T, W, C, A = Arithmetic<T>> =
+    AndOrList<ListableCommand<ShellPipeableCommand<T, W, C, A>>>;
 
 /// A type alias over an and/or list of conventional shell commands.
 ///
-/// Generic over the representation of literals, shell words, commands, and redirects.
-/// Uses `Arc` wrappers around function declarations.
-pub type AtomicCommandList<T, W, C> =
-    AndOrList<ListableCommand<AtomicShellPipeableCommand<T, W, C>>>;
+/// Generic over the representation of literals, shell words, commands, and
+/// arithmetic expressions. Uses `Arc` wrappers around function declarations.
+pub type AtomicCommandList<T, W, C, A = Arithmetic<T>> =
+    AndOrList<ListableCommand<AtomicShellPipeableCommand<T, W, C, A>>>;
 
 /// A type alias for the default hiearchy to represent pipeable commands,
 /// using `Rc` wrappers around function declarations.
-pub type ShellPipeableCommand<T, W, C> = PipeableCommand<
+pub type ShellPipeableCommand<T, W, C, A = Arithmetic<T>> = PipeableCommand<
     T,
     Box<SimpleCommand<T, W, Redirect<W>>>,
-    Box<ShellCompoundCommand<T, W, C>>,
-    Rc<ShellCompoundCommand<T, W, C>>,
+    Box<ShellCompoundCommand<T, W, C, A>>,
+    Rc<ShellCompoundCommand<T, W, C, A>>,
 >;
 
 /// A type alias for the default hiearchy to represent pipeable commands,
 /// using `Arc` wrappers around function declarations.
-pub type AtomicShellPipeableCommand<T, W, C> = PipeableCommand<
+pub type AtomicShellPipeableCommand<T, W, C, A = Arithmetic<T>> = PipeableCommand<
     T,
     Box<SimpleCommand<T, W, Redirect<W>>>,
-    Box<ShellCompoundCommand<T, W, C>>,
-    Arc<ShellCompoundCommand<T, W, C>>,
+    Box<ShellCompoundCommand<T, W, C, A>>,
+    Arc<ShellCompoundCommand<T, W, C, A>>,
 >;
 
 /// A command which conditionally runs based on the exit status of the previous command.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum AndOr<T> {
     /// A compound command which should run only if the previously run command succeeded.
     And(T),
@@ -252,11 +301,38 @@ pub enum AndOr<T> {
     Or(T),
 }
 
+impl<T> AndOr<T> {
+    /// Returns whether the command runs on the success or failure of the previous one.
+    pub fn kind(&self) -> AndOrKind {
+        match *self {
+            AndOr::And(_) => AndOrKind::And,
+            AndOr::Or(_) => AndOrKind::Or,
+        }
+    }
+
+    /// Returns a reference to the conditionally run command.
+    pub fn command(&self) -> &T {
+        match *self {
+            AndOr::And(ref t) | AndOr::Or(ref t) => t,
+        }
+    }
+}
+
+/// Indicates whether an `AndOr` command runs on the success or failure
+/// of the previous command, without holding the command itself.
+#[derive(Debug, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum AndOrKind {
+    /// Corresponds to `AndOr::And`, i.e. `&&`.
+    And,
+    /// Corresponds to `AndOr::Or`, i.e. `||`.
+    Or,
+}
+
 /// Type alias for the default `AndOrList` representation.
 pub type DefaultAndOrList = AndOrList<DefaultListableCommand>;
 
 /// A nonempty list of `AndOr` commands, e.g. `foo && bar || baz`.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct AndOrList<T> {
     /// The first command that always runs.
     pub first: T,
@@ -264,11 +340,66 @@ pub struct AndOrList<T> {
     pub rest: Vec<AndOr<T>>,
 }
 
+impl<T> AndOrList<T> {
+    /// Returns an iterator over the commands of the list in evaluation order.
+    ///
+    /// The `first` command is yielded with no `AndOrKind`, followed by each
+    /// command in `rest` along with the condition under which it runs.
+    pub fn iter(&self) -> AndOrListIter<'_, T> {
+        AndOrListIter {
+            first: Some(&self.first),
+            rest: self.rest.iter(),
+        }
+    }
+}
+
+impl<'a, T> IntoIterator for &'a AndOrList<T> {
+    type Item = (Option<AndOrKind>, &'a T);
+    type IntoIter = AndOrListIter<'a, T>;
+
+    fn into_iter(self) -> Self::IntoIter {
+        self.iter()
+    }
+}
+
+/// An iterator over the commands of an `AndOrList`, created by `AndOrList::iter`.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Debug, Clone)]
+pub struct AndOrListIter<'a, T> {
+    first: Option<&'a T>,
+    rest: std::slice::Iter<'a, AndOr<T>>,
+}
+
+impl<'a, T> Iterator for AndOrListIter<'a, T> {
+    type Item = (Option<AndOrKind>, &'a T);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        match self.first.take() {
+            Some(first) => Some((None, first)),
+            None => self
+                .rest
+                .next()
+                .map(|and_or| (Some(and_or.kind()), and_or.command())),
+        }
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        let len = self.len();
+        (len, Some(len))
+    }
+}
+
+impl<T> ExactSizeIterator for AndOrListIter<'_, T> {
+    fn len(&self) -> usize {
+        self.rest.len() + usize::from(self.first.is_some())
+    }
+}
+
 /// Type alias for the default `ListableCommand` representation.
 pub type DefaultListableCommand = ListableCommand<DefaultPipeableCommand>;
 
 /// Commands that can be used within an and/or list.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum ListableCommand<T> {
     /// A chain of concurrent commands where the standard output of the
     /// previous becomes the standard input of the next, e.g.
@@ -289,7 +420,7 @@ pub type DefaultPipeableCommand =
 ///
 /// Generic over the representations of function names, simple commands,
 /// compound commands, and function bodies.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum PipeableCommand<N, S, C, F> {
     /// The simplest possible command: an executable with arguments,
     /// environment variable assignments, and redirections.
@@ -299,10 +430,21 @@ pub enum PipeableCommand<N, S, C, F> {
     /// A function definition, associating a name with a group of commands,
     /// e.g. `function foo() { echo foo function; }`.
     FunctionDef(N, F),
+    /// A command run asynchronously as a coprocess, optionally with a name,
+    /// e.g. `coproc name { cat; }` or `coproc cat`.
+    ///
+    /// The body is always either a simple or compound command.
+    Coproc {
+        /// The name of the coprocess, if one was specified.
+        name: Option<N>,
+        /// The command to run within the coprocess.
+        body: Box<PipeableCommand<N, S, C, F>>,
+    },
 }
 
 /// A type alias for the default hiearchy for representing compound shell commands.
-pub type ShellCompoundCommand<T, W, C> = CompoundCommand<CompoundCommandKind<T, W, C>, Redirect<W>>;
+pub type ShellCompoundCommand<T, W, C, A = Arithmetic<T>> =
+    CompoundCommand<CompoundCommandKind<T, W, C, A>, Redirect<W>>;
 
 /// Type alias for the default `CompoundCommandKind` representation.
 pub type DefaultCompoundCommand =
@@ -312,7 +454,7 @@ pub type DefaultCompoundCommand =
 ///
 /// Generic over the representation of a type of compound command, and the
 /// representation of a redirect.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct CompoundCommand<T, R> {
     /// The specific kind of compound command.
     pub kind: T,
@@ -326,9 +468,10 @@ pub type DefaultCompoundCommandKind =
 
 /// A specific kind of a `CompoundCommand`.
 ///
-/// Generic over the representation of shell words and commands.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum CompoundCommandKind<V, W, C> {
+/// Generic over the representation of variable names, shell words, commands,
+/// and arithmetic expressions.
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum CompoundCommandKind<V, W, C, A = Arithmetic<V>> {
     /// A group of commands that should be executed in the current environment.
     Brace(Vec<C>),
     /// A group of commands that should be executed in a subshell environment.
@@ -355,6 +498,19 @@ pub enum CompoundCommandKind<V, W, C> {
         /// The body to run with the variable binding.
         body: Vec<C>,
     },
+    /// A command that evaluates arithmetic expressions to control how many times
+    /// its body is run, e.g. `for ((i=0; i<5; i++)); do body; done`.
+    ArithFor {
+        /// The expression to evaluate once before the first iteration, if any.
+        init: Option<A>,
+        /// The expression which must evaluate to a non-zero value for the body
+        /// to run, if any. An absent condition is always considered true.
+        cond: Option<A>,
+        /// The expression to evaluate after each iteration, if any.
+        update: Option<A>,
+        /// The body to run on each iteration.
+        body: Vec<C>,
+    },
     /// A command that behaves much like a `match` statment in Rust, running
     /// a branch of commands if a specified word matches another literal or
     /// glob pattern.
@@ -373,7 +529,7 @@ pub enum CompoundCommandKind<V, W, C> {
 /// execution, the parser will preserve the order in which they were parsed.
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum RedirectOrEnvVar<R, V, W> {
     /// A parsed redirect before a command was encountered.
     Redirect(R),
@@ -387,12 +543,83 @@ pub enum RedirectOrEnvVar<R, V, W> {
 /// execution, the parser will preserve the order in which they were parsed.
 /// Thus we need a wrapper like this to disambiguate what was encountered in
 /// the source program.
-#[derive(Debug, PartialEq, Eq, Clone)]
-pub enum RedirectOrCmdWord<R, W> {
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
+pub enum RedirectOrCmdWord<R, V, W> {
     /// A parsed redirect after a command was encountered.
     Redirect(R),
     /// A parsed command name or argument.
     CmdWord(W),
+    /// A parsed variable assignment passed as an argument to an
+    /// assignment builtin, e.g. `export foo=[bar]`.
+    Assignment(V, Option<W>),
+    /// A variable name passed without any `=` as an argument to an assignment
+    /// builtin, e.g. `export foo`, which leaves the value of the variable untouched.
+    Declaration(V),
+    /// Attribute flags passed as an option to an assignment builtin, e.g. the
+    /// `-ir` in `declare -ir foo=5`.
+    Attributes(VarAttributes),
+}
+
+/// The set of variable attributes which can be requested through the options
+/// of an assignment builtin, such as `declare -i` or `local -a`.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Hash)]
+pub struct VarAttributes {
+    /// `-i`: the variable holds an integer.
+    pub integer: bool,
+    /// `-r`: the variable is read-only.
+    pub readonly: bool,
+    /// `-a`: the variable is an indexed array.
+    pub indexed_array: bool,
+    /// `-A`: the variable is an associative array.
+    pub associative_array: bool,
+    /// `-x`: the variable is exported to the environment of commands.
+    pub export: bool,
+    /// `-l`: the value is converted to lower case on assignment.
+    pub lowercase: bool,
+    /// `-u`: the value is converted to upper case on assignment.
+    pub uppercase: bool,
+}
+
+impl VarAttributes {
+    /// Builds an attribute set from the letters of an option word without its
+    /// leading `-`, e.g. `ir` for `-ir`.
+    ///
+    /// Returns `None` if `flags` is empty or contains any letter which does not
+    /// denote a known attribute.
+    pub fn from_flags(flags: &str) -> Option<Self> {
+        if flags.is_empty() {
+            return None;
+        }
+
+        let mut attrs = Self::default();
+        for flag in flags.chars() {
+            match flag {
+                'i' => attrs.integer = true,
+                'r' => attrs.readonly = true,
+                'a' => attrs.indexed_array = true,
+                'A' => attrs.associative_array = true,
+                'x' => attrs.export = true,
+                'l' => attrs.lowercase = true,
+                'u' => attrs.uppercase = true,
+                _ => return None,
+            }
+        }
+
+        Some(attrs)
+    }
+
+    /// Combines two attribute sets, keeping every attribute set in either one.
+    pub fn union(self, other: Self) -> Self {
+        VarAttributes {
+            integer: self.integer || other.integer,
+            readonly: self.readonly || other.readonly,
+            indexed_array: self.indexed_array || other.indexed_array,
+            associative_array: self.associative_array || other.associative_array,
+            export: self.export || other.export,
+            lowercase: self.lowercase || other.lowercase,
+            uppercase: self.uppercase || other.uppercase,
+        }
+    }
 }
 
 /// Type alias for the default `SimpleCommand` representation.
@@ -403,13 +630,55 @@ pub type DefaultSimpleCommand =
 /// environment variable assignments, and redirections.
 ///
 /// Generic over representations of variable names, shell words, and redirects.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub struct SimpleCommand<V, W, R> {
     /// Redirections or environment variables that occur before any command
     /// in the order they were parsed.
     pub redirects_or_env_vars: Vec<RedirectOrEnvVar<R, V, W>>,
     /// Redirections or command name/argumetns in the order they were parsed.
-    pub redirects_or_cmd_words: Vec<RedirectOrCmdWord<R, W>>,
+    pub redirects_or_cmd_words: Vec<RedirectOrCmdWord<R, V, W>>,
+}
+
+impl<V, W, R> SimpleCommand<V, W, R> {
+    /// Checks if the command consists solely of variable assignments, e.g. `var=1`,
+    /// and has no command name, arguments, or redirections.
+    pub fn is_assignment_only(&self) -> bool {
+        !self.redirects_or_env_vars.is_empty()
+            && self.redirects_or_cmd_words.is_empty()
+            && self
+                .redirects_or_env_vars
+                .iter()
+                .all(|r| matches!(*r, RedirectOrEnvVar::EnvVar(..)))
+    }
+
+    /// Checks if the command consists solely of redirections, e.g. `> file`,
+    /// and has no command name, arguments, or variable assignments.
+    pub fn is_redirect_only(&self) -> bool {
+        let redirects_before = self
+            .redirects_or_env_vars
+            .iter()
+            .all(|r| matches!(*r, RedirectOrEnvVar::Redirect(_)));
+        let redirects_after = self
+            .redirects_or_cmd_words
+            .iter()
+            .all(|r| matches!(*r, RedirectOrCmdWord::Redirect(_)));
+
+        redirects_before
+            && redirects_after
+            && !(self.redirects_or_env_vars.is_empty() && self.redirects_or_cmd_words.is_empty())
+    }
+
+    /// Returns the union of all attribute options passed to an assignment
+    /// builtin, e.g. both `integer` and `readonly` for `declare -i -r foo`.
+    pub fn attributes(&self) -> VarAttributes {
+        self.redirects_or_cmd_words
+            .iter()
+            .filter_map(|r| match *r {
+                RedirectOrCmdWord::Attributes(attrs) => Some(attrs),
+                _ => None,
+            })
+            .fold(VarAttributes::default(), VarAttributes::union)
+    }
 }
 
 /// Type alias for the default `Arithmetic` representation.
@@ -418,7 +687,7 @@ pub type DefaultArithmetic = Arithmetic<String>;
 /// Represents an expression within an arithmetic subsitution.
 ///
 /// Generic over the representation of a variable name.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, PartialEq, Eq, Clone, Hash)]
 pub enum Arithmetic<T> {
     /// The value of a variable, e.g. `$var` or `var`.
     Var(T),
@@ -492,7 +761,7 @@ pub enum Arithmetic<T> {
 macro_rules! impl_top_level_cmd {
     ($(#[$attr:meta])* pub struct $Cmd:ident, $CmdList:ident, $Word:ident) => {
         $(#[$attr])*
-        #[derive(Debug, PartialEq, Eq, Clone)]
+        #[derive(Debug, PartialEq, Eq, Clone, Hash)]
         pub struct $Cmd<T>(pub Command<$CmdList<T, $Word<T>, $Cmd<T>>>);
 
         impl<T> ops::Deref for $Cmd<T> {
@@ -549,7 +818,7 @@ impl_top_level_cmd! {
 macro_rules! impl_top_level_word {
     ($(#[$attr:meta])* pub struct $Word:ident, $Cmd:ident) => {
         $(#[$attr])*
-        #[derive(Debug, PartialEq, Eq, Clone)]
+        #[derive(Debug, PartialEq, Eq, Clone, Hash)]
         pub struct $Word<T>(pub ShellWord<T, $Word<T>, $Cmd<T>>);
 
         impl<T> ops::Deref for $Word<T> {
@@ -600,6 +869,146 @@ impl_top_level_word! {
     AtomicTopLevelCommand
 }
 
+macro_rules! impl_from_str {
+    ($(#[$attr:meta])* $Type:ident, $Builder:ident, $parse:ident) => {
+        impl<T: From<String>> FromStr for $Type<T> {
+            type Err = ParseError<Void>;
+
+            $(#[$attr])*
+            fn from_str(s: &str) -> Result<Self, Self::Err> {
+                let lex = Lexer::new(s.chars());
+                let mut parser = Parser::with_builder(lex, $Builder::new());
+                let ret = parser.$parse()?.ok_or(ParseError::UnexpectedEOF)?;
+                parser.expect_eof()?;
+                Ok(ret)
+            }
+        }
+    };
+}
+
+impl_from_str! {
+    /// Parses exactly one complete command, e.g. `"echo hi".parse()`.
+    ///
+    /// It is an error if no command is present, or if anything other than
+    /// whitespace, newlines, or comments follows the command.
+    TopLevelCommand, DefaultBuilder, complete_command
+}
+
+impl_from_str! {
+    /// Parses exactly one complete command, e.g. `"echo hi".parse()`.
+    ///
+    /// It is an error if no command is present, or if anything other than
+    /// whitespace, newlines, or comments follows the command.
+    AtomicTopLevelCommand, AtomicDefaultBuilder, complete_command
+}
+
+impl_from_str! {
+    /// Parses exactly one word, e.g. `"$foo".parse()`.
+    ///
+    /// It is an error if no word is present, or if anything other than
+    /// whitespace, newlines, or comments follows the word.
+    TopLevelWord, DefaultBuilder, word
+}
+
+impl_from_str! {
+    /// Parses exactly one word, e.g. `"$foo".parse()`.
+    ///
+    /// It is an error if no word is present, or if anything other than
+    /// whitespace, newlines, or comments follows the word.
+    AtomicTopLevelWord, AtomicDefaultBuilder, word
+}
+
+/// Interprets a word made up solely of literal text as a cluster of single letter
+/// options, such as the `-euo` in `set -euo pipefail`, or the `+x` in `set +x`.
+///
+/// Returns the leading sign (`-` or `+`) along with each of the option letters.
+/// The end of options marker, `--`, is returned as a `-` without any letters.
+/// Any other word, e.g. one without a sign, a long option like `--foo`, or one
+/// containing parameters or substitutions, yields `None`.
+pub fn parse_option_cluster<T: AsRef<str>, W, C>(
+    word: &ShellWord<T, W, C>,
+) -> Option<(char, Vec<char>)> {
+    fn push_literal<T: AsRef<str>, P, S>(buf: &mut String, word: &SimpleWord<T, P, S>) -> bool {
+        match *word {
+            SimpleWord::Literal(ref s) | SimpleWord::Escaped(ref s) => {
+                buf.push_str(s.as_ref());
+                true
+            }
+            _ => false,
+        }
+    }
+
+    let fragments = match *word {
+        ComplexWord::Single(ref w) => std::slice::from_ref(w),
+        ComplexWord::Concat(ref ws) => &ws[..],
+    };
+
+    let mut text = String::new();
+    for fragment in fragments {
+        let is_literal = match *fragment {
+            Word::Simple(ref w) => push_literal(&mut text, w),
+            Word::DoubleQuoted(ref ws) => ws.iter().all(|w| push_literal(&mut text, w)),
+            Word::SingleQuoted(ref s) => {
+                text.push_str(s.as_ref());
+                true
+            }
+            Word::BracketClass(..) => false,
+        };
+
+        if !is_literal {
+            return None;
+        }
+    }
+
+    if text == "--" {
+        return Some(('-', Vec::new()));
+    }
+
+    let mut chars = text.chars();
+    let sign = match chars.next() {
+        Some(c @ '-') | Some(c @ '+') => c,
+        _ => return None,
+    };
+
+    let flags: Vec<char> = chars.collect();
+    if flags.is_empty() || !flags.iter().all(char::is_ascii_alphabetic) {
+        return None;
+    }
+
+    Some((sign, flags))
+}
+
+impl<T> Parameter<T> {
+    /// Returns an adapter which displays the parameter's sigil or name without the
+    /// leading `$` or any braces, e.g. `@`, `12`, or `foo`, so that callers can choose
+    /// between the `$...` and `${...}` forms themselves.
+    pub fn bare(&self) -> BareParameter<'_, T> {
+        BareParameter(self)
+    }
+}
+
+/// Displays a `Parameter` without its leading `$`, created by `Parameter::bare`.
+#[derive(Debug, Clone, Copy)]
+pub struct BareParameter<'a, T>(&'a Parameter<T>);
+
+impl<T: fmt::Display> fmt::Display for BareParameter<'_, T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Parameter::*;
+
+        match *self.0 {
+            At => fmt.write_str("@"),
+            Star => fmt.write_str("*"),
+            Pound => fmt.write_str("#"),
+            Question => fmt.write_str("?"),
+            Dash => fmt.write_str("-"),
+            Dollar => fmt.write_str("$"),
+            Bang => fmt.write_str("!"),
+            Var(ref p) => write!(fmt, "{}", p),
+            Positional(p) => write!(fmt, "{}", p),
+        }
+    }
+}
+
 impl<T: fmt::Display> fmt::Display for Parameter<T> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         use self::Parameter::*;
@@ -625,8 +1034,313 @@ impl<T: fmt::Display> fmt::Display for Parameter<T> {
     }
 }
 
+impl<T> Arithmetic<T> {
+    /// Binding strength of the expression's outermost operator, mirroring
+    /// the parser's precedence levels from `Sequence` (loosest) upwards.
+    fn precedence(&self) -> u8 {
+        use self::Arithmetic::*;
+
+        match *self {
+            Sequence(_) => 0,
+            Assign(..) => 1,
+            Ternary(..) => 2,
+            LogicalOr(..) => 3,
+            LogicalAnd(..) => 4,
+            BitwiseOr(..) => 5,
+            BitwiseXor(..) => 6,
+            BitwiseAnd(..) => 7,
+            Eq(..) | NotEq(..) => 8,
+            Less(..) | LessEq(..) | Great(..) | GreatEq(..) => 9,
+            ShiftLeft(..) | ShiftRight(..) => 10,
+            Add(..) | Sub(..) => 11,
+            Mult(..) | Div(..) | Modulo(..) => 12,
+            Pow(..) => 13,
+            UnaryPlus(_) | UnaryMinus(_) | LogicalNot(_) | BitwiseNot(_) | PreIncr(_)
+            | PreDecr(_) => 14,
+            Var(_) | Literal(_) | PostIncr(_) | PostDecr(_) => 15,
+        }
+    }
+
+    /// Constant-folds every subexpression made up solely of literals,
+    /// e.g. `(1 + 2) * 3` becomes `9`.
+    ///
+    /// No variables are ever evaluated: any operation involving a variable,
+    /// an assignment, or an increment/decrement is kept as is, though its
+    /// literal-only operands are still folded. Operations which would
+    /// overflow or divide by zero are left unfolded as well.
+    ///
+    /// Negative results are represented as `UnaryMinus(Literal(n))`, just as
+    /// the parser would represent them, while a result of `isize::MIN` (whose
+    /// magnitude cannot be written as a literal) is left unfolded.
+    pub fn simplify(self) -> Self {
+        use self::Arithmetic::*;
+
+        /// The value of a (possibly negated) literal.
+        fn value<T>(expr: &Arithmetic<T>) -> Option<isize> {
+            match *expr {
+                Literal(n) => Some(n),
+                UnaryMinus(ref e) => match **e {
+                    Literal(n) => n.checked_neg(),
+                    _ => None,
+                },
+                _ => None,
+            }
+        }
+
+        /// The expression the parser would produce for a value, if any.
+        fn literal<T>(n: isize) -> Option<Arithmetic<T>> {
+            if n >= 0 {
+                Some(Literal(n))
+            } else {
+                n.checked_neg().map(|n| UnaryMinus(Box::new(Literal(n))))
+            }
+        }
+
+        let unary =
+            |expr: Box<Self>, op: fn(isize) -> Option<isize>, wrap: fn(Box<Self>) -> Self| {
+                let expr = expr.simplify();
+                value(&expr)
+                    .and_then(op)
+                    .and_then(literal)
+                    .unwrap_or_else(|| wrap(Box::new(expr)))
+            };
+
+        let binary = |left: Box<Self>,
+                      right: Box<Self>,
+                      op: fn(isize, isize) -> Option<isize>,
+                      wrap: fn(Box<Self>, Box<Self>) -> Self| {
+            let (left, right) = (left.simplify(), right.simplify());
+            value(&left)
+                .and_then(|l| value(&right).and_then(|r| op(l, r)))
+                .and_then(literal)
+                .unwrap_or_else(|| wrap(Box::new(left), Box::new(right)))
+        };
+
+        fn flag(b: bool) -> Option<isize> {
+            Some(b as isize)
+        }
+
+        fn shift_amount(r: isize) -> Option<u32> {
+            u32::try_from(r).ok().filter(|&r| r < isize::BITS)
+        }
+
+        match self {
+            expr @ Var(_)
+            | expr @ Literal(_)
+            | expr @ PostIncr(_)
+            | expr @ PostDecr(_)
+            | expr @ PreIncr(_)
+            | expr @ PreDecr(_) => expr,
+
+            UnaryPlus(e) => unary(e, Some, UnaryPlus),
+            UnaryMinus(e) => unary(e, isize::checked_neg, UnaryMinus),
+            LogicalNot(e) => unary(e, |n| flag(n == 0), LogicalNot),
+            BitwiseNot(e) => unary(e, |n| Some(!n), BitwiseNot),
+
+            Pow(l, r) => binary(
+                l,
+                r,
+                |l, r| u32::try_from(r).ok().and_then(|r| l.checked_pow(r)),
+                Pow,
+            ),
+            Mult(l, r) => binary(l, r, isize::checked_mul, Mult),
+            Div(l, r) => binary(l, r, isize::checked_div, Div),
+            Modulo(l, r) => binary(l, r, isize::checked_rem, Modulo),
+            Add(l, r) => binary(l, r, isize::checked_add, Add),
+            Sub(l, r) => binary(l, r, isize::checked_sub, Sub),
+            ShiftLeft(l, r) => binary(
+                l,
+                r,
+                |l, r| shift_amount(r).map(|r| l.wrapping_shl(r)),
+                ShiftLeft,
+            ),
+            ShiftRight(l, r) => binary(
+                l,
+                r,
+                |l, r| shift_amount(r).map(|r| l.wrapping_shr(r)),
+                ShiftRight,
+            ),
+            Less(l, r) => binary(l, r, |l, r| flag(l < r), Less),
+            LessEq(l, r) => binary(l, r, |l, r| flag(l <= r), LessEq),
+            Great(l, r) => binary(l, r, |l, r| flag(l > r), Great),
+            GreatEq(l, r) => binary(l, r, |l, r| flag(l >= r), GreatEq),
+            Eq(l, r) => binary(l, r, |l, r| flag(l == r), Eq),
+            NotEq(l, r) => binary(l, r, |l, r| flag(l != r), NotEq),
+            BitwiseAnd(l, r) => binary(l, r, |l, r| Some(l & r), BitwiseAnd),
+            BitwiseXor(l, r) => binary(l, r, |l, r| Some(l ^ r), BitwiseXor),
+            BitwiseOr(l, r) => binary(l, r, |l, r| Some(l | r), BitwiseOr),
+            LogicalAnd(l, r) => binary(l, r, |l, r| flag(l != 0 && r != 0), LogicalAnd),
+            LogicalOr(l, r) => binary(l, r, |l, r| flag(l != 0 || r != 0), LogicalOr),
+
+            Ternary(guard, then, els) => {
+                let (guard, then, els) = (guard.simplify(), then.simplify(), els.simplify());
+                match (value(&guard), value(&then), value(&els)) {
+                    (Some(g), Some(_), Some(_)) => {
+                        if g != 0 {
+                            then
+                        } else {
+                            els
+                        }
+                    }
+                    _ => Ternary(Box::new(guard), Box::new(then), Box::new(els)),
+                }
+            }
+
+            Assign(var, e) => Assign(var, Box::new(e.simplify())),
+
+            Sequence(exprs) => {
+                let exprs: Vec<_> = exprs.into_iter().map(Arithmetic::simplify).collect();
+                if exprs.iter().all(|e| value(e).is_some()) {
+                    exprs
+                        .into_iter()
+                        .last()
+                        .unwrap_or_else(|| Sequence(Vec::new()))
+                } else {
+                    Sequence(exprs)
+                }
+            }
+        }
+    }
+}
+
+/// Renders an arithmetic expression as text which can be placed within
+/// `$(( ))` or `(( ))`, adding only the parentheses needed for the output
+/// to re-parse into an equal expression.
+impl<T: fmt::Display> fmt::Display for Arithmetic<T> {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        use self::Arithmetic::*;
+
+        fn operand<T: fmt::Display>(
+            fmt: &mut fmt::Formatter<'_>,
+            expr: &Arithmetic<T>,
+            min_precedence: u8,
+        ) -> fmt::Result {
+            if expr.precedence() < min_precedence {
+                write!(fmt, "({})", expr)
+            } else {
+                write!(fmt, "{}", expr)
+            }
+        }
+
+        let prec = self.precedence();
+        let (l, op, r) = match *self {
+            Var(ref v) => return write!(fmt, "{}", v),
+            Literal(n) => return write!(fmt, "{}", n),
+            PostIncr(ref v) => return write!(fmt, "{}++", v),
+            PostDecr(ref v) => return write!(fmt, "{}--", v),
+            PreIncr(ref v) => return write!(fmt, "++{}", v),
+            PreDecr(ref v) => return write!(fmt, "--{}", v),
+
+            // Keep `- -x` or `+ +x` from being read back as a decrement or increment
+            UnaryPlus(ref e) => {
+                let sep = match **e {
+                    UnaryPlus(_) | PreIncr(_) => " ",
+                    _ => "",
+                };
+                fmt.write_str("+")?;
+                fmt.write_str(sep)?;
+                return operand(fmt, e, prec);
+            }
+            UnaryMinus(ref e) => {
+                let sep = match **e {
+                    UnaryMinus(_) | PreDecr(_) => " ",
+                    Literal(n) if n < 0 => " ",
+                    _ => "",
+                };
+                fmt.write_str("-")?;
+                fmt.write_str(sep)?;
+                return operand(fmt, e, prec);
+            }
+            LogicalNot(ref e) => {
+                fmt.write_str("!")?;
+                return operand(fmt, e, prec);
+            }
+            BitwiseNot(ref e) => {
+                fmt.write_str("~")?;
+                return operand(fmt, e, prec);
+            }
+
+            // Right associative, with a unary operand on the left
+            Pow(ref l, ref r) => {
+                operand(fmt, l, prec + 1)?;
+                fmt.write_str(" ** ")?;
+                return operand(fmt, r, prec);
+            }
+
+            Mult(ref l, ref r) => (l, "*", r),
+            Div(ref l, ref r) => (l, "/", r),
+            Modulo(ref l, ref r) => (l, "%", r),
+            Add(ref l, ref r) => (l, "+", r),
+            Sub(ref l, ref r) => (l, "-", r),
+            ShiftLeft(ref l, ref r) => (l, "<<", r),
+            ShiftRight(ref l, ref r) => (l, ">>", r),
+            Less(ref l, ref r) => (l, "<", r),
+            LessEq(ref l, ref r) => (l, "<=", r),
+            Great(ref l, ref r) => (l, ">", r),
+            GreatEq(ref l, ref r) => (l, ">=", r),
+            Eq(ref l, ref r) => (l, "==", r),
+            NotEq(ref l, ref r) => (l, "!=", r),
+            BitwiseAnd(ref l, ref r) => (l, "&", r),
+            BitwiseXor(ref l, ref r) => (l, "^", r),
+            BitwiseOr(ref l, ref r) => (l, "|", r),
+            LogicalAnd(ref l, ref r) => (l, "&&", r),
+            LogicalOr(ref l, ref r) => (l, "||", r),
+
+            Ternary(ref guard, ref then, ref els) => {
+                operand(fmt, guard, prec + 1)?;
+                fmt.write_str(" ? ")?;
+                operand(fmt, then, prec)?;
+                fmt.write_str(" : ")?;
+                return operand(fmt, els, prec);
+            }
+
+            Assign(ref var, ref e) => {
+                write!(fmt, "{} = ", var)?;
+                return operand(fmt, e, prec);
+            }
+
+            Sequence(ref exprs) => {
+                for (i, e) in exprs.iter().enumerate() {
+                    if i > 0 {
+                        fmt.write_str(", ")?;
+                    }
+                    operand(fmt, e, prec + 1)?;
+                }
+                return Ok(());
+            }
+        };
+
+        // Left associative: the right operand must bind strictly tighter
+        operand(fmt, l, prec)?;
+        write!(fmt, " {} ", op)?;
+        operand(fmt, r, prec + 1)
+    }
+}
+
 #[cfg(test)]
 mod tests {
+    #[test]
+    fn test_display_bare_parameter() {
+        use super::Parameter::*;
+
+        let params = vec![
+            (At, "@"),
+            (Star, "*"),
+            (Pound, "#"),
+            (Question, "?"),
+            (Dash, "-"),
+            (Dollar, "$"),
+            (Bang, "!"),
+            (Positional(0), "0"),
+            (Positional(12), "12"),
+            (Var(String::from("foo_bar123")), "foo_bar123"),
+        ];
+
+        for (p, correct) in params {
+            assert_eq!(correct, p.bare().to_string());
+        }
+    }
+
     #[test]
     fn test_display_parameter() {
         use super::ComplexWord::Single;
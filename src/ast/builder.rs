@@ -9,13 +9,21 @@
 //! the `Builder` trait for your AST. Otherwise you can provide the `DefaultBuilder`
 //! struct to the parser if you wish to use the default AST implementation.
 
-use crate::ast::{AndOr, DefaultArithmetic, DefaultParameter, RedirectOrCmdWord, RedirectOrEnvVar};
+use crate::ast::{
+    AndOr, CaseArmTerminator, DefaultArithmetic, DefaultParameter, RedirectOrCmdWord,
+    RedirectOrEnvVar,
+};
 
+#[macro_use]
 mod default_builder;
 mod empty_builder;
+mod syntax_only_builder;
 
 pub use self::default_builder::*;
 pub use self::empty_builder::EmptyBuilder;
+pub use self::syntax_only_builder::{
+    ElidedArithmetic, ElidedWord, SyntaxOnlyBuilder, SyntaxOnlyCommand,
+};
 
 /// An indicator to the builder of how complete commands are separated.
 #[derive(Debug, PartialEq, Eq, Clone, Copy)]
@@ -42,6 +50,38 @@ pub enum LoopKind {
     Until,
 }
 
+/// An indicator to the builder of how a function declaration was spelled.
+#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+pub enum FunctionDeclarationKind {
+    /// The name was followed by parentheses, without the `function` reserved
+    /// word, e.g. `foo() { ... }`.
+    Parens,
+    /// The `function` reserved word was used, without any parentheses,
+    /// e.g. `function foo { ... }`.
+    Keyword,
+    /// Both the `function` reserved word and parentheses were used,
+    /// e.g. `function foo() { ... }`.
+    KeywordAndParens,
+}
+
+impl FunctionDeclarationKind {
+    /// Indicates if the `function` reserved word was present.
+    pub fn has_keyword(&self) -> bool {
+        match *self {
+            FunctionDeclarationKind::Parens => false,
+            FunctionDeclarationKind::Keyword | FunctionDeclarationKind::KeywordAndParens => true,
+        }
+    }
+
+    /// Indicates if parentheses were present after the function name.
+    pub fn has_parens(&self) -> bool {
+        match *self {
+            FunctionDeclarationKind::Keyword => false,
+            FunctionDeclarationKind::Parens | FunctionDeclarationKind::KeywordAndParens => true,
+        }
+    }
+}
+
 /// A grouping of a list of commands and any comments trailing after the commands.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CommandGroup<C> {
@@ -87,6 +127,22 @@ pub struct ForFragments<W, C> {
     pub body: CommandGroup<C>,
 }
 
+/// Parsed fragments relating to an arithmetic `for` command, e.g. `for ((i=0; i<5; i++))`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ArithForFragments<A, C> {
+    /// The expression evaluated once before the loop starts, if any.
+    pub init: Option<A>,
+    /// The expression checked before each iteration, if any.
+    /// An absent condition is always considered true.
+    pub cond: Option<A>,
+    /// The expression evaluated after each iteration, if any.
+    pub update: Option<A>,
+    /// Any comments that appear after the arithmetic expressions but before the body of commands.
+    pub pre_body_comments: Vec<Newline>,
+    /// The body to be invoked for every iteration.
+    pub body: CommandGroup<C>,
+}
+
 /// Parsed fragments relating to a shell `case` command.
 #[derive(Debug, PartialEq, Eq, Clone)]
 pub struct CaseFragments<W, C> {
@@ -113,6 +169,8 @@ pub struct CaseArm<W, C> {
     pub patterns: CasePatternFragments<W>,
     /// The body of commands to run if any pattern matches.
     pub body: CommandGroup<C>,
+    /// How the arm was terminated, e.g. with `;;`.
+    pub terminator: CaseArmTerminator,
     /// A comment appearing at the end of the arm declaration,
     /// i.e. after `;;` but on the same line.
     pub arm_comment: Option<Newline>,
@@ -131,34 +189,36 @@ pub struct CasePatternFragments<W> {
 
 /// An indicator to the builder what kind of complex word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum ComplexWordKind<C> {
+pub enum ComplexWordKind<C, A = DefaultArithmetic> {
     /// Several distinct words concatenated together.
-    Concat(Vec<WordKind<C>>),
+    Concat(Vec<WordKind<C, A>>),
     /// A regular word.
-    Single(WordKind<C>),
+    Single(WordKind<C, A>),
 }
 
 /// An indicator to the builder what kind of word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum WordKind<C> {
+pub enum WordKind<C, A = DefaultArithmetic> {
     /// A regular word.
-    Simple(SimpleWordKind<C>),
+    Simple(SimpleWordKind<C, A>),
     /// List of words concatenated within double quotes.
-    DoubleQuoted(Vec<SimpleWordKind<C>>),
+    DoubleQuoted(Vec<SimpleWordKind<C, A>>),
     /// List of words concatenated within single quotes. Virtually
     /// identical as a literal, but makes a distinction between the two.
     SingleQuoted(String),
+    /// A bracket expression, e.g. `[!0-9]`. The boolean indicates a negated set.
+    BracketClass(bool, String),
 }
 
 /// An indicator to the builder what kind of simple word was parsed.
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum SimpleWordKind<C> {
+pub enum SimpleWordKind<C, A = DefaultArithmetic> {
     /// A non-special literal word.
     Literal(String),
     /// Access of a value inside a parameter, e.g. `$foo` or `$$`.
     Param(DefaultParameter),
     /// A parameter substitution, e.g. `${param-word}`.
-    Subst(Box<ParameterSubstitutionKind<ComplexWordKind<C>, C>>),
+    Subst(Box<ParameterSubstitutionKind<ComplexWordKind<C, A>, C, A>>),
     /// Represents the standard output of some command, e.g. \`echo foo\`.
     CommandSubst(CommandGroup<C>),
     /// A token which normally has a special meaning is treated as a literal
@@ -197,17 +257,30 @@ pub enum RedirectKind<W> {
     DupRead(Option<u16>, W),
     /// Duplicate a file descriptor for writing, e.g. `[n]>& [n|-]`.
     DupWrite(Option<u16>, W),
+    /// Duplicate a file descriptor for reading, closing the original, e.g. `[n]<&n-`.
+    MoveRead(Option<u16>, W),
+    /// Duplicate a file descriptor for writing, closing the original, e.g. `[n]>&n-`.
+    MoveWrite(Option<u16>, W),
+    /// Open a file for writing after truncating, and redirect both standard output
+    /// and standard error to it, e.g. `&> file`.
+    WriteBoth(W),
+    /// Open a file for writing, appending to the end, and redirect both standard
+    /// output and standard error to it, e.g. `&>> file`.
+    AppendBoth(W),
+    /// Open a file for writing regardless of the `noclobber` shell option, and
+    /// redirect both standard output and standard error to it, e.g. `&>| file`.
+    ClobberBoth(W),
 }
 
 /// Represents the type of parameter that was parsed
 #[derive(Debug, PartialEq, Eq, Clone)]
-pub enum ParameterSubstitutionKind<W, C> {
+pub enum ParameterSubstitutionKind<W, C, A = DefaultArithmetic> {
     /// Returns the standard output of running a command, e.g. `$(cmd)`
     Command(CommandGroup<C>),
     /// Returns the length of the value of a parameter, e.g. ${#param}
     Len(DefaultParameter),
     /// Returns the resulting value of an arithmetic subsitution, e.g. `$(( x++ ))`
-    Arith(Option<DefaultArithmetic>),
+    Arith(Option<A>),
     /// Use a provided value if the parameter is null or unset, e.g.
     /// `${param:-[word]}`.
     /// The boolean indicates the presence of a `:`, and that if the parameter has
@@ -236,6 +309,13 @@ pub enum ParameterSubstitutionKind<W, C> {
     RemoveSmallestPrefix(DefaultParameter, Option<W>),
     /// Remove largest prefix pattern, e.g. `${param##pattern}`
     RemoveLargestPrefix(DefaultParameter, Option<W>),
+    /// Returns the names of variables beginning with a prefix, e.g. `${!prefix*}`
+    /// or `${!prefix@}`. The boolean indicates the presence of a `@`.
+    NamesMatchingPrefix(String, bool),
+    /// Returns the transformed value of a parameter, e.g. `${param@Q}`
+    Transform(DefaultParameter, char),
+    /// Returns the contents of a file, e.g. `$(< file)`
+    ReadFile(W),
 }
 
 /// Represents a parsed newline, more specifically, the presense of a comment
@@ -266,6 +346,8 @@ pub trait Builder {
     type Word;
     /// The type which represents a file descriptor redirection.
     type Redirect;
+    /// The type which represents an arithmetic expression.
+    type Arithmetic;
     /// A type for returning custom parse/build errors.
     type Error;
 
@@ -304,9 +386,9 @@ pub trait Builder {
     ///
     /// # Arguments
     /// * bang: the presence of a `!` at the start of the pipeline, typically indicating
-    /// that the pipeline's exit status should be logically inverted.
+    ///   that the pipeline's exit status should be logically inverted.
     /// * cmds: a collection of tuples which are any comments appearing after a pipe token, followed
-    /// by the command itself, all in the order they were parsed
+    ///   by the command itself, all in the order they were parsed
     fn pipeline(
         &mut self,
         bang: bool,
@@ -317,11 +399,12 @@ pub trait Builder {
     ///
     /// # Arguments
     /// * redirects_or_env_vars: redirections or environment variables that occur before any command
-    /// * redirects_or_cmd_words: redirections or any command or argument
+    /// * redirects_or_cmd_words: redirections, any command or argument, or assignments
+    ///   passed as arguments to an assignment builtin (e.g. `export`)
     fn simple_command(
         &mut self,
         redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>,
     ) -> Result<Self::PipeableCommand, Self::Error>;
 
     /// Invoked when a non-zero number of commands were parsed between balanced curly braces.
@@ -392,6 +475,20 @@ pub trait Builder {
         redirects: Vec<Self::Redirect>,
     ) -> Result<Self::CompoundCommand, Self::Error>;
 
+    /// Invoked when an arithmetic `for` command is parsed, e.g. `for ((i=0; i<5; i++))`.
+    /// Typically an arithmetic `for` command evaluates its initializer once, and invokes
+    /// its body for as long as its condition evaluates to a non-zero value, evaluating
+    /// its update expression after every iteration.
+    ///
+    /// # Arguments
+    /// * fragments: parsed fragments relating to an arithmetic `for` command.
+    /// * redirects: any redirects to be applied over **all** commands within the `for` command
+    fn arith_for_command(
+        &mut self,
+        fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error>;
+
     /// Invoked when a `case` command is parsed.
     /// Typically this command will execute certain commands when a given word matches a pattern.
     ///
@@ -419,16 +516,29 @@ pub trait Builder {
     /// within the current environment.
     ///
     /// # Arguments
+    /// * kind: indicates if the `function` reserved word and/or parentheses were present
     /// * name: the name of the function to be created
     /// * post_name_comments: any comments appearing after the function name but before the body
     /// * body: commands to be run when the function is invoked
     fn function_declaration(
         &mut self,
+        kind: FunctionDeclarationKind,
         name: String,
         post_name_comments: Vec<Newline>,
         body: Self::CompoundCommand,
     ) -> Result<Self::PipeableCommand, Self::Error>;
 
+    /// Invoked when a `coproc` command is parsed.
+    ///
+    /// # Arguments
+    /// * name: the name of the coprocess, only present if the body is a compound command
+    /// * body: the simple or compound command to be run as a coprocess
+    fn coproc(
+        &mut self,
+        name: Option<String>,
+        body: Self::PipeableCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error>;
+
     /// Invoked when only comments are parsed with no commands following.
     /// This can occur if an entire shell script is commented out or if there
     /// are comments present at the end of the script.
@@ -441,7 +551,16 @@ pub trait Builder {
     ///
     /// # Arguments
     /// * kind: the type of word that was parsed
-    fn word(&mut self, kind: ComplexWordKind<Self::Command>) -> Result<Self::Word, Self::Error>;
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command, Self::Arithmetic>,
+    ) -> Result<Self::Word, Self::Error>;
+
+    /// Invoked when the body of an arithmetic substitution, e.g. `$(( x + 1 ))`, is parsed.
+    ///
+    /// # Arguments
+    /// * expr: the parsed arithmetic expression
+    fn arithmetic(&mut self, expr: DefaultArithmetic) -> Result<Self::Arithmetic, Self::Error>;
 
     /// Invoked when a redirect is parsed.
     ///
@@ -459,6 +578,7 @@ macro_rules! impl_builder_body {
         type CompoundCommand = $T::CompoundCommand;
         type Word = $T::Word;
         type Redirect = $T::Redirect;
+        type Arithmetic = $T::Arithmetic;
         type Error = $T::Error;
 
         fn complete_command(
@@ -490,7 +610,7 @@ macro_rules! impl_builder_body {
         fn simple_command(
             &mut self,
             redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-            redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+            redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>,
         ) -> Result<Self::PipeableCommand, Self::Error> {
             (**self).simple_command(redirects_or_env_vars, redirects_or_cmd_words)
         }
@@ -536,6 +656,14 @@ macro_rules! impl_builder_body {
             (**self).for_command(fragments, redirects)
         }
 
+        fn arith_for_command(
+            &mut self,
+            fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+            redirects: Vec<Self::Redirect>,
+        ) -> Result<Self::CompoundCommand, Self::Error> {
+            (**self).arith_for_command(fragments, redirects)
+        }
+
         fn case_command(
             &mut self,
             fragments: CaseFragments<Self::Word, Self::Command>,
@@ -553,11 +681,20 @@ macro_rules! impl_builder_body {
 
         fn function_declaration(
             &mut self,
+            kind: FunctionDeclarationKind,
             name: String,
             post_name_comments: Vec<Newline>,
             body: Self::CompoundCommand,
         ) -> Result<Self::PipeableCommand, Self::Error> {
-            (**self).function_declaration(name, post_name_comments, body)
+            (**self).function_declaration(kind, name, post_name_comments, body)
+        }
+
+        fn coproc(
+            &mut self,
+            name: Option<String>,
+            body: Self::PipeableCommand,
+        ) -> Result<Self::PipeableCommand, Self::Error> {
+            (**self).coproc(name, body)
         }
 
         fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
@@ -566,11 +703,15 @@ macro_rules! impl_builder_body {
 
         fn word(
             &mut self,
-            kind: ComplexWordKind<Self::Command>,
+            kind: ComplexWordKind<Self::Command, Self::Arithmetic>,
         ) -> Result<Self::Word, Self::Error> {
             (**self).word(kind)
         }
 
+        fn arithmetic(&mut self, expr: DefaultArithmetic) -> Result<Self::Arithmetic, Self::Error> {
+            (**self).arithmetic(expr)
+        }
+
         fn redirect(
             &mut self,
             kind: RedirectKind<Self::Word>,
@@ -580,7 +721,7 @@ macro_rules! impl_builder_body {
     };
 }
 
-impl<'a, T: Builder + ?Sized> Builder for &'a mut T {
+impl<T: Builder + ?Sized> Builder for &mut T {
     impl_builder_body!(T);
 }
 
@@ -18,17 +18,56 @@ use void::Void;
 /// builder type, however, rustdoc will only generate docs for the alias
 /// definition is, and the docs for the inner builder will be rendered in
 /// their entire complexity.
+///
+/// The `word` and `arithmetic` methods are delegated as well, unless a block
+/// with replacement definitions for both is given at the end.
 // FIXME: might be good to revisit this complexity/indirection
 macro_rules! default_builder {
     ($(#[$attr:meta])*
      pub struct $Builder:ident,
      $CoreBuilder:ident,
-     $Word:ident,
-     $Cmd:ident,
+     $Word:ty,
+     $Cmd:ty,
+     $PipeableCmd:ident,
+     $Arith:ty,
+    ) => {
+        default_builder! {
+            $(#[$attr])*
+            pub struct $Builder,
+            $CoreBuilder,
+            $Word,
+            $Cmd,
+            $PipeableCmd,
+            $Arith,
+            {
+                fn word(&mut self,
+                        kind: ComplexWordKind<Self::Command, Self::Arithmetic>)
+                    -> Result<Self::Word, Self::Error>
+                {
+                    self.0.word(kind)
+                }
+
+                fn arithmetic(&mut self,
+                              expr: DefaultArithmetic)
+                    -> Result<Self::Arithmetic, Self::Error>
+                {
+                    self.0.arithmetic(expr)
+                }
+            }
+        }
+    };
+
+    ($(#[$attr:meta])*
+     pub struct $Builder:ident,
+     $CoreBuilder:ident,
+     $Word:ty,
+     $Cmd:ty,
      $PipeableCmd:ident,
+     $Arith:ty,
+     { $($word_and_arithmetic:tt)* }
     ) => {
         $(#[$attr])*
-        pub struct $Builder<T>($CoreBuilder<T, $Word<T>, $Cmd<T>>);
+        pub struct $Builder<T>($CoreBuilder<T, $Word, $Cmd, $Arith>);
 
         impl<T> $Builder<T> {
             /// Constructs a builder.
@@ -59,13 +98,14 @@ macro_rules! default_builder {
         impl<T> Copy for $Builder<T> {}
 
         impl<T: From<String>> Builder for $Builder<T> {
-            type Command         = $Cmd<T>;
+            type Command         = $Cmd;
             type CommandList     = AndOrList<Self::ListableCommand>;
             type ListableCommand = ListableCommand<Self::PipeableCommand>;
-            type PipeableCommand = $PipeableCmd<T, Self::Word, Self::Command>;
-            type CompoundCommand = ShellCompoundCommand<T, Self::Word, Self::Command>;
-            type Word            = $Word<T>;
+            type PipeableCommand = $PipeableCmd<T, Self::Word, Self::Command, Self::Arithmetic>;
+            type CompoundCommand = ShellCompoundCommand<T, Self::Word, Self::Command, Self::Arithmetic>;
+            type Word            = $Word;
             type Redirect        = Redirect<Self::Word>;
+            type Arithmetic      = $Arith;
             type Error           = Void;
 
             fn complete_command(&mut self,
@@ -97,7 +137,7 @@ macro_rules! default_builder {
             fn simple_command(
                 &mut self,
                 redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-                redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>
+                redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>
             ) -> Result<Self::PipeableCommand, Self::Error>
             {
                 self.0.simple_command(redirects_or_env_vars, redirects_or_cmd_words)
@@ -144,6 +184,14 @@ macro_rules! default_builder {
                 self.0.for_command(fragments, redirects)
             }
 
+            fn arith_for_command(&mut self,
+                                 fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+                                 redirects: Vec<Self::Redirect>)
+                -> Result<Self::CompoundCommand, Self::Error>
+            {
+                self.0.arith_for_command(fragments, redirects)
+            }
+
             fn case_command(&mut self,
                             fragments: CaseFragments<Self::Word, Self::Command>,
                             redirects: Vec<Self::Redirect>)
@@ -160,12 +208,21 @@ macro_rules! default_builder {
             }
 
             fn function_declaration(&mut self,
+                                    kind: FunctionDeclarationKind,
                                     name: String,
                                     post_name_comments: Vec<Newline>,
                                     body: Self::CompoundCommand)
                 -> Result<Self::PipeableCommand, Self::Error>
             {
-                self.0.function_declaration(name, post_name_comments, body)
+                self.0.function_declaration(kind, name, post_name_comments, body)
+            }
+
+            fn coproc(&mut self,
+                      name: Option<String>,
+                      body: Self::PipeableCommand)
+                -> Result<Self::PipeableCommand, Self::Error>
+            {
+                self.0.coproc(name, body)
             }
 
             fn comments(&mut self,
@@ -175,12 +232,7 @@ macro_rules! default_builder {
                 self.0.comments(comments)
             }
 
-            fn word(&mut self,
-                    kind: ComplexWordKind<Self::Command>)
-                -> Result<Self::Word, Self::Error>
-            {
-                self.0.word(kind)
-            }
+            $($word_and_arithmetic)*
 
             fn redirect(&mut self,
                         kind: RedirectKind<Self::Word>)
@@ -192,17 +244,62 @@ macro_rules! default_builder {
     };
 }
 
-type RcCoreBuilder<T, W, C> = CoreBuilder<T, W, C, Rc<ShellCompoundCommand<T, W, C>>>;
-type ArcCoreBuilder<T, W, C> = CoreBuilder<T, W, C, Arc<ShellCompoundCommand<T, W, C>>>;
+/// Converts an arithmetic expression to use the desired representation of variables.
+fn map_arith<T: From<String>>(kind: DefaultArithmetic) -> Arithmetic<T> {
+    use crate::ast::Arithmetic::*;
+    match kind {
+        Var(v) => Var(v.into()),
+        Literal(l) => Literal(l),
+        Pow(a, b) => Pow(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        PostIncr(p) => PostIncr(p.into()),
+        PostDecr(p) => PostDecr(p.into()),
+        PreIncr(p) => PreIncr(p.into()),
+        PreDecr(p) => PreDecr(p.into()),
+        UnaryPlus(a) => UnaryPlus(Box::new(map_arith(*a))),
+        UnaryMinus(a) => UnaryMinus(Box::new(map_arith(*a))),
+        LogicalNot(a) => LogicalNot(Box::new(map_arith(*a))),
+        BitwiseNot(a) => BitwiseNot(Box::new(map_arith(*a))),
+        Mult(a, b) => Mult(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Div(a, b) => Div(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Modulo(a, b) => Modulo(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Add(a, b) => Add(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Sub(a, b) => Sub(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        ShiftLeft(a, b) => ShiftLeft(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        ShiftRight(a, b) => ShiftRight(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Less(a, b) => Less(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LessEq(a, b) => LessEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Great(a, b) => Great(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        GreatEq(a, b) => GreatEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Eq(a, b) => Eq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        NotEq(a, b) => NotEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseAnd(a, b) => BitwiseAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseXor(a, b) => BitwiseXor(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        BitwiseOr(a, b) => BitwiseOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LogicalAnd(a, b) => LogicalAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        LogicalOr(a, b) => LogicalOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
+        Ternary(a, b, c) => Ternary(
+            Box::new(map_arith(*a)),
+            Box::new(map_arith(*b)),
+            Box::new(map_arith(*c)),
+        ),
+        Assign(v, a) => Assign(v.into(), Box::new(map_arith(*a))),
+        Sequence(ariths) => Sequence(ariths.into_iter().map(map_arith).collect()),
+    }
+}
+
+pub(super) type RcCoreBuilder<T, W, C, A> =
+    CoreBuilder<T, W, C, Rc<ShellCompoundCommand<T, W, C, A>>, A>;
+type ArcCoreBuilder<T, W, C, A> = CoreBuilder<T, W, C, Arc<ShellCompoundCommand<T, W, C, A>>, A>;
 
 default_builder! {
     /// A `Builder` implementation which builds shell commands
     /// using the (non-atomic) AST definitions in the `ast` module.
     pub struct DefaultBuilder,
     RcCoreBuilder,
-    TopLevelWord,
-    TopLevelCommand,
+    TopLevelWord<T>,
+    TopLevelCommand<T>,
     ShellPipeableCommand,
+    Arithmetic<T>,
 }
 
 default_builder! {
@@ -210,9 +307,10 @@ default_builder! {
     /// using the (atomic) AST definitions in the `ast` module.
     pub struct AtomicDefaultBuilder,
     ArcCoreBuilder,
-    AtomicTopLevelWord,
-    AtomicTopLevelCommand,
+    AtomicTopLevelWord<T>,
+    AtomicTopLevelCommand<T>,
     AtomicShellPipeableCommand,
+    Arithmetic<T>,
 }
 
 /// A `DefaultBuilder` implementation which uses regular `String`s when
@@ -228,32 +326,33 @@ pub type RcBuilder = DefaultBuilder<Rc<String>>;
 pub type ArcBuilder = AtomicDefaultBuilder<Arc<String>>;
 
 /// The actual provided `Builder` implementation.
-/// The various type parameters are used to swap out atomic/non-atomic AST versions.
-pub struct CoreBuilder<T, W, C, F> {
-    phantom_data: PhantomData<(T, W, C, F)>,
+/// The various type parameters are used to swap out atomic/non-atomic AST versions,
+/// as well as the representation of arithmetic expressions.
+pub struct CoreBuilder<T, W, C, F, A = Arithmetic<T>> {
+    phantom_data: PhantomData<(T, W, C, F, A)>,
 }
 
-impl<T, W, C, F> fmt::Debug for CoreBuilder<T, W, C, F> {
+impl<T, W, C, F, A> fmt::Debug for CoreBuilder<T, W, C, F, A> {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
         fmt.debug_struct("CoreBuilder").finish()
     }
 }
 
-impl<T, W, C, F> Clone for CoreBuilder<T, W, C, F> {
+impl<T, W, C, F, A> Clone for CoreBuilder<T, W, C, F, A> {
     fn clone(&self) -> Self {
         *self
     }
 }
 
-impl<T, W, C, F> Copy for CoreBuilder<T, W, C, F> {}
+impl<T, W, C, F, A> Copy for CoreBuilder<T, W, C, F, A> {}
 
-impl<T, W, C, F> Default for CoreBuilder<T, W, C, F> {
+impl<T, W, C, F, A> Default for CoreBuilder<T, W, C, F, A> {
     fn default() -> Self {
         Self::new()
     }
 }
 
-impl<T, W, C, F> CoreBuilder<T, W, C, F> {
+impl<T, W, C, F, A> CoreBuilder<T, W, C, F, A> {
     /// Constructs a builder.
     pub fn new() -> Self {
         CoreBuilder {
@@ -262,27 +361,29 @@ impl<T, W, C, F> CoreBuilder<T, W, C, F> {
     }
 }
 
-type BuilderPipeableCommand<T, W, C, F> = PipeableCommand<
+type BuilderPipeableCommand<T, W, C, F, A> = PipeableCommand<
     T,
     Box<SimpleCommand<T, W, Redirect<W>>>,
-    Box<ShellCompoundCommand<T, W, C>>,
+    Box<ShellCompoundCommand<T, W, C, A>>,
     F,
 >;
 
-impl<T, W, C, F> Builder for CoreBuilder<T, W, C, F>
+impl<T, W, C, F, A> Builder for CoreBuilder<T, W, C, F, A>
 where
     T: From<String>,
-    W: From<ShellWord<T, W, C>>,
-    C: From<Command<AndOrList<ListableCommand<BuilderPipeableCommand<T, W, C, F>>>>>,
-    F: From<ShellCompoundCommand<T, W, C>>,
+    W: From<ShellWord<T, W, C, A>>,
+    C: From<Command<AndOrList<ListableCommand<BuilderPipeableCommand<T, W, C, F, A>>>>>,
+    F: From<ShellCompoundCommand<T, W, C, A>>,
+    A: From<Arithmetic<T>>,
 {
     type Command = C;
     type CommandList = AndOrList<Self::ListableCommand>;
     type ListableCommand = ListableCommand<Self::PipeableCommand>;
-    type PipeableCommand = BuilderPipeableCommand<T, W, C, F>;
-    type CompoundCommand = ShellCompoundCommand<T, Self::Word, Self::Command>;
+    type PipeableCommand = BuilderPipeableCommand<T, W, C, F, A>;
+    type CompoundCommand = ShellCompoundCommand<T, Self::Word, Self::Command, A>;
     type Word = W;
     type Redirect = Redirect<Self::Word>;
+    type Arithmetic = A;
     type Error = Void;
 
     /// Constructs a `Command::Job` node with the provided inputs if the command
@@ -323,7 +424,7 @@ where
         bang: bool,
         cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>,
     ) -> Result<Self::ListableCommand, Self::Error> {
-        debug_assert_eq!(cmds.is_empty(), false);
+        debug_assert!(!cmds.is_empty());
         let mut cmds: Vec<_> = cmds.into_iter().map(|(_, c)| c).collect();
 
         // Pipe is the only AST node which allows for a status
@@ -341,7 +442,7 @@ where
     fn simple_command(
         &mut self,
         redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-        mut redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>,
     ) -> Result<Self::PipeableCommand, Self::Error> {
         let redirects_or_env_vars = redirects_or_env_vars
             .into_iter()
@@ -351,6 +452,17 @@ where
             })
             .collect();
 
+        let mut redirects_or_cmd_words: Vec<_> = redirects_or_cmd_words
+            .into_iter()
+            .map(|rocw| match rocw {
+                RedirectOrCmdWord::Redirect(red) => RedirectOrCmdWord::Redirect(red),
+                RedirectOrCmdWord::CmdWord(w) => RedirectOrCmdWord::CmdWord(w),
+                RedirectOrCmdWord::Assignment(k, v) => RedirectOrCmdWord::Assignment(k.into(), v),
+                RedirectOrCmdWord::Declaration(k) => RedirectOrCmdWord::Declaration(k.into()),
+                RedirectOrCmdWord::Attributes(attrs) => RedirectOrCmdWord::Attributes(attrs),
+            })
+            .collect();
+
         redirects_or_cmd_words.shrink_to_fit();
 
         Ok(PipeableCommand::Simple(Box::new(SimpleCommand {
@@ -485,6 +597,27 @@ where
         })
     }
 
+    /// Constructs a `CompoundCommand::ArithFor` node with the provided inputs.
+    fn arith_for_command(
+        &mut self,
+        fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+        mut redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        let mut body = fragments.body.commands;
+        body.shrink_to_fit();
+        redirects.shrink_to_fit();
+
+        Ok(CompoundCommand {
+            kind: CompoundCommandKind::ArithFor {
+                init: fragments.init,
+                cond: fragments.cond,
+                update: fragments.update,
+                body,
+            },
+            io: redirects,
+        })
+    }
+
     /// Constructs a `CompoundCommand::Case` node with the provided inputs.
     fn case_command(
         &mut self,
@@ -501,7 +634,11 @@ where
                 let mut body = arm.body.commands;
                 body.shrink_to_fit();
 
-                PatternBodyPair { patterns, body }
+                PatternBodyPair {
+                    patterns,
+                    body,
+                    terminator: arm.terminator,
+                }
             })
             .collect();
 
@@ -526,6 +663,7 @@ where
     /// Constructs a `Command::FunctionDef` node with the provided inputs.
     fn function_declaration(
         &mut self,
+        _kind: FunctionDeclarationKind,
         name: String,
         _post_name_comments: Vec<Newline>,
         body: Self::CompoundCommand,
@@ -533,13 +671,28 @@ where
         Ok(PipeableCommand::FunctionDef(name.into(), body.into()))
     }
 
+    /// Constructs a `Command::Coproc` node with the provided inputs.
+    fn coproc(
+        &mut self,
+        name: Option<String>,
+        body: Self::PipeableCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        Ok(PipeableCommand::Coproc {
+            name: name.map(Into::into),
+            body: Box::new(body),
+        })
+    }
+
     /// Ignored by the builder.
     fn comments(&mut self, _comments: Vec<Newline>) -> Result<(), Self::Error> {
         Ok(())
     }
 
     /// Constructs a `ast::Word` from the provided input.
-    fn word(&mut self, kind: ComplexWordKind<Self::Command>) -> Result<Self::Word, Self::Error> {
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command, Self::Arithmetic>,
+    ) -> Result<Self::Word, Self::Error> {
         macro_rules! map {
             ($pat:expr) => {
                 match $pat {
@@ -549,48 +702,6 @@ where
             };
         }
 
-        fn map_arith<T: From<String>>(kind: DefaultArithmetic) -> Arithmetic<T> {
-            use crate::ast::Arithmetic::*;
-            match kind {
-                Var(v) => Var(v.into()),
-                Literal(l) => Literal(l),
-                Pow(a, b) => Pow(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                PostIncr(p) => PostIncr(p.into()),
-                PostDecr(p) => PostDecr(p.into()),
-                PreIncr(p) => PreIncr(p.into()),
-                PreDecr(p) => PreDecr(p.into()),
-                UnaryPlus(a) => UnaryPlus(Box::new(map_arith(*a))),
-                UnaryMinus(a) => UnaryMinus(Box::new(map_arith(*a))),
-                LogicalNot(a) => LogicalNot(Box::new(map_arith(*a))),
-                BitwiseNot(a) => BitwiseNot(Box::new(map_arith(*a))),
-                Mult(a, b) => Mult(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Div(a, b) => Div(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Modulo(a, b) => Modulo(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Add(a, b) => Add(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Sub(a, b) => Sub(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                ShiftLeft(a, b) => ShiftLeft(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                ShiftRight(a, b) => ShiftRight(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Less(a, b) => Less(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LessEq(a, b) => LessEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Great(a, b) => Great(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                GreatEq(a, b) => GreatEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Eq(a, b) => Eq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                NotEq(a, b) => NotEq(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseAnd(a, b) => BitwiseAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseXor(a, b) => BitwiseXor(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                BitwiseOr(a, b) => BitwiseOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LogicalAnd(a, b) => LogicalAnd(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                LogicalOr(a, b) => LogicalOr(Box::new(map_arith(*a)), Box::new(map_arith(*b))),
-                Ternary(a, b, c) => Ternary(
-                    Box::new(map_arith(*a)),
-                    Box::new(map_arith(*b)),
-                    Box::new(map_arith(*c)),
-                ),
-                Assign(v, a) => Assign(v.into(), Box::new(map_arith(*a))),
-                Sequence(ariths) => Sequence(ariths.into_iter().map(map_arith).collect()),
-            }
-        }
-
         let map_param = |kind: DefaultParameter| -> Parameter<T> {
             use crate::ast::Parameter::*;
             match kind {
@@ -631,7 +742,7 @@ where
                     let subst = match s {
                         Len(p) => ParameterSubstitution::Len(map_param(p)),
                         Command(c) => ParameterSubstitution::Command(c.commands),
-                        Arith(a) => ParameterSubstitution::Arith(a.map(map_arith)),
+                        Arith(a) => ParameterSubstitution::Arith(a),
                         Default(c, p, w) => {
                             ParameterSubstitution::Default(c, map_param(p), map!(w))
                         }
@@ -652,6 +763,11 @@ where
                         RemoveLargestPrefix(p, w) => {
                             ParameterSubstitution::RemoveLargestPrefix(map_param(p), map!(w))
                         }
+                        NamesMatchingPrefix(prefix, at) => {
+                            ParameterSubstitution::NamesMatchingPrefix(prefix, at)
+                        }
+                        Transform(p, op) => ParameterSubstitution::Transform(map_param(p), op),
+                        ReadFile(w) => ParameterSubstitution::ReadFile(self.word(w)?),
                     };
                     SimpleWord::Subst(Box::new(subst))
                 }
@@ -663,6 +779,7 @@ where
             let word = match kind {
                 WordKind::Simple(s) => Word::Simple(map_simple(s)?),
                 WordKind::SingleQuoted(s) => Word::SingleQuoted(s.into()),
+                WordKind::BracketClass(negated, s) => Word::BracketClass(negated, s.into()),
                 WordKind::DoubleQuoted(v) => Word::DoubleQuoted(
                     v.into_iter()
                         .map(&mut map_simple)
@@ -685,6 +802,11 @@ where
         Ok(word.into())
     }
 
+    /// Constructs a `ast::Arithmetic` from the provided expression.
+    fn arithmetic(&mut self, expr: DefaultArithmetic) -> Result<Self::Arithmetic, Self::Error> {
+        Ok(map_arith(expr).into())
+    }
+
     /// Constructs a `ast::Redirect` from the provided input.
     fn redirect(&mut self, kind: RedirectKind<Self::Word>) -> Result<Self::Redirect, Self::Error> {
         let io = match kind {
@@ -696,6 +818,11 @@ where
             RedirectKind::Heredoc(fd, body) => Redirect::Heredoc(fd, body),
             RedirectKind::DupRead(src, dst) => Redirect::DupRead(src, dst),
             RedirectKind::DupWrite(src, dst) => Redirect::DupWrite(src, dst),
+            RedirectKind::MoveRead(src, dst) => Redirect::MoveRead(src, dst),
+            RedirectKind::MoveWrite(src, dst) => Redirect::MoveWrite(src, dst),
+            RedirectKind::WriteBoth(path) => Redirect::WriteBoth(path),
+            RedirectKind::AppendBoth(path) => Redirect::AppendBoth(path),
+            RedirectKind::ClobberBoth(path) => Redirect::ClobberBoth(path),
         };
 
         Ok(io)
@@ -758,15 +885,15 @@ where
     }
 }
 
-fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
+fn compress<C, A>(word: ComplexWordKind<C, A>) -> ComplexWordKind<C, A> {
     use crate::ast::builder::ComplexWordKind::*;
     use crate::ast::builder::SimpleWordKind::*;
     use crate::ast::builder::WordKind::*;
 
-    fn coalesce_simple<C>(
-        a: SimpleWordKind<C>,
-        b: SimpleWordKind<C>,
-    ) -> CoalesceResult<SimpleWordKind<C>> {
+    fn coalesce_simple<C, A>(
+        a: SimpleWordKind<C, A>,
+        b: SimpleWordKind<C, A>,
+    ) -> CoalesceResult<SimpleWordKind<C, A>> {
         match (a, b) {
             (Literal(mut a), Literal(b)) => {
                 a.push_str(&b);
@@ -776,7 +903,7 @@ fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
         }
     }
 
-    fn coalesce_word<C>(a: WordKind<C>, b: WordKind<C>) -> CoalesceResult<WordKind<C>> {
+    fn coalesce_word<C, A>(a: WordKind<C, A>, b: WordKind<C, A>) -> CoalesceResult<WordKind<C, A>> {
         match (a, b) {
             (Simple(a), Simple(b)) => coalesce_simple(a, b)
                 .map(Simple)
@@ -795,11 +922,11 @@ fn compress<C>(word: ComplexWordKind<C>) -> ComplexWordKind<C> {
 
     match word {
         Single(s) => Single(match s {
-            s @ Simple(_) | s @ SingleQuoted(_) => s,
+            s @ Simple(_) | s @ SingleQuoted(_) | s @ BracketClass(..) => s,
             DoubleQuoted(v) => DoubleQuoted(Coalesce::new(v, coalesce_simple).collect()),
         }),
         Concat(v) => {
-            let mut body: Vec<_> = Coalesce::new(v.into_iter(), coalesce_word).collect();
+            let mut body: Vec<_> = Coalesce::new(v, coalesce_word).collect();
             if body.len() == 1 {
                 Single(body.pop().unwrap())
             } else {
@@ -30,6 +30,7 @@ impl Builder for EmptyBuilder {
     type CompoundCommand = ();
     type Word = ();
     type Redirect = ();
+    type Arithmetic = ();
     type Error = Void;
 
     fn complete_command(
@@ -61,7 +62,7 @@ impl Builder for EmptyBuilder {
     fn simple_command(
         &mut self,
         _redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
-        _redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, Self::Word>>,
+        _redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>,
     ) -> Result<Self::PipeableCommand, Self::Error> {
         Ok(())
     }
@@ -107,6 +108,14 @@ impl Builder for EmptyBuilder {
         Ok(())
     }
 
+    fn arith_for_command(
+        &mut self,
+        _fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+        _redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::Command, Self::Error> {
+        Ok(())
+    }
+
     fn case_command(
         &mut self,
         _fragments: CaseFragments<Self::Word, Self::Command>,
@@ -117,6 +126,7 @@ impl Builder for EmptyBuilder {
 
     fn function_declaration(
         &mut self,
+        _kind: FunctionDeclarationKind,
         _name: String,
         _post_name_comments: Vec<Newline>,
         _body: Self::CompoundCommand,
@@ -124,11 +134,26 @@ impl Builder for EmptyBuilder {
         Ok(())
     }
 
+    fn coproc(
+        &mut self,
+        _name: Option<String>,
+        _body: Self::PipeableCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        Ok(())
+    }
+
     fn comments(&mut self, _comments: Vec<Newline>) -> Result<(), Self::Error> {
         Ok(())
     }
 
-    fn word(&mut self, _kind: ComplexWordKind<Self::Command>) -> Result<Self::Word, Self::Error> {
+    fn word(
+        &mut self,
+        _kind: ComplexWordKind<Self::Command, Self::Arithmetic>,
+    ) -> Result<Self::Word, Self::Error> {
+        Ok(())
+    }
+
+    fn arithmetic(&mut self, _expr: DefaultArithmetic) -> Result<Self::Arithmetic, Self::Error> {
         Ok(())
     }
 
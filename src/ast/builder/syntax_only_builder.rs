@@ -0,0 +1,89 @@
+use crate::ast::builder::default_builder::RcCoreBuilder;
+use crate::ast::builder::*;
+use crate::ast::*;
+use std::fmt;
+use std::ops;
+use void::Void;
+
+/// A placeholder for a shell word whose contents have been elided.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ElidedWord;
+
+impl<T, C, A> From<ShellWord<T, ElidedWord, C, A>> for ElidedWord {
+    fn from(_inner: ShellWord<T, ElidedWord, C, A>) -> Self {
+        ElidedWord
+    }
+}
+
+/// A placeholder for an arithmetic expression whose contents have been elided.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone)]
+pub struct ElidedArithmetic;
+
+impl<T> From<Arithmetic<T>> for ElidedArithmetic {
+    fn from(_inner: Arithmetic<T>) -> Self {
+        ElidedArithmetic
+    }
+}
+
+/// The and/or list of a shell command whose words have been elided.
+type SyntaxOnlyCommandList<T> = CommandList<T, ElidedWord, SyntaxOnlyCommand<T>, ElidedArithmetic>;
+
+/// A top-level representation of a shell command whose words and arithmetic
+/// expressions have been elided. Uses `Rc` wrappers for function declarations.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct SyntaxOnlyCommand<T>(pub Command<SyntaxOnlyCommandList<T>>);
+
+impl<T> ops::Deref for SyntaxOnlyCommand<T> {
+    type Target = Command<SyntaxOnlyCommandList<T>>;
+
+    fn deref(&self) -> &Self::Target {
+        &self.0
+    }
+}
+
+impl<T> ops::DerefMut for SyntaxOnlyCommand<T> {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.0
+    }
+}
+
+impl<T> From<Command<SyntaxOnlyCommandList<T>>> for SyntaxOnlyCommand<T> {
+    fn from(inner: Command<SyntaxOnlyCommandList<T>>) -> Self {
+        SyntaxOnlyCommand(inner)
+    }
+}
+
+default_builder! {
+    /// A `Builder` implementation which builds the structure of shell commands
+    /// using the (non-atomic) AST definitions in the `ast` module, but elides the
+    /// contents of all words and arithmetic expressions.
+    ///
+    /// Useful for tools which only care about the shape of a program (e.g. counting
+    /// commands) and not about what its words expand to.
+    ///
+    /// Any arithmetic expression present in the source is still kept as an
+    /// `ElidedArithmetic` placeholder, so that e.g. the elided condition of an
+    /// arithmetic `for` command is not mistaken for an absent (and thus always
+    /// true) one.
+    pub struct SyntaxOnlyBuilder,
+    RcCoreBuilder,
+    ElidedWord,
+    SyntaxOnlyCommand<T>,
+    ShellPipeableCommand,
+    ElidedArithmetic,
+    {
+        fn word(&mut self,
+                _kind: ComplexWordKind<Self::Command, Self::Arithmetic>)
+            -> Result<Self::Word, Self::Error>
+        {
+            Ok(ElidedWord)
+        }
+
+        fn arithmetic(&mut self,
+                      _expr: DefaultArithmetic)
+            -> Result<Self::Arithmetic, Self::Error>
+        {
+            Ok(ElidedArithmetic)
+        }
+    }
+}
@@ -0,0 +1,557 @@
+//! Defines traits for rewriting the default AST representations.
+//!
+//! Every `fold_*` method of a folder defaults to calling the corresponding
+//! `walk_*` function, which will fold each of the node's children and rebuild
+//! the node from the results. Implementors can override only the methods for
+//! the nodes they wish to transform, and call the corresponding `walk_*`
+//! function from within an override to continue folding the node's children.
+//!
+//! The `Fold` trait rewrites `TopLevelCommand` based ASTs, while the
+//! `atomic::Fold` trait rewrites `AtomicTopLevelCommand` based ASTs.
+
+use std::rc::Rc;
+
+macro_rules! impl_fold {
+    ($Cmd:ident, $Word:ident, $CmdList:ident, $Pipeable:ident, $Rc:ident) => {
+        use crate::ast::*;
+
+        /// A folder which rewrites the nodes of a shell program.
+        ///
+        /// Generic over the representation of literals and names. Function bodies
+        /// which are shared with other nodes will be cloned before being folded.
+        pub trait Fold<T: Clone> {
+            /// Folds a complete command.
+            fn fold_command(&mut self, cmd: $Cmd<T>) -> $Cmd<T> {
+                walk_command(self, cmd)
+            }
+
+            /// Folds an and/or list of commands, e.g. `foo && bar || baz`.
+            fn fold_and_or_list(
+                &mut self,
+                list: $CmdList<T, $Word<T>, $Cmd<T>>,
+            ) -> $CmdList<T, $Word<T>, $Cmd<T>> {
+                walk_and_or_list(self, list)
+            }
+
+            /// Folds a pipeline or a single command within an and/or list.
+            fn fold_listable_command(
+                &mut self,
+                cmd: ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>>,
+            ) -> ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>> {
+                walk_listable_command(self, cmd)
+            }
+
+            /// Folds a command within a pipeline, including function definitions.
+            fn fold_pipeable_command(
+                &mut self,
+                cmd: $Pipeable<T, $Word<T>, $Cmd<T>>,
+            ) -> $Pipeable<T, $Word<T>, $Cmd<T>> {
+                walk_pipeable_command(self, cmd)
+            }
+
+            /// Folds a simple command, e.g. `foo=bar cmd arg >out`.
+            fn fold_simple_command(
+                &mut self,
+                cmd: SimpleCommand<T, $Word<T>, Redirect<$Word<T>>>,
+            ) -> SimpleCommand<T, $Word<T>, Redirect<$Word<T>>> {
+                walk_simple_command(self, cmd)
+            }
+
+            /// Folds a compound command along with any of its redirections.
+            fn fold_compound_command(
+                &mut self,
+                cmd: ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+            ) -> ShellCompoundCommand<T, $Word<T>, $Cmd<T>> {
+                walk_compound_command(self, cmd)
+            }
+
+            /// Folds a redirection, e.g. `2>out`.
+            fn fold_redirect(&mut self, redirect: Redirect<$Word<T>>) -> Redirect<$Word<T>> {
+                walk_redirect(self, redirect)
+            }
+
+            /// Folds a whitespace delimited word.
+            fn fold_word(&mut self, word: $Word<T>) -> $Word<T> {
+                walk_word(self, word)
+            }
+
+            /// Folds the smallest fragment of a word, e.g. a literal or a parameter.
+            fn fold_simple_word(
+                &mut self,
+                word: SimpleWord<
+                    T,
+                    Parameter<T>,
+                    Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+                >,
+            ) -> SimpleWord<
+                T,
+                Parameter<T>,
+                Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+            > {
+                walk_simple_word(self, word)
+            }
+
+            /// Folds a function definition, e.g. `foo() { bar; }`, returning
+            /// the (possibly renamed) function name along with its body.
+            fn fold_function_def(
+                &mut self,
+                name: T,
+                body: ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+            ) -> (T, ShellCompoundCommand<T, $Word<T>, $Cmd<T>>) {
+                walk_function_def(self, name, body)
+            }
+
+            /// Folds a parameter, e.g. `$foo` or `$@`, either on its own
+            /// or as part of a parameter substitution.
+            ///
+            /// The names of variables which are assigned (e.g. `foo=bar`),
+            /// iterated over by a `for` command, or which appear within an
+            /// arithmetic expression are also folded as a `Parameter::Var`.
+            fn fold_parameter(&mut self, param: Parameter<T>) -> Parameter<T> {
+                param
+            }
+
+            /// Folds a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+            fn fold_parameter_substitution(
+                &mut self,
+                subst: ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>,
+            ) -> ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>> {
+                walk_parameter_substitution(self, subst)
+            }
+
+            /// Folds an arithmetic expression.
+            fn fold_arithmetic(&mut self, expr: Arithmetic<T>) -> Arithmetic<T> {
+                walk_arithmetic(self, expr)
+            }
+        }
+
+        /// Folds the and/or list of a complete command.
+        pub fn walk_command<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            cmd: $Cmd<T>,
+        ) -> $Cmd<T> {
+            let cmd = match cmd.0 {
+                Command::Job(list) => Command::Job(folder.fold_and_or_list(list)),
+                Command::List(list) => Command::List(folder.fold_and_or_list(list)),
+            };
+
+            $Cmd(cmd)
+        }
+
+        /// Folds each command of an and/or list.
+        pub fn walk_and_or_list<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            list: $CmdList<T, $Word<T>, $Cmd<T>>,
+        ) -> $CmdList<T, $Word<T>, $Cmd<T>> {
+            AndOrList {
+                first: folder.fold_listable_command(list.first),
+                rest: list
+                    .rest
+                    .into_iter()
+                    .map(|and_or| match and_or {
+                        AndOr::And(cmd) => AndOr::And(folder.fold_listable_command(cmd)),
+                        AndOr::Or(cmd) => AndOr::Or(folder.fold_listable_command(cmd)),
+                    })
+                    .collect(),
+            }
+        }
+
+        /// Folds each command of a pipeline, or the single command.
+        pub fn walk_listable_command<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            cmd: ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>>,
+        ) -> ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>> {
+            match cmd {
+                ListableCommand::Pipe(bang, cmds) => ListableCommand::Pipe(
+                    bang,
+                    cmds.into_iter()
+                        .map(|cmd| folder.fold_pipeable_command(cmd))
+                        .collect(),
+                ),
+                ListableCommand::Single(cmd) => {
+                    ListableCommand::Single(folder.fold_pipeable_command(cmd))
+                }
+            }
+        }
+
+        /// Folds the simple command, compound command, function body,
+        /// or coprocess body within a pipeable command.
+        pub fn walk_pipeable_command<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            cmd: $Pipeable<T, $Word<T>, $Cmd<T>>,
+        ) -> $Pipeable<T, $Word<T>, $Cmd<T>> {
+            match cmd {
+                PipeableCommand::Simple(cmd) => {
+                    PipeableCommand::Simple(Box::new(folder.fold_simple_command(*cmd)))
+                }
+                PipeableCommand::Compound(cmd) => {
+                    PipeableCommand::Compound(Box::new(folder.fold_compound_command(*cmd)))
+                }
+                PipeableCommand::FunctionDef(name, body) => {
+                    let body = $Rc::try_unwrap(body).unwrap_or_else(|body| (*body).clone());
+                    let (name, body) = folder.fold_function_def(name, body);
+                    PipeableCommand::FunctionDef(name, $Rc::new(body))
+                }
+                PipeableCommand::Coproc { name, body } => PipeableCommand::Coproc {
+                    name,
+                    body: Box::new(folder.fold_pipeable_command(*body)),
+                },
+            }
+        }
+
+        /// Folds the body of a function definition, leaving its name as is.
+        pub fn walk_function_def<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            name: T,
+            body: ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+        ) -> (T, ShellCompoundCommand<T, $Word<T>, $Cmd<T>>) {
+            (name, folder.fold_compound_command(body))
+        }
+
+        /// Folds a variable name as a `Parameter::Var`, keeping the original
+        /// name if the folder replaces it with any other kind of parameter.
+        fn fold_var_name<T: Clone, F: Fold<T> + ?Sized>(folder: &mut F, name: T) -> T {
+            match folder.fold_parameter(Parameter::Var(name.clone())) {
+                Parameter::Var(name) => name,
+                _ => name,
+            }
+        }
+
+        /// Folds the assignment names and values, redirections, and words of a simple command.
+        pub fn walk_simple_command<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            cmd: SimpleCommand<T, $Word<T>, Redirect<$Word<T>>>,
+        ) -> SimpleCommand<T, $Word<T>, Redirect<$Word<T>>> {
+            let redirects_or_env_vars = cmd
+                .redirects_or_env_vars
+                .into_iter()
+                .map(|roev| match roev {
+                    RedirectOrEnvVar::Redirect(r) => {
+                        RedirectOrEnvVar::Redirect(folder.fold_redirect(r))
+                    }
+                    RedirectOrEnvVar::EnvVar(name, value) => RedirectOrEnvVar::EnvVar(
+                        fold_var_name(folder, name),
+                        value.map(|w| folder.fold_word(w)),
+                    ),
+                })
+                .collect();
+
+            let redirects_or_cmd_words = cmd
+                .redirects_or_cmd_words
+                .into_iter()
+                .map(|rocw| match rocw {
+                    RedirectOrCmdWord::Redirect(r) => {
+                        RedirectOrCmdWord::Redirect(folder.fold_redirect(r))
+                    }
+                    RedirectOrCmdWord::CmdWord(w) => {
+                        RedirectOrCmdWord::CmdWord(folder.fold_word(w))
+                    }
+                    RedirectOrCmdWord::Assignment(name, value) => RedirectOrCmdWord::Assignment(
+                        fold_var_name(folder, name),
+                        value.map(|w| folder.fold_word(w)),
+                    ),
+                    RedirectOrCmdWord::Declaration(name) => {
+                        RedirectOrCmdWord::Declaration(fold_var_name(folder, name))
+                    }
+                    RedirectOrCmdWord::Attributes(attrs) => RedirectOrCmdWord::Attributes(attrs),
+                })
+                .collect();
+
+            SimpleCommand {
+                redirects_or_env_vars,
+                redirects_or_cmd_words,
+            }
+        }
+
+        /// Folds the words and commands of a compound command, and its redirections.
+        pub fn walk_compound_command<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            cmd: ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+        ) -> ShellCompoundCommand<T, $Word<T>, $Cmd<T>> {
+            let fold_commands = |folder: &mut F, cmds: Vec<$Cmd<T>>| -> Vec<$Cmd<T>> {
+                cmds.into_iter()
+                    .map(|cmd| folder.fold_command(cmd))
+                    .collect()
+            };
+
+            let fold_pair = |folder: &mut F, pair: GuardBodyPair<$Cmd<T>>| GuardBodyPair {
+                guard: fold_commands(folder, pair.guard),
+                body: fold_commands(folder, pair.body),
+            };
+
+            let kind = match cmd.kind {
+                CompoundCommandKind::Brace(cmds) => {
+                    CompoundCommandKind::Brace(fold_commands(folder, cmds))
+                }
+                CompoundCommandKind::Subshell(cmds) => {
+                    CompoundCommandKind::Subshell(fold_commands(folder, cmds))
+                }
+                CompoundCommandKind::While(pair) => {
+                    CompoundCommandKind::While(fold_pair(folder, pair))
+                }
+                CompoundCommandKind::Until(pair) => {
+                    CompoundCommandKind::Until(fold_pair(folder, pair))
+                }
+
+                CompoundCommandKind::If {
+                    conditionals,
+                    else_branch,
+                } => CompoundCommandKind::If {
+                    conditionals: conditionals
+                        .into_iter()
+                        .map(|pair| fold_pair(folder, pair))
+                        .collect(),
+                    else_branch: else_branch.map(|cmds| fold_commands(folder, cmds)),
+                },
+
+                CompoundCommandKind::For { var, words, body } => CompoundCommandKind::For {
+                    var: fold_var_name(folder, var),
+                    words: words
+                        .map(|words| words.into_iter().map(|w| folder.fold_word(w)).collect()),
+                    body: fold_commands(folder, body),
+                },
+
+                CompoundCommandKind::ArithFor {
+                    init,
+                    cond,
+                    update,
+                    body,
+                } => CompoundCommandKind::ArithFor {
+                    init: init.map(|e| folder.fold_arithmetic(e)),
+                    cond: cond.map(|e| folder.fold_arithmetic(e)),
+                    update: update.map(|e| folder.fold_arithmetic(e)),
+                    body: fold_commands(folder, body),
+                },
+
+                CompoundCommandKind::Case { word, arms } => CompoundCommandKind::Case {
+                    word: folder.fold_word(word),
+                    arms: arms
+                        .into_iter()
+                        .map(|arm| PatternBodyPair {
+                            patterns: arm
+                                .patterns
+                                .into_iter()
+                                .map(|w| folder.fold_word(w))
+                                .collect(),
+                            body: fold_commands(folder, arm.body),
+                            terminator: arm.terminator,
+                        })
+                        .collect(),
+                },
+            };
+
+            CompoundCommand {
+                kind,
+                io: cmd
+                    .io
+                    .into_iter()
+                    .map(|r| folder.fold_redirect(r))
+                    .collect(),
+            }
+        }
+
+        /// Folds the path or file descriptor word of a redirection.
+        pub fn walk_redirect<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            redirect: Redirect<$Word<T>>,
+        ) -> Redirect<$Word<T>> {
+            match redirect {
+                Redirect::Read(fd, w) => Redirect::Read(fd, folder.fold_word(w)),
+                Redirect::Write(fd, w) => Redirect::Write(fd, folder.fold_word(w)),
+                Redirect::ReadWrite(fd, w) => Redirect::ReadWrite(fd, folder.fold_word(w)),
+                Redirect::Append(fd, w) => Redirect::Append(fd, folder.fold_word(w)),
+                Redirect::Clobber(fd, w) => Redirect::Clobber(fd, folder.fold_word(w)),
+                Redirect::Heredoc(fd, w) => Redirect::Heredoc(fd, folder.fold_word(w)),
+                Redirect::DupRead(fd, w) => Redirect::DupRead(fd, folder.fold_word(w)),
+                Redirect::DupWrite(fd, w) => Redirect::DupWrite(fd, folder.fold_word(w)),
+                Redirect::MoveRead(fd, w) => Redirect::MoveRead(fd, folder.fold_word(w)),
+                Redirect::MoveWrite(fd, w) => Redirect::MoveWrite(fd, folder.fold_word(w)),
+                Redirect::WriteBoth(w) => Redirect::WriteBoth(folder.fold_word(w)),
+                Redirect::AppendBoth(w) => Redirect::AppendBoth(folder.fold_word(w)),
+                Redirect::ClobberBoth(w) => Redirect::ClobberBoth(folder.fold_word(w)),
+            }
+        }
+
+        /// Folds each simple word within a word, including those within double quotes.
+        pub fn walk_word<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            word: $Word<T>,
+        ) -> $Word<T> {
+            let fold = |folder: &mut F, word| match word {
+                Word::Simple(w) => Word::Simple(folder.fold_simple_word(w)),
+                Word::DoubleQuoted(words) => Word::DoubleQuoted(
+                    words
+                        .into_iter()
+                        .map(|w| folder.fold_simple_word(w))
+                        .collect(),
+                ),
+                Word::SingleQuoted(s) => Word::SingleQuoted(s),
+                Word::BracketClass(negated, s) => Word::BracketClass(negated, s),
+            };
+
+            let word = match word.0 {
+                ComplexWord::Concat(words) => {
+                    ComplexWord::Concat(words.into_iter().map(|w| fold(folder, w)).collect())
+                }
+                ComplexWord::Single(w) => ComplexWord::Single(fold(folder, w)),
+            };
+
+            $Word(word)
+        }
+
+        /// Folds the parameter or parameter substitution of a simple word, if any.
+        pub fn walk_simple_word<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            word: SimpleWord<
+                T,
+                Parameter<T>,
+                Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+            >,
+        ) -> SimpleWord<
+            T,
+            Parameter<T>,
+            Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+        > {
+            match word {
+                SimpleWord::Param(p) => SimpleWord::Param(folder.fold_parameter(p)),
+                SimpleWord::Subst(s) => {
+                    SimpleWord::Subst(Box::new(folder.fold_parameter_substitution(*s)))
+                }
+                w @ SimpleWord::Literal(_)
+                | w @ SimpleWord::Escaped(_)
+                | w @ SimpleWord::Star
+                | w @ SimpleWord::Question
+                | w @ SimpleWord::SquareOpen
+                | w @ SimpleWord::SquareClose
+                | w @ SimpleWord::Tilde
+                | w @ SimpleWord::Colon => w,
+            }
+        }
+
+        /// Folds the parameter, words, commands, or arithmetic expression
+        /// of a parameter substitution.
+        pub fn walk_parameter_substitution<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            subst: ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>,
+        ) -> ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>> {
+            use crate::ast::ParameterSubstitution::*;
+
+            macro_rules! fold_word {
+                ($word:expr) => {
+                    $word.map(|w| folder.fold_word(w))
+                };
+            }
+
+            match subst {
+                Command(cmds) => {
+                    Command(cmds.into_iter().map(|c| folder.fold_command(c)).collect())
+                }
+                Len(p) => Len(folder.fold_parameter(p)),
+                Arith(expr) => Arith(expr.map(|e| folder.fold_arithmetic(e))),
+                Default(colon, p, w) => Default(colon, folder.fold_parameter(p), fold_word!(w)),
+                Assign(colon, p, w) => Assign(colon, folder.fold_parameter(p), fold_word!(w)),
+                Error(colon, p, w) => Error(colon, folder.fold_parameter(p), fold_word!(w)),
+                Alternative(colon, p, w) => {
+                    Alternative(colon, folder.fold_parameter(p), fold_word!(w))
+                }
+                RemoveSmallestSuffix(p, w) => {
+                    RemoveSmallestSuffix(folder.fold_parameter(p), fold_word!(w))
+                }
+                RemoveLargestSuffix(p, w) => {
+                    RemoveLargestSuffix(folder.fold_parameter(p), fold_word!(w))
+                }
+                RemoveSmallestPrefix(p, w) => {
+                    RemoveSmallestPrefix(folder.fold_parameter(p), fold_word!(w))
+                }
+                RemoveLargestPrefix(p, w) => {
+                    RemoveLargestPrefix(folder.fold_parameter(p), fold_word!(w))
+                }
+                NamesMatchingPrefix(prefix, at) => NamesMatchingPrefix(prefix, at),
+                Transform(p, op) => Transform(folder.fold_parameter(p), op),
+                ReadFile(w) => ReadFile(folder.fold_word(w)),
+            }
+        }
+
+        /// Folds each operand of an arithmetic expression, as well as the
+        /// name of each variable it refers to.
+        pub fn walk_arithmetic<T: Clone, F: Fold<T> + ?Sized>(
+            folder: &mut F,
+            expr: Arithmetic<T>,
+        ) -> Arithmetic<T> {
+            use crate::ast::Arithmetic::*;
+
+            macro_rules! fold {
+                ($expr:expr) => {
+                    Box::new(folder.fold_arithmetic(*$expr))
+                };
+            }
+
+            match expr {
+                Var(var) => Var(fold_var_name(folder, var)),
+                Literal(n) => Literal(n),
+                PostIncr(var) => PostIncr(fold_var_name(folder, var)),
+                PostDecr(var) => PostDecr(fold_var_name(folder, var)),
+                PreIncr(var) => PreIncr(fold_var_name(folder, var)),
+                PreDecr(var) => PreDecr(fold_var_name(folder, var)),
+
+                UnaryPlus(e) => UnaryPlus(fold!(e)),
+                UnaryMinus(e) => UnaryMinus(fold!(e)),
+                LogicalNot(e) => LogicalNot(fold!(e)),
+                BitwiseNot(e) => BitwiseNot(fold!(e)),
+                Assign(var, e) => Assign(fold_var_name(folder, var), fold!(e)),
+
+                Pow(l, r) => Pow(fold!(l), fold!(r)),
+                Mult(l, r) => Mult(fold!(l), fold!(r)),
+                Div(l, r) => Div(fold!(l), fold!(r)),
+                Modulo(l, r) => Modulo(fold!(l), fold!(r)),
+                Add(l, r) => Add(fold!(l), fold!(r)),
+                Sub(l, r) => Sub(fold!(l), fold!(r)),
+                ShiftLeft(l, r) => ShiftLeft(fold!(l), fold!(r)),
+                ShiftRight(l, r) => ShiftRight(fold!(l), fold!(r)),
+                Less(l, r) => Less(fold!(l), fold!(r)),
+                LessEq(l, r) => LessEq(fold!(l), fold!(r)),
+                Great(l, r) => Great(fold!(l), fold!(r)),
+                GreatEq(l, r) => GreatEq(fold!(l), fold!(r)),
+                Eq(l, r) => Eq(fold!(l), fold!(r)),
+                NotEq(l, r) => NotEq(fold!(l), fold!(r)),
+                BitwiseAnd(l, r) => BitwiseAnd(fold!(l), fold!(r)),
+                BitwiseXor(l, r) => BitwiseXor(fold!(l), fold!(r)),
+                BitwiseOr(l, r) => BitwiseOr(fold!(l), fold!(r)),
+                LogicalAnd(l, r) => LogicalAnd(fold!(l), fold!(r)),
+                LogicalOr(l, r) => LogicalOr(fold!(l), fold!(r)),
+
+                Ternary(guard, if_true, if_false) => {
+                    Ternary(fold!(guard), fold!(if_true), fold!(if_false))
+                }
+
+                Sequence(exprs) => Sequence(
+                    exprs
+                        .into_iter()
+                        .map(|e| folder.fold_arithmetic(e))
+                        .collect(),
+                ),
+            }
+        }
+    };
+}
+
+impl_fold!(
+    TopLevelCommand,
+    TopLevelWord,
+    CommandList,
+    ShellPipeableCommand,
+    Rc
+);
+
+/// Defines traits for rewriting `AtomicTopLevelCommand` based ASTs.
+pub mod atomic {
+    use std::sync::Arc;
+
+    impl_fold!(
+        AtomicTopLevelCommand,
+        AtomicTopLevelWord,
+        AtomicCommandList,
+        AtomicShellPipeableCommand,
+        Arc
+    );
+}
@@ -0,0 +1,468 @@
+//! Defines traits for traversing the default AST representations.
+//!
+//! Every `visit_*` method of a visitor defaults to calling the corresponding
+//! `walk_*` function, which will in turn visit each of the node's children.
+//! Implementors can override only the methods for the nodes they care about,
+//! and call the corresponding `walk_*` function from within an override to
+//! continue traversing into the node's children.
+//!
+//! The `Visitor` trait walks `TopLevelCommand` based ASTs, while the
+//! `atomic::Visitor` trait walks `AtomicTopLevelCommand` based ASTs.
+
+macro_rules! impl_visitor {
+    ($Cmd:ident, $Word:ident, $CmdList:ident, $Pipeable:ident) => {
+        use crate::ast::*;
+
+        /// A visitor of the nodes of a shell program.
+        ///
+        /// Generic over the representation of literals and names.
+        pub trait Visitor<T> {
+            /// Visits a complete command.
+            fn visit_command(&mut self, cmd: &$Cmd<T>) {
+                walk_command(self, cmd)
+            }
+
+            /// Visits an and/or list of commands, e.g. `foo && bar || baz`.
+            fn visit_and_or_list(&mut self, list: &$CmdList<T, $Word<T>, $Cmd<T>>) {
+                walk_and_or_list(self, list)
+            }
+
+            /// Visits a pipeline or a single command within an and/or list.
+            fn visit_listable_command(
+                &mut self,
+                cmd: &ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>>,
+            ) {
+                walk_listable_command(self, cmd)
+            }
+
+            /// Visits a command within a pipeline.
+            fn visit_pipeable_command(&mut self, cmd: &$Pipeable<T, $Word<T>, $Cmd<T>>) {
+                walk_pipeable_command(self, cmd)
+            }
+
+            /// Visits a simple command, e.g. `foo=bar cmd arg >out`.
+            fn visit_simple_command(
+                &mut self,
+                cmd: &SimpleCommand<T, $Word<T>, Redirect<$Word<T>>>,
+            ) {
+                walk_simple_command(self, cmd)
+            }
+
+            /// Visits a compound command along with any of its redirections.
+            fn visit_compound_command(&mut self, cmd: &ShellCompoundCommand<T, $Word<T>, $Cmd<T>>) {
+                walk_compound_command(self, cmd)
+            }
+
+            /// Visits a function definition and its body.
+            fn visit_function_def(
+                &mut self,
+                name: &T,
+                body: &ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+            ) {
+                walk_function_def(self, name, body)
+            }
+
+            /// Visits a variable assignment, e.g. `foo=bar`.
+            fn visit_assignment(&mut self, name: &T, value: Option<&$Word<T>>) {
+                walk_assignment(self, name, value)
+            }
+
+            /// Visits a variable declared without a value, e.g. the `foo` in `export foo`.
+            fn visit_declaration(&mut self, _name: &T) {}
+
+            /// Visits a redirection, e.g. `2>out`.
+            fn visit_redirect(&mut self, redirect: &Redirect<$Word<T>>) {
+                walk_redirect(self, redirect)
+            }
+
+            /// Visits a whitespace delimited word.
+            fn visit_word(&mut self, word: &$Word<T>) {
+                walk_word(self, word)
+            }
+
+            /// Visits the smallest fragment of a word, e.g. a literal or a parameter.
+            fn visit_simple_word(
+                &mut self,
+                word: &SimpleWord<
+                    T,
+                    Parameter<T>,
+                    Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+                >,
+            ) {
+                walk_simple_word(self, word)
+            }
+
+            /// Visits a parameter, e.g. `$foo` or `$@`, either on its own
+            /// or as part of a parameter substitution.
+            fn visit_parameter(&mut self, _param: &Parameter<T>) {}
+
+            /// Visits a parameter substitution, e.g. `${foo:-bar}` or `$(cmd)`.
+            fn visit_parameter_substitution(
+                &mut self,
+                subst: &ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>,
+            ) {
+                walk_parameter_substitution(self, subst)
+            }
+
+            /// Visits an arithmetic expression.
+            fn visit_arithmetic(&mut self, expr: &Arithmetic<T>) {
+                walk_arithmetic(self, expr)
+            }
+        }
+
+        /// Visits the and/or list of a complete command.
+        pub fn walk_command<T, V: Visitor<T> + ?Sized>(visitor: &mut V, cmd: &$Cmd<T>) {
+            match cmd.0 {
+                Command::Job(ref list) | Command::List(ref list) => visitor.visit_and_or_list(list),
+            }
+        }
+
+        /// Visits each command of an and/or list.
+        pub fn walk_and_or_list<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            list: &$CmdList<T, $Word<T>, $Cmd<T>>,
+        ) {
+            visitor.visit_listable_command(&list.first);
+            for and_or in &list.rest {
+                match *and_or {
+                    AndOr::And(ref cmd) | AndOr::Or(ref cmd) => visitor.visit_listable_command(cmd),
+                }
+            }
+        }
+
+        /// Visits each command of a pipeline, or the single command.
+        pub fn walk_listable_command<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            cmd: &ListableCommand<$Pipeable<T, $Word<T>, $Cmd<T>>>,
+        ) {
+            match *cmd {
+                ListableCommand::Pipe(_, ref cmds) => {
+                    for cmd in cmds {
+                        visitor.visit_pipeable_command(cmd);
+                    }
+                }
+                ListableCommand::Single(ref cmd) => visitor.visit_pipeable_command(cmd),
+            }
+        }
+
+        /// Visits the simple command, compound command, function definition,
+        /// or coprocess body within a pipeable command.
+        pub fn walk_pipeable_command<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            cmd: &$Pipeable<T, $Word<T>, $Cmd<T>>,
+        ) {
+            match *cmd {
+                PipeableCommand::Simple(ref cmd) => visitor.visit_simple_command(cmd),
+                PipeableCommand::Compound(ref cmd) => visitor.visit_compound_command(cmd),
+                PipeableCommand::FunctionDef(ref name, ref body) => {
+                    visitor.visit_function_def(name, body)
+                }
+                PipeableCommand::Coproc { ref body, .. } => visitor.visit_pipeable_command(body),
+            }
+        }
+
+        /// Visits the assignments, redirections, and words of a simple command
+        /// in the order they appear.
+        pub fn walk_simple_command<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            cmd: &SimpleCommand<T, $Word<T>, Redirect<$Word<T>>>,
+        ) {
+            for redirect_or_env_var in &cmd.redirects_or_env_vars {
+                match *redirect_or_env_var {
+                    RedirectOrEnvVar::Redirect(ref redirect) => visitor.visit_redirect(redirect),
+                    RedirectOrEnvVar::EnvVar(ref name, ref value) => {
+                        visitor.visit_assignment(name, value.as_ref())
+                    }
+                }
+            }
+
+            for redirect_or_cmd_word in &cmd.redirects_or_cmd_words {
+                match *redirect_or_cmd_word {
+                    RedirectOrCmdWord::Redirect(ref redirect) => visitor.visit_redirect(redirect),
+                    RedirectOrCmdWord::CmdWord(ref word) => visitor.visit_word(word),
+                    RedirectOrCmdWord::Assignment(ref name, ref value) => {
+                        visitor.visit_assignment(name, value.as_ref())
+                    }
+                    RedirectOrCmdWord::Declaration(ref name) => visitor.visit_declaration(name),
+                    RedirectOrCmdWord::Attributes(_) => {}
+                }
+            }
+        }
+
+        /// Visits the words and commands of a compound command, followed by its redirections.
+        pub fn walk_compound_command<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            cmd: &ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+        ) {
+            let visit_commands = |visitor: &mut V, cmds: &[$Cmd<T>]| {
+                for cmd in cmds {
+                    visitor.visit_command(cmd);
+                }
+            };
+
+            match cmd.kind {
+                CompoundCommandKind::Brace(ref cmds) | CompoundCommandKind::Subshell(ref cmds) => {
+                    visit_commands(visitor, cmds)
+                }
+
+                CompoundCommandKind::While(ref pair) | CompoundCommandKind::Until(ref pair) => {
+                    visit_commands(visitor, &pair.guard);
+                    visit_commands(visitor, &pair.body);
+                }
+
+                CompoundCommandKind::If {
+                    ref conditionals,
+                    ref else_branch,
+                } => {
+                    for pair in conditionals {
+                        visit_commands(visitor, &pair.guard);
+                        visit_commands(visitor, &pair.body);
+                    }
+
+                    if let Some(ref else_branch) = *else_branch {
+                        visit_commands(visitor, else_branch);
+                    }
+                }
+
+                CompoundCommandKind::For {
+                    ref words,
+                    ref body,
+                    ..
+                } => {
+                    for word in words.iter().flatten() {
+                        visitor.visit_word(word);
+                    }
+
+                    visit_commands(visitor, body);
+                }
+
+                CompoundCommandKind::ArithFor {
+                    ref init,
+                    ref cond,
+                    ref update,
+                    ref body,
+                } => {
+                    for expr in [init, cond, update].iter().filter_map(|e| e.as_ref()) {
+                        visitor.visit_arithmetic(expr);
+                    }
+
+                    visit_commands(visitor, body);
+                }
+
+                CompoundCommandKind::Case { ref word, ref arms } => {
+                    visitor.visit_word(word);
+                    for arm in arms {
+                        for pattern in &arm.patterns {
+                            visitor.visit_word(pattern);
+                        }
+
+                        visit_commands(visitor, &arm.body);
+                    }
+                }
+            }
+
+            for redirect in &cmd.io {
+                visitor.visit_redirect(redirect);
+            }
+        }
+
+        /// Visits the body of a function definition.
+        pub fn walk_function_def<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            _name: &T,
+            body: &ShellCompoundCommand<T, $Word<T>, $Cmd<T>>,
+        ) {
+            visitor.visit_compound_command(body)
+        }
+
+        /// Visits the value of a variable assignment, if any.
+        pub fn walk_assignment<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            _name: &T,
+            value: Option<&$Word<T>>,
+        ) {
+            if let Some(value) = value {
+                visitor.visit_word(value);
+            }
+        }
+
+        /// Visits the path or file descriptor word of a redirection.
+        pub fn walk_redirect<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            redirect: &Redirect<$Word<T>>,
+        ) {
+            match *redirect {
+                Redirect::Read(_, ref word)
+                | Redirect::Write(_, ref word)
+                | Redirect::ReadWrite(_, ref word)
+                | Redirect::Append(_, ref word)
+                | Redirect::Clobber(_, ref word)
+                | Redirect::Heredoc(_, ref word)
+                | Redirect::DupRead(_, ref word)
+                | Redirect::DupWrite(_, ref word)
+                | Redirect::MoveRead(_, ref word)
+                | Redirect::MoveWrite(_, ref word)
+                | Redirect::WriteBoth(ref word)
+                | Redirect::AppendBoth(ref word)
+                | Redirect::ClobberBoth(ref word) => visitor.visit_word(word),
+            }
+        }
+
+        /// Visits each simple word within a word, including those within double quotes.
+        pub fn walk_word<T, V: Visitor<T> + ?Sized>(visitor: &mut V, word: &$Word<T>) {
+            let walk = |visitor: &mut V, word: &Word<T, _>| match *word {
+                Word::Simple(ref word) => visitor.visit_simple_word(word),
+                Word::DoubleQuoted(ref words) => {
+                    for word in words {
+                        visitor.visit_simple_word(word);
+                    }
+                }
+                Word::SingleQuoted(_) | Word::BracketClass(..) => {}
+            };
+
+            match word.0 {
+                ComplexWord::Concat(ref words) => {
+                    for word in words {
+                        walk(visitor, word);
+                    }
+                }
+                ComplexWord::Single(ref word) => walk(visitor, word),
+            }
+        }
+
+        /// Visits the parameter or parameter substitution of a simple word, if any.
+        pub fn walk_simple_word<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            word: &SimpleWord<
+                T,
+                Parameter<T>,
+                Box<ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>>,
+            >,
+        ) {
+            match *word {
+                SimpleWord::Param(ref param) => visitor.visit_parameter(param),
+                SimpleWord::Subst(ref subst) => visitor.visit_parameter_substitution(subst),
+                SimpleWord::Literal(_)
+                | SimpleWord::Escaped(_)
+                | SimpleWord::Star
+                | SimpleWord::Question
+                | SimpleWord::SquareOpen
+                | SimpleWord::SquareClose
+                | SimpleWord::Tilde
+                | SimpleWord::Colon => {}
+            }
+        }
+
+        /// Visits the parameter, words, commands, or arithmetic expression
+        /// of a parameter substitution.
+        pub fn walk_parameter_substitution<T, V: Visitor<T> + ?Sized>(
+            visitor: &mut V,
+            subst: &ParameterSubstitution<Parameter<T>, $Word<T>, $Cmd<T>, Arithmetic<T>>,
+        ) {
+            match *subst {
+                ParameterSubstitution::Command(ref cmds) => {
+                    for cmd in cmds {
+                        visitor.visit_command(cmd);
+                    }
+                }
+
+                ParameterSubstitution::Len(ref param)
+                | ParameterSubstitution::Transform(ref param, _) => visitor.visit_parameter(param),
+
+                ParameterSubstitution::Arith(ref expr) => {
+                    if let Some(ref expr) = *expr {
+                        visitor.visit_arithmetic(expr);
+                    }
+                }
+
+                ParameterSubstitution::Default(_, ref param, ref word)
+                | ParameterSubstitution::Assign(_, ref param, ref word)
+                | ParameterSubstitution::Error(_, ref param, ref word)
+                | ParameterSubstitution::Alternative(_, ref param, ref word)
+                | ParameterSubstitution::RemoveSmallestSuffix(ref param, ref word)
+                | ParameterSubstitution::RemoveLargestSuffix(ref param, ref word)
+                | ParameterSubstitution::RemoveSmallestPrefix(ref param, ref word)
+                | ParameterSubstitution::RemoveLargestPrefix(ref param, ref word) => {
+                    visitor.visit_parameter(param);
+                    if let Some(ref word) = *word {
+                        visitor.visit_word(word);
+                    }
+                }
+
+                ParameterSubstitution::ReadFile(ref word) => visitor.visit_word(word),
+
+                ParameterSubstitution::NamesMatchingPrefix(..) => {}
+            }
+        }
+
+        /// Visits each operand of an arithmetic expression.
+        pub fn walk_arithmetic<T, V: Visitor<T> + ?Sized>(visitor: &mut V, expr: &Arithmetic<T>) {
+            match *expr {
+                Arithmetic::Var(_)
+                | Arithmetic::Literal(_)
+                | Arithmetic::PostIncr(_)
+                | Arithmetic::PostDecr(_)
+                | Arithmetic::PreIncr(_)
+                | Arithmetic::PreDecr(_) => {}
+
+                Arithmetic::UnaryPlus(ref expr)
+                | Arithmetic::UnaryMinus(ref expr)
+                | Arithmetic::LogicalNot(ref expr)
+                | Arithmetic::BitwiseNot(ref expr)
+                | Arithmetic::Assign(_, ref expr) => visitor.visit_arithmetic(expr),
+
+                Arithmetic::Pow(ref left, ref right)
+                | Arithmetic::Mult(ref left, ref right)
+                | Arithmetic::Div(ref left, ref right)
+                | Arithmetic::Modulo(ref left, ref right)
+                | Arithmetic::Add(ref left, ref right)
+                | Arithmetic::Sub(ref left, ref right)
+                | Arithmetic::ShiftLeft(ref left, ref right)
+                | Arithmetic::ShiftRight(ref left, ref right)
+                | Arithmetic::Less(ref left, ref right)
+                | Arithmetic::LessEq(ref left, ref right)
+                | Arithmetic::Great(ref left, ref right)
+                | Arithmetic::GreatEq(ref left, ref right)
+                | Arithmetic::Eq(ref left, ref right)
+                | Arithmetic::NotEq(ref left, ref right)
+                | Arithmetic::BitwiseAnd(ref left, ref right)
+                | Arithmetic::BitwiseXor(ref left, ref right)
+                | Arithmetic::BitwiseOr(ref left, ref right)
+                | Arithmetic::LogicalAnd(ref left, ref right)
+                | Arithmetic::LogicalOr(ref left, ref right) => {
+                    visitor.visit_arithmetic(left);
+                    visitor.visit_arithmetic(right);
+                }
+
+                Arithmetic::Ternary(ref guard, ref if_true, ref if_false) => {
+                    visitor.visit_arithmetic(guard);
+                    visitor.visit_arithmetic(if_true);
+                    visitor.visit_arithmetic(if_false);
+                }
+
+                Arithmetic::Sequence(ref exprs) => {
+                    for expr in exprs {
+                        visitor.visit_arithmetic(expr);
+                    }
+                }
+            }
+        }
+    };
+}
+
+impl_visitor!(
+    TopLevelCommand,
+    TopLevelWord,
+    CommandList,
+    ShellPipeableCommand
+);
+
+/// Defines traits for traversing `AtomicTopLevelCommand` based ASTs.
+pub mod atomic {
+    impl_visitor!(
+        AtomicTopLevelCommand,
+        AtomicTopLevelWord,
+        AtomicCommandList,
+        AtomicShellPipeableCommand
+    );
+}
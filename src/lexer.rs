@@ -3,7 +3,9 @@
 use self::TokenOrLiteral::*;
 use super::token::Token::*;
 use super::token::{Positional, Token};
+use std::io::{self, BufRead};
 use std::iter::{Fuse, Peekable};
+use std::str;
 
 #[derive(PartialEq, Eq, Debug, Clone)]
 enum TokenOrLiteral {
@@ -12,20 +14,62 @@ enum TokenOrLiteral {
     Lit(char),
 }
 
+/// The set of characters which a `Lexer` accepts as part of a `Token::Name`.
+///
+/// Any word which starts with a character outside of the set is yielded as a
+/// `Token::Literal`, and a name is delimited by the first character outside of it.
+#[derive(Debug, Default, PartialEq, Eq, Copy, Clone, Hash)]
+pub enum NameChars {
+    /// ASCII letters, digits, and underscores, as defined by POSIX.
+    Posix,
+    /// Any alphabetic character as defined by Unicode (e.g. `é`),
+    /// ASCII digits, and underscores.
+    #[default]
+    Unicode,
+}
+
+impl NameChars {
+    /// Checks if a name may start with the specified character.
+    pub fn is_name_start(self, c: char) -> bool {
+        match self {
+            NameChars::Posix => c == '_' || c.is_ascii_alphabetic(),
+            NameChars::Unicode => c == '_' || c.is_alphabetic(),
+        }
+    }
+
+    /// Checks if the specified character may appear anywhere within a name.
+    pub fn is_name_char(self, c: char) -> bool {
+        c.is_ascii_digit() || self.is_name_start(c)
+    }
+}
+
 /// Converts raw characters into shell tokens.
+///
+/// Any character without a special meaning, including NUL (`\0`), is yielded as
+/// part of a `Token::Literal`, thus a NUL is never treated as whitespace or as a
+/// word delimiter, and is preserved within quotes and heredoc bodies.
 #[must_use = "`Lexer` is lazy and does nothing unless consumed"]
 #[derive(Clone, Debug)]
 pub struct Lexer<I: Iterator<Item = char>> {
     inner: Peekable<Fuse<I>>,
     peeked: Option<TokenOrLiteral>,
+    name_chars: NameChars,
 }
 
 impl<I: Iterator<Item = char>> Lexer<I> {
-    /// Creates a new Lexer from any char iterator.
+    /// Creates a new Lexer from any char iterator, which recognizes names
+    /// made up of any Unicode alphabetic characters.
     pub fn new(iter: I) -> Lexer<I> {
+        Self::with_name_chars(iter, NameChars::default())
+    }
+
+    /// Creates a new Lexer from any char iterator, which recognizes names
+    /// made up of the specified set of characters.
+    pub fn with_name_chars(iter: I, name_chars: NameChars) -> Lexer<I> {
         Lexer {
             inner: iter.fuse().peekable(),
             peeked: None,
+            name_chars,
         }
     }
 
@@ -43,10 +87,7 @@ impl<I: Iterator<Item = char>> Lexer<I> {
             return self.peeked.take();
         }
 
-        let cur = match self.inner.next() {
-            Some(c) => c,
-            None => return None,
-        };
+        let cur = self.inner.next()?;
 
         let tok = match cur {
             '\n' => Newline,
@@ -71,11 +112,9 @@ impl<I: Iterator<Item = char>> Lexer<I> {
             // first & is a literal while the second retains its properties.
             // We will let the parser deal with what actually becomes a literal.
             '\\' => {
-                return Some(Escaped(
-                    self.inner
-                        .next()
-                        .and_then(|c| Lexer::new(std::iter::once(c)).next()),
-                ))
+                return Some(Escaped(self.inner.next().and_then(|c| {
+                    Lexer::with_name_chars(std::iter::once(c), self.name_chars).next()
+                })))
             }
 
             '\'' => SingleQuote,
@@ -84,7 +123,13 @@ impl<I: Iterator<Item = char>> Lexer<I> {
 
             ';' => {
                 if self.next_is(';') {
-                    DSemi
+                    if self.next_is('&') {
+                        DSemiAmp
+                    } else {
+                        DSemi
+                    }
+                } else if self.next_is('&') {
+                    SemiAmp
                 } else {
                     Semi
                 }
@@ -92,6 +137,14 @@ impl<I: Iterator<Item = char>> Lexer<I> {
             '&' => {
                 if self.next_is('&') {
                     AndIf
+                } else if self.next_is('>') {
+                    if self.next_is('>') {
+                        AmpDGreat
+                    } else if self.next_is('|') {
+                        AmpClobber
+                    } else {
+                        AmpGreat
+                    }
                 } else {
                     Amp
                 }
@@ -194,17 +247,7 @@ impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Token> {
-        fn name_start_char(c: char) -> bool {
-            c == '_' || c.is_alphabetic()
-        }
-
-        fn is_digit(c: char) -> bool {
-            c.is_digit(10)
-        }
-
-        fn name_char(c: char) -> bool {
-            is_digit(c) || name_start_char(c)
-        }
+        let name_chars = self.name_chars;
 
         match self.next_internal() {
             None => None,
@@ -216,7 +259,7 @@ impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
             }
 
             Some(Lit(c)) => {
-                let is_name = name_start_char(c);
+                let is_name = name_chars.is_name_start(c);
                 let mut word = String::new();
                 word.push(c);
 
@@ -230,7 +273,7 @@ impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
                         }
 
                         // Make sure we delimit valid names whenever a non-name char comes along
-                        Some(Lit(c)) if is_name && !name_char(c) => {
+                        Some(Lit(c)) if is_name && !name_chars.is_name_char(c) => {
                             debug_assert_eq!(self.peeked, None);
                             self.peeked = Some(Lit(c));
                             return Some(Name(word));
@@ -264,3 +307,88 @@ impl<I: Iterator<Item = char>> Iterator for Lexer<I> {
         (low, hi)
     }
 }
+
+/// An iterator which lazily decodes UTF-8 characters from a byte stream,
+/// allowing a `Lexer` to consume a script without reading all of it into
+/// memory first.
+///
+/// Any I/O error, or any byte sequence which is not valid UTF-8, is yielded
+/// as an error (the latter with `io::ErrorKind::InvalidData`), after which
+/// decoding will resume with the remaining bytes.
+#[must_use = "iterators are lazy and do nothing unless consumed"]
+#[derive(Clone, Debug)]
+pub struct Utf8Chars<R> {
+    reader: R,
+}
+
+impl<R: BufRead> Utf8Chars<R> {
+    /// Creates a new adaptor which decodes the bytes of the provided reader.
+    pub fn new(reader: R) -> Self {
+        Utf8Chars { reader }
+    }
+
+    /// Unwraps this adaptor, returning the underlying reader.
+    pub fn into_inner(self) -> R {
+        self.reader
+    }
+}
+
+impl<R: BufRead> Iterator for Utf8Chars<R> {
+    type Item = io::Result<char>;
+
+    fn next(&mut self) -> Option<Self::Item> {
+        fn invalid_data() -> io::Error {
+            io::Error::new(
+                io::ErrorKind::InvalidData,
+                "stream did not contain valid UTF-8",
+            )
+        }
+
+        let mut bytes = [0; 4];
+        let mut len = 0;
+        let mut width = 0;
+
+        loop {
+            let byte = match self.reader.fill_buf() {
+                Ok(buf) => match buf.first() {
+                    Some(&b) => b,
+                    None if len == 0 => return None,
+                    None => return Some(Err(invalid_data())),
+                },
+                Err(ref e) if e.kind() == io::ErrorKind::Interrupted => continue,
+                Err(e) => return Some(Err(e)),
+            };
+
+            if len == 0 {
+                width = match byte {
+                    0x00..=0x7f => {
+                        self.reader.consume(1);
+                        return Some(Ok(char::from(byte)));
+                    }
+                    0xc2..=0xdf => 2,
+                    0xe0..=0xef => 3,
+                    0xf0..=0xf4 => 4,
+                    _ => {
+                        self.reader.consume(1);
+                        return Some(Err(invalid_data()));
+                    }
+                };
+            } else if byte & 0xc0 != 0x80 {
+                // Leave the byte in the stream since it may start a valid character
+                return Some(Err(invalid_data()));
+            }
+
+            bytes[len] = byte;
+            len += 1;
+            self.reader.consume(1);
+
+            if len == width {
+                let ch = str::from_utf8(&bytes[..len])
+                    .ok()
+                    .and_then(|s| s.chars().next())
+                    .ok_or_else(invalid_data);
+                return Some(ch);
+            }
+        }
+    }
+}
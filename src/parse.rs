@@ -12,15 +12,16 @@ use std::str::FromStr;
 
 use self::iter::{PeekableIterator, PositionIterator, TokenIter, TokenIterWrapper, TokenIterator};
 use crate::ast::builder::ComplexWordKind::{self, Concat, Single};
-use crate::ast::builder::WordKind::{self, DoubleQuoted, Simple, SingleQuoted};
+use crate::ast::builder::WordKind::{self, BracketClass, DoubleQuoted, Simple, SingleQuoted};
 use crate::ast::builder::{self, Builder, SimpleWordKind};
 use crate::ast::{self, DefaultArithmetic, DefaultParameter};
-use crate::token::Token;
 use crate::token::Token::*;
+use crate::token::{Token, TokenKind};
 
 mod iter;
 
 const CASE: &str = "case";
+const COPROC: &str = "coproc";
 const DO: &str = "do";
 const DONE: &str = "done";
 const ELIF: &str = "elif";
@@ -35,6 +36,21 @@ const THEN: &str = "then";
 const UNTIL: &str = "until";
 const WHILE: &str = "while";
 
+/// The reserved words recognized by a parser with the default configuration.
+pub const DEFAULT_RESERVED_WORDS: &[&str] = &[
+    CASE, COPROC, DO, DONE, ELIF, ELSE, ESAC, FI, FOR, FUNCTION, IF, IN, THEN, UNTIL, WHILE,
+];
+
+/// The reserved tokens recognized by a parser with the default configuration.
+pub const DEFAULT_RESERVED_TOKENS: &[Token] = &[CurlyOpen, Bang];
+
+/// The builtins whose arguments may be variable assignments with the default configuration.
+pub const DEFAULT_ASSIGNMENT_BUILTINS: &[&str] =
+    &["declare", "export", "local", "readonly", "typeset"];
+
+/// The maximum nesting depth permitted by a parser with the default configuration.
+pub const DEFAULT_MAX_NESTING_DEPTH: usize = 64;
+
 /// A parser which will use a default AST builder implementation,
 /// yielding results in terms of types defined in the `ast` module.
 pub type DefaultParser<I> = Parser<I, builder::StringBuilder>;
@@ -42,14 +58,31 @@ pub type DefaultParser<I> = Parser<I, builder::StringBuilder>;
 /// A specialized `Result` type for parsing shell commands.
 pub type ParseResult<T, E> = Result<T, ParseError<E>>;
 
+/// The kind of complex word which is handed to a particular builder.
+type BuilderComplexWordKind<B> =
+    ComplexWordKind<<B as Builder>::Command, <B as Builder>::Arithmetic>;
+
+/// The kind of simple word which is handed to a particular builder.
+type BuilderSimpleWordKind<B> = SimpleWordKind<<B as Builder>::Command, <B as Builder>::Arithmetic>;
+
 /// Indicates a character/token position in the original source.
-#[derive(Debug, PartialEq, Eq, Copy, Clone)]
+///
+/// Positions are ordered by their byte offset first, falling back to
+/// comparing their lines and columns only if the byte offsets are equal.
+///
+/// When line and column tracking has been disabled (see
+/// `ParserConfig::track_line_col`), only the byte offset is maintained and
+/// both `line` and `col` are set to `0`, which is never a valid line or column.
+#[derive(Debug, PartialEq, Eq, PartialOrd, Ord, Hash, Copy, Clone)]
 pub struct SourcePos {
     /// The byte offset since the start of parsing.
     pub byte: usize,
     /// The line offset since the start of parsing, useful for error messages.
+    /// Lines start at `1`, or are always `0` if lines are not being tracked.
     pub line: usize,
     /// The column offset since the start of parsing, useful for error messages.
+    /// Columns are counted in characters, not bytes, and start at `1`, or are
+    /// always `0` if columns are not being tracked.
     pub col: usize,
 }
 
@@ -69,30 +102,144 @@ impl SourcePos {
         }
     }
 
+    /// Constructs a new, starting, source position which only tracks the byte
+    /// offset, leaving `line` and `col` set to `0` as it advances.
+    pub fn new_byte_only() -> SourcePos {
+        SourcePos {
+            byte: 0,
+            line: 0,
+            col: 0,
+        }
+    }
+
+    /// Returns `false` if this position only tracks its byte offset, in which
+    /// case `line` and `col` are not meaningful.
+    pub fn tracks_line_col(&self) -> bool {
+        self.line != 0
+    }
+
     /// Increments self using the length of the provided token.
+    ///
+    /// The byte offset is advanced by the token's length in bytes, while the
+    /// column is advanced by the number of characters in the token.
     pub fn advance(&mut self, next: &Token) {
-        let newlines = match *next {
+        self.byte += next.len();
+        if !self.tracks_line_col() {
+            return;
+        }
+
+        let (newlines, tok_chars) = match *next {
             // Most of these should not have any newlines
             // embedded within them, but permitting external
             // tokenizers means we should sanity check anyway.
             Name(ref s) | Literal(ref s) | Whitespace(ref s) => {
-                s.chars().filter(|&c| c == '\n').count()
+                (s.chars().filter(|&c| c == '\n').count(), s.chars().count())
             }
 
-            Newline => 1,
-            _ => 0,
+            Newline => (1, 1),
+            // All other tokens are made up of ASCII characters
+            _ => (0, next.len()),
         };
 
-        let tok_len = next.len();
-        self.byte += tok_len;
         self.line += newlines;
-        self.col = if newlines == 0 { self.col + tok_len } else { 1 };
+        self.col = if newlines == 0 {
+            self.col + tok_chars
+        } else {
+            1
+        };
     }
 
     /// Increments self by `num_tab` tab characters
     fn advance_tabs(&mut self, num_tab: usize) {
         self.byte += num_tab;
-        self.col += num_tab;
+        if self.tracks_line_col() {
+            self.col += num_tab;
+        }
+    }
+}
+
+/// An iterator adapter which pairs each token of the underlying iterator
+/// (e.g. a `Lexer`) with the position in the source at which it starts.
+#[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
+#[derive(Debug, Clone)]
+pub struct PositionedTokens<I> {
+    iter: I,
+    pos: SourcePos,
+}
+
+impl<I: Iterator<Item = Token>> PositionedTokens<I> {
+    /// Creates a new adapter over any token iterator, starting at the beginning of the source.
+    pub fn new<T>(iter: T) -> Self
+    where
+        T: IntoIterator<Item = Token, IntoIter = I>,
+    {
+        PositionedTokens {
+            iter: iter.into_iter(),
+            pos: SourcePos::new(),
+        }
+    }
+
+    /// Returns the position immediately after the last yielded token.
+    pub fn pos(&self) -> SourcePos {
+        self.pos
+    }
+}
+
+impl<I: Iterator<Item = Token>> Iterator for PositionedTokens<I> {
+    type Item = (Token, SourcePos);
+
+    fn next(&mut self) -> Option<Self::Item> {
+        let tok = self.iter.next()?;
+        let pos = self.pos;
+        self.pos.advance(&tok);
+        Some((tok, pos))
+    }
+
+    fn size_hint(&self) -> (usize, Option<usize>) {
+        self.iter.size_hint()
+    }
+}
+
+/// The kind of quoting which was left unterminated, as reported by
+/// `ParseError::UnterminatedQuote`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum QuoteKind {
+    /// A single quoted string, e.g. `'foo'`.
+    Single,
+    /// A double quoted string, e.g. `"foo"`.
+    Double,
+    /// A single quoted string immediately preceded by a `$`, e.g. `$'foo'`.
+    DollarSingle,
+}
+
+impl fmt::Display for QuoteKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            QuoteKind::Single => fmt.write_str("single quote"),
+            QuoteKind::Double => fmt.write_str("double quote"),
+            QuoteKind::DollarSingle => fmt.write_str("`$'` quote"),
+        }
+    }
+}
+
+/// A reserved word or kind of token which would have been valid where parsing
+/// failed, as reported by `ParseError::Expected`.
+#[derive(Debug, Clone, Copy, PartialEq, Eq, Hash)]
+pub enum Expectation {
+    /// A reserved word, e.g. `do`.
+    ReservedWord(&'static str),
+    /// Any token of the given kind, e.g. a `Name` or a `)`.
+    Token(TokenKind),
+}
+
+impl fmt::Display for Expectation {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        match *self {
+            Expectation::ReservedWord(word) => write!(fmt, "`{}`", word),
+            Expectation::Token(TokenKind::Newline) => fmt.write_str("`\\n`"),
+            Expectation::Token(kind) if kind.has_payload() => write!(fmt, "{}", kind),
+            Expectation::Token(kind) => write!(fmt, "`{}`", kind),
+        }
     }
 }
 
@@ -109,6 +256,16 @@ pub enum ParseError<T> {
     /// Encountered EOF while looking for a match for the specified token.
     /// Stores position of opening token.
     Unmatched(Token, SourcePos),
+    /// Encountered EOF within a quoted string. Stores the kind of quoting, the
+    /// position of the opening quote, and the position where the input ended.
+    UnterminatedQuote {
+        /// The kind of quoting which was never closed.
+        kind: QuoteKind,
+        /// The position of the opening quote (or of the `$` in `$'`).
+        open: SourcePos,
+        /// The position at which the input ended.
+        end: SourcePos,
+    },
     /// Did not find a reserved keyword within a command. The first String is the
     /// command being parsed, followed by the position of where it starts. Next
     /// is the missing keyword followed by the position of where the parse
@@ -116,10 +273,63 @@ pub enum ParseError<T> {
     IncompleteCmd(&'static str, SourcePos, &'static str, SourcePos),
     /// Encountered a token not appropriate for the current context.
     Unexpected(Token, SourcePos),
+    /// Did not find any of the tokens or reserved words which would have been
+    /// valid at the current position. Stores the expected candidates, the token
+    /// which was found instead (or `None` if at the end of input), and its position.
+    Expected(Vec<Expectation>, Option<Token>, SourcePos),
     /// Encountered the end of input while expecting additional tokens.
     UnexpectedEOF,
+    /// Commands, substitutions, or arithmetic expressions were nested deeper
+    /// than the parser's configured limit. Stores the position where the limit
+    /// was exceeded.
+    NestingTooDeep(SourcePos),
     /// A custom error returned by the AST builder.
     Custom(T),
+    /// A custom error returned by the AST builder, along with the position at
+    /// which the source it was building (e.g. a command or word) starts.
+    CustomAt(T, SourcePos),
+}
+
+impl<T> ParseError<T> {
+    /// Returns the position at which the error was encountered, if any.
+    ///
+    /// For `IncompleteCmd` this is the position where the missing keyword was
+    /// expected, rather than the start of the command.
+    pub fn primary_pos(&self) -> Option<SourcePos> {
+        self.span().map(|(start, _)| start)
+    }
+
+    /// Returns the start and end positions of the source which caused the
+    /// error, if any.
+    ///
+    /// Errors which record a single token span from the token's position up
+    /// to the position just past it. `IncompleteCmd` spans from the point
+    /// where the missing keyword was expected; the command's own starting
+    /// position is left to the variant itself.
+    pub fn span(&self) -> Option<(SourcePos, SourcePos)> {
+        let token_span = |tok: &Token, pos: SourcePos| {
+            let mut end = pos;
+            end.advance(tok);
+            (pos, end)
+        };
+
+        match *self {
+            ParseError::BadFd(start, end)
+            | ParseError::UnterminatedQuote {
+                open: start, end, ..
+            } => Some((start, end)),
+            ParseError::BadIdent(ref id, pos) => Some(token_span(&Literal(id.clone()), pos)),
+            ParseError::BadSubst(ref t, pos)
+            | ParseError::Unmatched(ref t, pos)
+            | ParseError::Unexpected(ref t, pos)
+            | ParseError::Expected(_, Some(ref t), pos) => Some(token_span(t, pos)),
+            ParseError::IncompleteCmd(_, _, _, pos)
+            | ParseError::Expected(_, None, pos)
+            | ParseError::NestingTooDeep(pos)
+            | ParseError::CustomAt(_, pos) => Some((pos, pos)),
+            ParseError::UnexpectedEOF | ParseError::Custom(_) => None,
+        }
+    }
 }
 
 impl<T: Error> Error for ParseError<T> {
@@ -131,10 +341,13 @@ impl<T: Error> Error for ParseError<T> {
             | ParseError::BadIdent(..)
             | ParseError::BadSubst(..)
             | ParseError::Unmatched(..)
+            | ParseError::UnterminatedQuote { .. }
             | ParseError::IncompleteCmd(..)
             | ParseError::Unexpected(..)
-            | ParseError::UnexpectedEOF => None,
-            ParseError::Custom(ref e) => Some(e),
+            | ParseError::Expected(..)
+            | ParseError::UnexpectedEOF
+            | ParseError::NestingTooDeep(..) => None,
+            ParseError::Custom(ref e) | ParseError::CustomAt(ref e, _) => Some(e),
         }
     }
 }
@@ -144,35 +357,62 @@ impl<T: fmt::Display> fmt::Display for ParseError<T> {
         match *self {
             ParseError::BadFd(ref start, ref end) => write!(
                 fmt,
-                "file descriptor found between lines {} - {} cannot possibly be a valid",
+                "file descriptor found at {}-{} cannot possibly be valid",
                 start, end
             ),
             ParseError::BadIdent(ref id, pos) => {
-                write!(fmt, "not a valid identifier {}: {}", pos, id)
+                write!(fmt, "not a valid identifier at {}: {}", pos, id)
             }
             ParseError::BadSubst(ref t, pos) => {
-                write!(fmt, "bad substitution {}: invalid token: {}", pos, t)
+                write!(fmt, "bad substitution at {}: invalid token: {}", pos, t)
             }
             ParseError::Unmatched(ref t, pos) => {
-                write!(fmt, "unmatched `{}` starting on line {}", t, pos)
+                write!(fmt, "unmatched `{}` starting at {}", t, pos)
+            }
+            ParseError::UnterminatedQuote { kind, open, .. } => {
+                write!(fmt, "{} opened at {} never closed", kind, open)
             }
 
             ParseError::IncompleteCmd(c, start, kw, kw_pos) => write!(
                 fmt,
-                "did not find `{}` keyword on line {}, in `{}` command which starts on line {}",
+                "did not find `{}` keyword at {}, in `{}` command which starts at {}",
                 kw, kw_pos, c, start
             ),
 
             // When printing unexpected newlines, print \n instead to avoid confusingly formatted messages
             ParseError::Unexpected(Newline, pos) => {
-                write!(fmt, "found unexpected token on line {}: \\n", pos)
+                write!(fmt, "found unexpected token at {}: \\n", pos)
             }
             ParseError::Unexpected(ref t, pos) => {
-                write!(fmt, "found unexpected token on line {}: {}", pos, t)
+                write!(fmt, "found unexpected token at {}: {}", pos, t)
+            }
+
+            ParseError::Expected(ref expected, ref found, pos) => {
+                match expected.split_last() {
+                    Some((last, [])) => write!(fmt, "expected {} at {}", last, pos)?,
+                    Some((last, rest)) => {
+                        write!(fmt, "expected one of ")?;
+                        for e in rest {
+                            write!(fmt, "{}, ", e)?;
+                        }
+                        write!(fmt, "or {} at {}", last, pos)?;
+                    }
+                    None => write!(fmt, "expected additional tokens at {}", pos)?,
+                }
+
+                match *found {
+                    Some(Newline) => fmt.write_str(", found: \\n"),
+                    Some(ref t) => write!(fmt, ", found: {}", t),
+                    None => fmt.write_str(", found end of input"),
+                }
             }
 
             ParseError::UnexpectedEOF => fmt.write_str("unexpected end of input"),
+            ParseError::NestingTooDeep(pos) => {
+                write!(fmt, "exceeded the maximum nesting depth at {}", pos)
+            }
             ParseError::Custom(ref e) => write!(fmt, "{}", e),
+            ParseError::CustomAt(ref e, pos) => write!(fmt, "{} at {}", e, pos),
         }
     }
 }
@@ -185,7 +425,11 @@ impl<T> From<T> for ParseError<T> {
 
 impl fmt::Display for SourcePos {
     fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
-        write!(fmt, "{}:{}", self.line, self.col)
+        if self.tracks_line_col() {
+            write!(fmt, "{}:{}", self.line, self.col)
+        } else {
+            write!(fmt, "byte {}", self.byte)
+        }
     }
 }
 
@@ -193,6 +437,7 @@ impl fmt::Display for SourcePos {
 #[derive(Debug, PartialEq, Eq, Copy, Clone)]
 enum CompoundCmdKeyword {
     For,
+    ArithFor,
     Case,
     If,
     While,
@@ -202,7 +447,7 @@ enum CompoundCmdKeyword {
 }
 
 /// Used to configure when `Parser::command_group` stops parsing commands.
-#[derive(Debug, PartialEq, Eq, Clone)]
+#[derive(Debug, Default, PartialEq, Eq, Clone)]
 pub struct CommandGroupDelimiters<'a, 'b, 'c> {
     /// Any token which appears after a complete command separator (e.g. `;`, `&`, or a
     /// newline) will be considered a delimeter for the command group.
@@ -214,14 +459,126 @@ pub struct CommandGroupDelimiters<'a, 'b, 'c> {
     pub exact_tokens: &'c [Token],
 }
 
-impl Default for CommandGroupDelimiters<'static, 'static, 'static> {
+/// A complete command along with the comments attached to it, as returned by
+/// `Parser::complete_command_with_leading_comments`.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct CommentedCommand<C> {
+    /// The comments (and blank lines) appearing before the command.
+    pub leading_comments: Vec<builder::Newline>,
+    /// The command which was parsed.
+    pub command: C,
+    /// A comment appearing at the end of the command, yet still on the same line.
+    pub trailing_comment: Option<builder::Newline>,
+}
+
+/// Configures which reserved words and tokens a `Parser` will recognize.
+///
+/// Removing a reserved word (e.g. `function`) causes the parser to treat it
+/// like any other word, while adding a reserved word the parser does not
+/// know how to handle (e.g. `select`) causes the parser to reject any
+/// command which begins with it, instead of silently treating it as a
+/// simple command.
+#[derive(Debug, PartialEq, Eq, Clone)]
+pub struct ParserConfig {
+    /// Words which have a special meaning when they appear unquoted where
+    /// a command (or a part of a compound command) is expected.
+    pub reserved_words: Vec<&'static str>,
+    /// Tokens which have a special meaning when they appear as standalone
+    /// words, e.g. `{` or `!`. A `}` is only special when closing a brace
+    /// group, thus it is recognized whenever `{` is reserved.
+    pub reserved_tokens: Vec<Token>,
+    /// Allow the bodies of `then`, `else`, and `do` to be empty (e.g. `if x; then; fi`),
+    /// which POSIX does not permit, but some shells do. Disabled by default.
+    pub allow_empty_bodies: bool,
+    /// The maximum depth to which commands, substitutions, and arithmetic expressions
+    /// may be nested before parsing fails with `ParseError::NestingTooDeep`.
+    /// Guards against exhausting the stack when parsing untrusted input.
+    pub max_nesting_depth: usize,
+    /// Only recognize `#` as the start of a comment where a command may begin
+    /// (e.g. at the start of a line or after a separator), treating it as a
+    /// literal anywhere within a simple command, such as `echo foo #bar`.
+    /// Disabled by default.
+    pub comment_only_at_line_start: bool,
+    /// Builtin commands whose arguments of the form `name=value` are parsed as
+    /// variable assignments (e.g. `export foo=~/bar`) instead of regular words.
+    pub assignment_builtins: Vec<&'static str>,
+    /// Track the line and column of every position reported by the parser.
+    /// When disabled, positions only carry a valid byte offset (with `line` and
+    /// `col` left as `0`), which avoids counting characters in every token.
+    /// Enabled by default.
+    pub track_line_col: bool,
+    /// Report a `ParseError::BadFd` when a word which is known to not be a valid
+    /// file descriptor immediately precedes a redirection, e.g. `abc<>file`, instead
+    /// of treating it as a separate word. Words containing parameters or substitutions
+    /// (e.g. `$fd<>file`) can't be checked until they are expanded, and are always
+    /// treated as separate words. Disabled by default.
+    pub strict_redirect_fds: bool,
+    /// Parse `$'...'` as a single ANSI-C quoted string (as bash and other shells do),
+    /// replacing escape sequences such as `\n` or `\t` with the characters they
+    /// represent. When disabled, the `$` is kept as a literal which is followed by a
+    /// regular single quoted string, as required by POSIX. Disabled by default.
+    pub ansi_c_quoting: bool,
+    /// Treat a standalone `^` as a pipe, e.g. `a ^ b` behaves like `a | b`, as some
+    /// very old Bourne shells did. A `^` within a larger word (e.g. `a^b`) remains
+    /// a literal. Disabled by default.
+    pub caret_is_pipe: bool,
+    /// Report a `ParseError::Unexpected` when a standalone `}` appears where a command
+    /// is expected but does not close a brace group, e.g. `foo; }`, as bash does.
+    /// When disabled, such a `}` is treated as a regular word. Disabled by default.
+    pub strict_close_brace: bool,
+    /// Parse `&> file`, `&>> file`, and `&>| file` as redirecting both standard output
+    /// and standard error to a file, as bash does. When disabled, the `&` runs the
+    /// preceding command in the background as required by POSIX, e.g. `foo &>bar`
+    /// is treated as `foo &` followed by `>bar`. Disabled by default.
+    pub ampersand_redirects: bool,
+    /// Accept the bash `;&` and `;;&` terminators of `case` arms, which respectively
+    /// execute the body of the next arm without matching its patterns, or continue
+    /// matching the patterns of the following arms. When disabled, they are reported
+    /// as `ParseError::Unexpected` as required by POSIX. Disabled by default.
+    pub case_fallthrough: bool,
+}
+
+impl Default for ParserConfig {
     fn default() -> Self {
-        CommandGroupDelimiters {
-            reserved_tokens: &[],
-            reserved_words: &[],
-            exact_tokens: &[],
+        Self::new()
+    }
+}
+
+impl ParserConfig {
+    /// Constructs a configuration which recognizes the default reserved
+    /// words and tokens of the POSIX shell grammar.
+    pub fn new() -> Self {
+        ParserConfig {
+            reserved_words: DEFAULT_RESERVED_WORDS.to_vec(),
+            reserved_tokens: DEFAULT_RESERVED_TOKENS.to_vec(),
+            allow_empty_bodies: false,
+            max_nesting_depth: DEFAULT_MAX_NESTING_DEPTH,
+            comment_only_at_line_start: false,
+            assignment_builtins: DEFAULT_ASSIGNMENT_BUILTINS.to_vec(),
+            track_line_col: true,
+            strict_redirect_fds: false,
+            ansi_c_quoting: false,
+            caret_is_pipe: false,
+            strict_close_brace: false,
+            ampersand_redirects: false,
+            case_fallthrough: false,
         }
     }
+
+    /// Checks if the specified word is configured to be reserved.
+    pub fn is_reserved_word(&self, word: &str) -> bool {
+        self.reserved_words.contains(&word)
+    }
+
+    /// Checks if the specified token is configured to be reserved.
+    pub fn is_reserved_token(&self, token: &Token) -> bool {
+        self.reserved_tokens.contains(token)
+    }
+
+    /// Checks if the specified word is configured to be an assignment builtin.
+    pub fn is_assignment_builtin(&self, word: &str) -> bool {
+        self.assignment_builtins.contains(&word)
+    }
 }
 
 /// An `Iterator` adapter around a `Parser`.
@@ -358,10 +715,19 @@ where
 /// yields the tokens `[Backslash, DLess]`, the parser will treat the source as if
 /// it were `[Literal(<<)]`. The lexer's behavior need not be consistent between different
 /// multi-char tokens, as long as it is aware of the implications.
-#[derive(Debug)]
+///
+/// A parser can be cloned if both its token iterator and builder can be, which
+/// allows for speculatively parsing ahead and later resuming from the original.
+#[derive(Debug, Clone)]
 pub struct Parser<I, B> {
     iter: TokenIterWrapper<I>,
     builder: B,
+    config: ParserConfig,
+    /// How deeply nested the construct currently being parsed is.
+    depth: usize,
+    /// Whether a simple command currently being parsed has consumed any words,
+    /// assignments, or redirects.
+    in_simple_command: bool,
 }
 
 impl<I: Iterator<Item = Token>, B: Builder + Default> Parser<I, B> {
@@ -448,47 +814,180 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         })
     }
 
+    /// Construct an `Expected` error listing the tokens or reserved words which would
+    /// have been valid here. The next token in the iterator (if any) is reported as found.
+    #[inline]
+    fn make_expected_err(&mut self, expected: &[Expectation]) -> ParseError<B::Error> {
+        let pos = self.iter.pos();
+        ParseError::Expected(expected.to_vec(), self.iter.next(), pos)
+    }
+
+    /// Runs the provided parse function one nesting level deeper, failing with
+    /// `NestingTooDeep` instead if doing so would exceed the configured limit.
+    #[inline]
+    fn nested<T, F>(&mut self, parse: F) -> ParseResult<T, B::Error>
+    where
+        F: FnOnce(&mut Self) -> ParseResult<T, B::Error>,
+    {
+        if self.depth >= self.config.max_nesting_depth {
+            return Err(ParseError::NestingTooDeep(self.iter.pos()));
+        }
+
+        self.depth += 1;
+        let ret = parse(self);
+        self.depth -= 1;
+        ret
+    }
+
     /// Creates a new Parser from a Token iterator and provided AST builder.
     pub fn with_builder(iter: I, builder: B) -> Self {
+        Parser::with_config(iter, builder, ParserConfig::default())
+    }
+
+    /// Creates a new Parser from a Token iterator, provided AST builder,
+    /// and a configuration of which reserved words and tokens to recognize.
+    pub fn with_config(iter: I, builder: B, config: ParserConfig) -> Self {
+        let start_pos = if config.track_line_col {
+            SourcePos::new()
+        } else {
+            SourcePos::new_byte_only()
+        };
+
         Parser {
-            iter: TokenIterWrapper::Regular(TokenIter::new(iter)),
+            iter: TokenIterWrapper::Regular(TokenIter::with_position(iter, start_pos)),
             builder,
+            config,
+            depth: 0,
+            in_simple_command: false,
         }
     }
 
+    /// Resets the parser to begin parsing a new source, keeping its builder
+    /// and configuration and reusing any internal buffers.
+    ///
+    /// Any tokens which remained unconsumed from the previous source are discarded
+    /// and positions are reported from the start of the new source.
+    pub fn reset<T>(&mut self, iter: T)
+    where
+        T: IntoIterator<Item = Token, IntoIter = I>,
+    {
+        self.iter.reset(iter.into_iter());
+        self.depth = 0;
+        self.in_simple_command = false;
+    }
+
+    /// Returns the configuration the parser is using.
+    pub fn config(&self) -> &ParserConfig {
+        &self.config
+    }
+
     /// Returns the parser's current position in the source.
     pub fn pos(&self) -> SourcePos {
         self.iter.pos()
     }
 
+    /// Skips past the next `;`, `&`, or newline which separates commands at the
+    /// top level, allowing a caller which performs its own error recovery to
+    /// resume parsing with the following command.
+    ///
+    /// Quoted strings, substitutions, and parenthesized groups are skipped as a
+    /// whole, so any separators within them are ignored. Likewise, a comment is
+    /// skipped through to the end of its line. If no separator is found the rest
+    /// of the input is consumed.
+    ///
+    /// Note that heredoc bodies are not recognized: if the skipped command began
+    /// a heredoc (e.g. `cat <<EOF`), the lines of its body will be parsed as
+    /// commands once parsing resumes.
+    pub fn skip_to_next_command(&mut self) {
+        self.in_simple_command = false;
+
+        let mut word_start = true;
+        while let Some(tok) = self.iter.peek() {
+            match *tok {
+                Semi | Amp | Newline | SemiAmp | DSemiAmp => {
+                    self.iter.next();
+                    return;
+                }
+
+                AmpGreat | AmpDGreat | AmpClobber if !self.config.ampersand_redirects => {
+                    self.split_ampersand_redirect();
+                    self.iter.next();
+                    return;
+                }
+
+                // A comment may contain separators, but only ends at a newline
+                Pound if word_start => {
+                    self.iter
+                        .by_ref()
+                        .take_while(|t| t != &Newline)
+                        .for_each(drop);
+                    return;
+                }
+
+                _ => {}
+            }
+
+            word_start = tok.is_word_delimiter();
+            for t in self.iter.balanced() {
+                // An unbalanced token means we have reached the end of the input
+                if t.is_err() {
+                    return;
+                }
+            }
+        }
+    }
+
     /// Parses a single complete command.
     ///
     /// For example, `foo && bar; baz` will yield two complete
     /// commands: `And(foo, bar)`, and `Simple(baz)`.
     pub fn complete_command(&mut self) -> ParseResult<Option<B::Command>, B::Error> {
-        let pre_cmd_comments = self.linebreak();
+        self.complete_command_with_leading_comments()
+            .map(|c| c.map(|c| c.command))
+    }
+
+    /// Parses a single complete command like `Parser::complete_command`, but also
+    /// returns the comments attached to it, even if the builder discards them.
+    ///
+    /// For example, `# lead\nfoo # trail` yields `foo` with a leading `# lead`
+    /// comment and a trailing `# trail` comment. Any comments which are not
+    /// followed by a command are handed to the builder as usual, and `None` is returned.
+    pub fn complete_command_with_leading_comments(
+        &mut self,
+    ) -> ParseResult<Option<CommentedCommand<B::Command>>, B::Error> {
+        let start_pos = self.iter.pos();
+        let leading_comments = self.linebreak();
 
         if self.iter.peek().is_some() {
-            Ok(Some(self.complete_command_with_leading_comments(
-                pre_cmd_comments,
-            )?))
+            let (command, trailing_comment) =
+                self.complete_command_after_comments(leading_comments.clone())?;
+            Ok(Some(CommentedCommand {
+                leading_comments,
+                command,
+                trailing_comment,
+            }))
         } else {
-            if !pre_cmd_comments.is_empty() {
-                self.builder.comments(pre_cmd_comments)?;
+            if !leading_comments.is_empty() {
+                self.builder
+                    .comments(leading_comments)
+                    .map_err(custom_at(start_pos))?;
             }
             Ok(None)
         }
     }
 
     /// Parses a single complete command, but expects caller to parse any leading comments.
+    /// Returns the command along with any comment which appeared after it on the same line.
     ///
     /// It is considered an error there is not a valid complete command to be parsed, thus
     /// the caller should perform any EOF checks.
-    fn complete_command_with_leading_comments(
+    fn complete_command_after_comments(
         &mut self,
         pre_cmd_comments: Vec<builder::Newline>,
-    ) -> ParseResult<B::Command, B::Error> {
+    ) -> ParseResult<(B::Command, Option<builder::Newline>), B::Error> {
+        let start_pos = self.iter.pos();
         let cmd = self.and_or_list()?;
+        self.split_case_fallthrough();
 
         let (sep, cmd_comment) = eat_maybe!(self, {
             Semi => { (builder::SeparatorKind::Semi, self.newline()) },
@@ -501,9 +1000,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         });
 
-        Ok(self
+        let cmd = self
             .builder
-            .complete_command(pre_cmd_comments, cmd, sep, cmd_comment)?)
+            .complete_command(pre_cmd_comments, cmd, sep, cmd_comment.clone())
+            .map_err(custom_at(start_pos))?;
+        Ok((cmd, cmd_comment))
     }
 
     /// Parses compound AND/OR commands.
@@ -511,6 +1012,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// Commands are left associative. For example `foo || bar && baz`
     /// parses to `And(Or(foo, bar), baz)`.
     pub fn and_or_list(&mut self) -> ParseResult<B::CommandList, B::Error> {
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
         let first = self.pipeline()?;
         let mut rest = Vec::new();
 
@@ -534,7 +1037,9 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             rest.push((post_sep_comments, next));
         }
 
-        Ok(self.builder.and_or_list(first, rest)?)
+        self.builder
+            .and_or_list(first, rest)
+            .map_err(custom_at(start_pos))
     }
 
     /// Parses either a single command or a pipeline of commands.
@@ -542,38 +1047,66 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// For example `[!] foo | bar`.
     pub fn pipeline(&mut self) -> ParseResult<B::ListableCommand, B::Error> {
         self.skip_whitespace();
-        let bang = eat_maybe!(self, {
-            Bang => { true };
-            _ => { false },
-        });
+        let start_pos = self.iter.pos();
+        let bang_reserved = self.config.is_reserved_token(&Bang);
+        let bang = bang_reserved
+            && eat_maybe!(self, {
+                Bang => { true };
+                _ => { false },
+            });
 
         let mut cmds = Vec::new();
         loop {
             // We've already passed an apropriate spot for !, so it
             // is an error if it appears before the start of a command.
-            if let Some(&Bang) = self.iter.peek() {
+            if bang_reserved && Some(&Bang) == self.iter.peek() {
                 return Err(self.make_unexpected_err());
             }
 
             let cmd = self.command()?;
 
-            eat_maybe!(self, {
-                Pipe => { cmds.push((self.linebreak(), cmd)) };
-                _ => {
-                    cmds.push((Vec::new(), cmd));
-                    break;
-                },
-            });
+            let is_pipe = match self.iter.peek() {
+                Some(&Pipe) => true,
+                Some(&Caret) => self.config.caret_is_pipe,
+                _ => false,
+            };
+
+            if is_pipe {
+                self.iter.next();
+                cmds.push((self.linebreak(), cmd));
+            } else {
+                cmds.push((Vec::new(), cmd));
+                break;
+            }
         }
 
-        Ok(self.builder.pipeline(bang, cmds)?)
+        self.builder
+            .pipeline(bang, cmds)
+            .map_err(custom_at(start_pos))
     }
 
     /// Parses any compound or individual command.
     pub fn command(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
+        self.nested(Self::command_internal)
+    }
+
+    /// Like `Parser::command`, but does not track the nesting depth.
+    fn command_internal(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
         if let Some(kw) = self.next_compound_command_type() {
+            let start_pos = self.iter.pos();
             let compound = self.compound_command_internal(Some(kw))?;
-            Ok(self.builder.compound_command_into_pipeable(compound)?)
+            self.builder
+                .compound_command_into_pipeable(compound)
+                .map_err(custom_at(start_pos))
+        } else if self.peek_unsupported_keyword() {
+            Err(self.make_unexpected_err())
+        } else if self.config.strict_close_brace
+            && self.peek_reserved_token(&[CurlyClose]).is_some()
+        {
+            // Any `}` which closes a brace group is consumed before we get here
+            Err(self.make_unexpected_err())
+        } else if self.peek_keyword(&[COPROC]).is_some() {
+            self.coproc_command()
         } else if let Some(fn_def) = self.maybe_function_declaration()? {
             Ok(fn_def)
         } else {
@@ -586,47 +1119,43 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// A valid command is expected to have at least an executable name, or a single
     /// variable assignment or redirection. Otherwise an error will be returned.
     pub fn simple_command(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
+        // Simple commands may be nested within each other's words (e.g. via
+        // command substitutions), so the outer state must be restored afterwards.
+        let outer = mem::replace(&mut self.in_simple_command, false);
+        let ret = self.simple_command_inner();
+        self.in_simple_command = outer;
+        ret
+    }
+
+    /// Like `Parser::simple_command`, but does not save and restore whether
+    /// an outer simple command is being parsed.
+    fn simple_command_inner(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
         use crate::ast::{RedirectOrCmdWord, RedirectOrEnvVar};
 
+        let start_pos = self.iter.pos();
         let mut vars = Vec::new();
         let mut cmd_args = Vec::new();
+        let mut is_assignment_builtin;
 
         loop {
-            self.skip_whitespace();
-            let is_name = {
-                let mut peeked = self.iter.multipeek();
-                if let Some(&Name(_)) = peeked.peek_next() {
-                    Some(&Equals) == peeked.peek_next()
-                } else {
-                    false
-                }
-            };
-
-            if is_name {
-                if let Some(Name(var)) = self.iter.next() {
-                    self.iter.next(); // Consume the =
+            if let Some((var, value)) = self.assignment()? {
+                vars.push(RedirectOrEnvVar::EnvVar(var, value));
+                self.in_simple_command = true;
 
-                    let value = if let Some(&Whitespace(_)) = self.iter.peek() {
-                        None
-                    } else {
-                        self.word()?
-                    };
-                    vars.push(RedirectOrEnvVar::EnvVar(var, value));
-
-                    // Make sure we continue checking for assignments,
-                    // otherwise it they can be interpreted as literal words.
-                    continue;
-                } else {
-                    unreachable!();
-                }
+                // Make sure we continue checking for assignments,
+                // otherwise it they can be interpreted as literal words.
+                continue;
             }
 
+            is_assignment_builtin = self.peek_assignment_builtin();
+
             // If we find a redirect we should keep checking for
             // more redirects or assignments. Otherwise we will either
             // run into the command name or the end of the simple command.
             let exec = match self.redirect()? {
                 Some(Ok(redirect)) => {
                     vars.push(RedirectOrEnvVar::Redirect(redirect));
+                    self.in_simple_command = true;
                     continue;
                 }
                 Some(Err(w)) => w,
@@ -636,14 +1165,43 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             // Since there are no more assignments or redirects present
             // it must be the first real word, and thus the executable name.
             cmd_args.push(RedirectOrCmdWord::CmdWord(exec));
+            self.in_simple_command = true;
             break;
         }
 
         let vars = vars;
 
         // Now that all assignments are taken care of, any other occurances of `=` will be
-        // treated as literals when we attempt to parse a word out.
+        // treated as literals when we attempt to parse a word out, unless the command is
+        // an assignment builtin (e.g. `export`) whose arguments may also be assignments.
+        // Such builtins may also take attribute options (e.g. `declare -i`) before any
+        // other argument.
+        let mut options_allowed = is_assignment_builtin;
         loop {
+            if is_assignment_builtin {
+                match self.peek_option_flags() {
+                    Some(flags) if options_allowed => {
+                        if let Some(attrs) = ast::VarAttributes::from_flags(&flags) {
+                            self.iter.next(); // Consume the -
+                            self.iter.next(); // Consume the flags
+                            cmd_args.push(RedirectOrCmdWord::Attributes(attrs));
+                            continue;
+                        }
+                    }
+                    _ => options_allowed = false,
+                }
+
+                if let Some((var, value)) = self.assignment()? {
+                    cmd_args.push(RedirectOrCmdWord::Assignment(var, value));
+                    continue;
+                }
+
+                if let Some(var) = self.declaration() {
+                    cmd_args.push(RedirectOrCmdWord::Declaration(var));
+                    continue;
+                }
+            }
+
             match self.redirect()? {
                 Some(Ok(redirect)) => cmd_args.push(RedirectOrCmdWord::Redirect(redirect)),
                 Some(Err(w)) => cmd_args.push(RedirectOrCmdWord::CmdWord(w)),
@@ -656,7 +1214,103 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         if vars.is_empty() && cmd_args.is_empty() {
             Err(self.make_unexpected_err())
         } else {
-            Ok(self.builder.simple_command(vars, cmd_args)?)
+            self.builder
+                .simple_command(vars, cmd_args)
+                .map_err(custom_at(start_pos))
+        }
+    }
+
+    /// Parses a variable assignment, e.g. `foo=bar`, if one is present,
+    /// skipping any leading whitespace.
+    #[allow(clippy::type_complexity)]
+    fn assignment(&mut self) -> ParseResult<Option<(String, Option<B::Word>)>, B::Error> {
+        self.skip_whitespace();
+        let is_name = {
+            let mut peeked = self.iter.multipeek();
+            if let Some(&Name(_)) = peeked.peek_next() {
+                Some(&Equals) == peeked.peek_next()
+            } else {
+                false
+            }
+        };
+
+        if !is_name {
+            return Ok(None);
+        }
+
+        let var = match self.iter.next() {
+            Some(Name(var)) => var,
+            _ => unreachable!(),
+        };
+        self.iter.next(); // Consume the =
+
+        let value = if let Some(&Whitespace(_)) = self.iter.peek() {
+            None
+        } else {
+            self.assignment_value()?
+        };
+
+        Ok(Some((var, value)))
+    }
+
+    /// Parses a variable name which is not followed by an `=`, e.g. the `foo` in
+    /// `export foo`, if one is present, skipping any leading whitespace.
+    fn declaration(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let is_name = {
+            let mut peeked = self.iter.multipeek();
+            if let Some(&Name(_)) = peeked.peek_next() {
+                ends_word(peeked.peek_next())
+            } else {
+                false
+            }
+        };
+
+        if !is_name {
+            return None;
+        }
+
+        match self.iter.next() {
+            Some(Name(var)) => Some(var),
+            _ => unreachable!(),
+        }
+    }
+
+    /// Checks if the next word is an option made up solely of letters, e.g. `-ir`,
+    /// and returns its letters without consuming anything, skipping any leading
+    /// whitespace.
+    fn peek_option_flags(&mut self) -> Option<String> {
+        self.skip_whitespace();
+        let mut peeked = self.iter.multipeek();
+        if Some(&Dash) != peeked.peek_next() {
+            return None;
+        }
+
+        let flags = match peeked.peek_next() {
+            Some(Name(flags)) if flags.chars().all(|c| c.is_ascii_alphabetic()) => flags.clone(),
+            _ => return None,
+        };
+
+        if ends_word(peeked.peek_next()) {
+            Some(flags)
+        } else {
+            None
+        }
+    }
+
+    /// Checks if the next word is exactly one of the configured assignment builtins
+    /// (e.g. `export`), without consuming anything.
+    fn peek_assignment_builtin(&mut self) -> bool {
+        self.skip_whitespace();
+        let mut peeked = self.iter.multipeek();
+        let found = match peeked.peek_next() {
+            Some(&Name(ref w)) | Some(&Literal(ref w)) => self.config.is_assignment_builtin(w),
+            _ => false,
+        };
+
+        match peeked.peek_next() {
+            Some(delim) => found && delim.is_word_delimiter(),
+            None => found,
         }
     }
 
@@ -690,9 +1344,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// redirect or word if either is found. In other words, `Ok(Some(Ok(redirect)))`
     /// will result if a redirect is found, `Ok(Some(Err(word)))` if a word is found,
     /// or `Ok(None)` if neither is found.
+    #[allow(clippy::type_complexity)]
     pub fn redirect(&mut self) -> ParseResult<Option<Result<B::Redirect, B::Word>>, B::Error> {
-        fn could_be_numeric<C>(word: &WordKind<C>) -> bool {
-            let simple_could_be_numeric = |word: &SimpleWordKind<C>| match *word {
+        fn could_be_numeric<C, A>(word: &WordKind<C, A>) -> bool {
+            let simple_could_be_numeric = |word: &SimpleWordKind<C, A>| match *word {
                 SimpleWordKind::Star
                 | SimpleWordKind::Question
                 | SimpleWordKind::SquareOpen
@@ -702,7 +1357,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
                 // Literals and can be statically checked if they have non-numeric characters
                 SimpleWordKind::Escaped(ref s) | SimpleWordKind::Literal(ref s) => {
-                    s.chars().all(|c| c.is_digit(10))
+                    s.chars().all(|c| c.is_ascii_digit())
                 }
 
                 // These could end up evaluating to a numeric,
@@ -714,15 +1369,17 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
             match *word {
                 Simple(ref s) => simple_could_be_numeric(s),
-                SingleQuoted(ref s) => s.chars().all(|c| c.is_digit(10)),
+                SingleQuoted(ref s) => s.chars().all(|c| c.is_ascii_digit()),
                 DoubleQuoted(ref fragments) => fragments.iter().all(simple_could_be_numeric),
+                BracketClass(..) => false,
             }
         }
 
-        fn as_num<C>(word: &ComplexWordKind<C>) -> Option<u16> {
-            match *word {
-                Single(Simple(SimpleWordKind::Literal(ref s))) => u16::from_str_radix(s, 10).ok(),
-                Single(_) => None,
+        /// Returns the literal digits making up a word, if it consists of only digits.
+        fn as_digits<C, A>(word: &ComplexWordKind<C, A>) -> Option<String> {
+            let digits = match *word {
+                Single(Simple(SimpleWordKind::Literal(ref s))) => s.clone(),
+                Single(_) => return None,
                 Concat(ref fragments) => {
                     let mut buf = String::new();
                     for w in fragments {
@@ -733,27 +1390,82 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         }
                     }
 
-                    u16::from_str_radix(&buf, 10).ok()
+                    buf
                 }
+            };
+
+            if !digits.is_empty() && digits.chars().all(|c| c.is_ascii_digit()) {
+                Some(digits)
+            } else {
+                None
             }
         }
 
-        let (src_fd, src_fd_as_word) = match self.word_preserve_trailing_whitespace_raw()? {
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
+        let word = self.word_preserve_trailing_whitespace_raw()?;
+        self.split_ampersand_redirect();
+
+        // A source descriptor which is too large (or not numeric, when being
+        // strict) is only an error if it is immediately followed by a redirection.
+        let is_redirect = matches!(
+            self.iter.peek(),
+            Some(&Less)
+                | Some(&Great)
+                | Some(&DGreat)
+                | Some(&Clobber)
+                | Some(&LessAnd)
+                | Some(&GreatAnd)
+                | Some(&LessGreat)
+                | Some(&DLess)
+                | Some(&DLessDash)
+        );
+
+        let (src_fd, src_fd_as_word) = match word {
             None => (None, None),
-            Some(w) => match as_num(&w) {
-                Some(num) => (Some(num), Some(w)),
-                None => return Ok(Some(Err(self.builder.word(w)?))),
+            Some(w) => match as_digits(&w) {
+                Some(digits) => (Some(digits.parse::<u16>()), Some(w)),
+                None => {
+                    let is_static_non_numeric = match w {
+                        Single(ref w) => !could_be_numeric(w),
+                        Concat(ref v) => !v.iter().all(could_be_numeric),
+                    };
+
+                    if is_redirect && is_static_non_numeric && self.config.strict_redirect_fds {
+                        return Err(ParseError::BadFd(start_pos, self.iter.pos()));
+                    }
+
+                    let word = self.builder.word(w).map_err(custom_at(start_pos))?;
+                    return Ok(Some(Err(word)));
+                }
             },
         };
 
+        let src_fd = match src_fd {
+            Some(Ok(fd)) => Some(fd),
+            Some(Err(_)) if is_redirect => {
+                return Err(ParseError::BadFd(start_pos, self.iter.pos()))
+            }
+            Some(Err(_)) | None => None,
+        };
+
         let redir_tok = match self.iter.peek() {
             Some(&Less) | Some(&Great) | Some(&DGreat) | Some(&Clobber) | Some(&LessAnd)
             | Some(&GreatAnd) | Some(&LessGreat) => self.iter.next().unwrap(),
 
             Some(&DLess) | Some(&DLessDash) => return Ok(Some(Ok(self.redirect_heredoc(src_fd)?))),
 
+            // Like bash, these never take a source descriptor, e.g. `2&>file`
+            // is an argument followed by a redirection.
+            Some(&AmpGreat) | Some(&AmpDGreat) | Some(&AmpClobber) if src_fd_as_word.is_none() => {
+                self.iter.next().unwrap()
+            }
+
             _ => match src_fd_as_word {
-                Some(w) => return Ok(Some(Err(self.builder.word(w)?))),
+                Some(w) => {
+                    let word = self.builder.word(w).map_err(custom_at(start_pos))?;
+                    return Ok(Some(Err(word)));
+                }
                 None => return Ok(None),
             },
         };
@@ -763,17 +1475,19 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         macro_rules! get_path {
             ($parser:expr) => {
                 match $parser.word_preserve_trailing_whitespace_raw()? {
-                    Some(p) => $parser.builder.word(p)?,
+                    Some(p) => $parser.builder.word(p).map_err(custom_at(start_pos))?,
                     None => return Err(self.make_unexpected_err()),
                 }
             };
         }
 
+        // Yields the destination of a duplication, and whether the destination
+        // should be moved (e.g. `>&2-`) rather than just duplicated.
         macro_rules! get_dup_path {
             ($parser:expr) => {{
-                let path = if $parser.peek_reserved_token(&[Dash]).is_some() {
+                let (path, is_move) = if $parser.peek_reserved_token(&[Dash]).is_some() {
                     let dash = $parser.reserved_token(&[Dash])?;
-                    Single(Simple(SimpleWordKind::Literal(dash.to_string())))
+                    (Single(Simple(SimpleWordKind::Literal(dash.to_string()))), false)
                 } else {
                     let path_start_pos = $parser.iter.pos();
                     let path = if let Some(p) = $parser.word_preserve_trailing_whitespace_raw()? {
@@ -781,17 +1495,40 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     } else {
                         return Err($parser.make_unexpected_err());
                     };
+
+                    let (path, is_move) = match path {
+                        Concat(mut v) => {
+                            let is_move = v.len() > 1
+                                && matches!(
+                                    v.last(),
+                                    Some(Simple(SimpleWordKind::Literal(ref s))) if *s == Dash.as_str()
+                                );
+
+                            if is_move {
+                                v.pop();
+                            }
+
+                            if v.len() == 1 {
+                                (Single(v.pop().unwrap()), is_move)
+                            } else {
+                                (Concat(v), is_move)
+                            }
+                        }
+                        path @ Single(_) => (path, false),
+                    };
+
                     let is_numeric = match path {
                         Single(ref p) => could_be_numeric(&p),
                         Concat(ref v) => v.iter().all(could_be_numeric),
                     };
                     if is_numeric {
-                        path
+                        (path, is_move)
                     } else {
                         return Err(ParseError::BadFd(path_start_pos, self.iter.pos()));
                     }
                 };
-                $parser.builder.word(path)?
+                let path = $parser.builder.word(path).map_err(custom_at(start_pos))?;
+                (path, is_move)
             }};
         }
 
@@ -801,14 +1538,67 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             DGreat => builder::RedirectKind::Append(src_fd, get_path!(self)),
             Clobber => builder::RedirectKind::Clobber(src_fd, get_path!(self)),
             LessGreat => builder::RedirectKind::ReadWrite(src_fd, get_path!(self)),
+            AmpGreat => builder::RedirectKind::WriteBoth(get_path!(self)),
+            AmpDGreat => builder::RedirectKind::AppendBoth(get_path!(self)),
+            AmpClobber => builder::RedirectKind::ClobberBoth(get_path!(self)),
 
-            LessAnd => builder::RedirectKind::DupRead(src_fd, get_dup_path!(self)),
-            GreatAnd => builder::RedirectKind::DupWrite(src_fd, get_dup_path!(self)),
+            LessAnd => match get_dup_path!(self) {
+                (path, false) => builder::RedirectKind::DupRead(src_fd, path),
+                (path, true) => builder::RedirectKind::MoveRead(src_fd, path),
+            },
+            GreatAnd => match get_dup_path!(self) {
+                (path, false) => builder::RedirectKind::DupWrite(src_fd, path),
+                (path, true) => builder::RedirectKind::MoveWrite(src_fd, path),
+            },
 
             _ => unreachable!(),
         };
 
-        Ok(Some(Ok(self.builder.redirect(redirect)?)))
+        let redirect = self
+            .builder
+            .redirect(redirect)
+            .map_err(custom_at(start_pos))?;
+        Ok(Some(Ok(redirect)))
+    }
+
+    /// Splits a peeked `&>`, `&>>`, or `&>|` into a `&` followed by the respective
+    /// redirection token, unless such redirections are enabled by the configuration.
+    fn split_ampersand_redirect(&mut self) {
+        if self.config.ampersand_redirects {
+            return;
+        }
+
+        let redirect = match self.iter.peek() {
+            Some(&AmpGreat) => Great,
+            Some(&AmpDGreat) => DGreat,
+            Some(&AmpClobber) => Clobber,
+            _ => return,
+        };
+
+        let pos = self.iter.pos();
+        self.iter.next();
+        self.iter
+            .buffer_tokens_to_yield_first(vec![Amp, redirect], pos);
+    }
+
+    /// Splits a peeked `;&` or `;;&` into a `;` or `;;` followed by a `&`, unless
+    /// such `case` arm terminators are enabled by the configuration, so that the
+    /// `&` is rejected just like any other misplaced `&`.
+    fn split_case_fallthrough(&mut self) {
+        if self.config.case_fallthrough {
+            return;
+        }
+
+        let terminator = match self.iter.peek() {
+            Some(&SemiAmp) => Semi,
+            Some(&DSemiAmp) => DSemi,
+            _ => return,
+        };
+
+        let pos = self.iter.pos();
+        self.iter.next();
+        self.iter
+            .buffer_tokens_to_yield_first(vec![terminator, Amp], pos);
     }
 
     /// Parses a heredoc redirection and the heredoc's body.
@@ -837,14 +1627,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// Note: this method expects that the caller provide a potential file
     /// descriptor for redirection.
     pub fn redirect_heredoc(&mut self, src_fd: Option<u16>) -> ParseResult<B::Redirect, B::Error> {
-        use std::iter::FromIterator;
-
         macro_rules! try_map {
             ($result:expr) => {
                 $result.map_err(|e: iter::UnmatchedError| ParseError::Unmatched(e.0, e.1))?
             };
         }
 
+        let start_pos = self.iter.pos();
+
         let strip_tabs = eat!(self, {
             DLess => { false },
             DLessDash => { true },
@@ -866,14 +1656,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // parsing out a word as usual), so to maintain reasonable expectations, we'll
         // do the same here.
         let mut delim_tokens = Vec::new();
-        loop {
-            // Normally parens are never part of words, but many
-            // shells permit them to be part of a heredoc delimeter.
-            if let Some(t) = self.iter.peek() {
-                if t.is_word_delimiter() && t != &ParenOpen {
-                    break;
-                }
-            } else {
+        // Normally parens are never part of words, but many
+        // shells permit them to be part of a heredoc delimeter.
+        while let Some(t) = self.iter.peek() {
+            if t.is_word_delimiter() && t != &ParenOpen {
                 break;
             }
 
@@ -958,6 +1744,13 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     delim.push_str(Backtick.as_str());
                 }
 
+                // Bash also accepts `$'...'` and `$"..."` as quoted delimeters, in which
+                // case the `$` is dropped and the rest of the word is unquoted as usual.
+                Some(Dollar) => match iter.peek() {
+                    Some(&SingleQuote) | Some(&DoubleQuote) => {}
+                    _ => delim.push_str(Dollar.as_str()),
+                },
+
                 Some(t) => delim.push_str(t.as_str()),
                 None => break,
             }
@@ -970,8 +1763,6 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         delim.shrink_to_fit();
         let (delim, quoted) = (delim, quoted);
         let delim_len = delim.len();
-        let delim_r = String::from_iter(vec![delim.as_str(), "\r"]);
-        let delim_r_len = delim_r.len();
 
         // Here we will fast-forward to the next newline and capture the heredoc's
         // body that comes after it. Then we'll store these tokens in a safe place
@@ -981,6 +1772,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // heredocs, especially if we want to keep the builder unaware of our
         // shenanigans (since it *could* be keeping some internal state of what
         // we feed it).
+        //
+        // Since the saved tokens (which may include further heredoc operators on
+        // the same line) are yielded again before the remaining input, any later
+        // heredocs will capture their bodies after this one's, effectively acting
+        // as a queue which assigns bodies in left-to-right order.
         let saved_pos = self.iter.pos();
         let mut saved_tokens = Vec::new();
         while self.iter.peek().is_some() {
@@ -1002,7 +1798,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             let mut line = Vec::new();
             'line: loop {
                 if strip_tabs {
-                    let skip_next = if let Some(&Whitespace(ref w)) = self.iter.peek() {
+                    let skip_next = if let Some(Whitespace(w)) = self.iter.peek() {
                         let stripped = w.trim_start_matches('\t');
                         let num_tabs = w.len() - stripped.len();
                         line_start_pos.advance_tabs(num_tabs);
@@ -1031,6 +1827,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     // whether it happens to be the delimeter, and append it
                     // to the body if it isn't
                     None | Some(Newline) => {
+                        // Treat \r\n as a single line terminator. Although we could lex
+                        // \r\n as a Newline token, doing so would complicate keeping track
+                        // of positions in the source, as we could have one or two byte Newlines,
+                        // so instead we drop the \r (which is lexed as whitespace) here.
+                        if next == Some(Newline) {
+                            strip_trailing_cr(&mut line);
+                        }
+
                         // Do a quick length check on the line. Odds are that heredoc lines
                         // will be much longer than the delimeter itself, and it could get
                         // slow to stringify each and every line (and alloc it in memory)
@@ -1038,20 +1842,16 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         let mut line_len = 0;
                         for t in &line {
                             line_len += t.len();
-                            if line_len > delim_r_len {
+                            if line_len > delim_len {
                                 break;
                             }
                         }
 
                         // NB A delimeter like "\eof" becomes [Name(e), Name(of)], which
                         // won't compare to [Name(eof)], forcing us to do a string comparison
-                        // NB We must also do a check using \r\n line endings. Although we could
-                        // lex \r\n as a Newline token, doing so would complicate keeping track
-                        // of positions in the source, as we could have one or two byte Newlines,
-                        // or two different tokens to deal with.
-                        if line_len == delim_len || line_len == delim_r_len {
+                        if line_len == delim_len {
                             let line_str = concat_tokens(&line);
-                            if line_str == delim || line_str == delim_r {
+                            if line_str == delim {
                                 break 'heredoc;
                             }
                         }
@@ -1096,56 +1896,255 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         };
 
-        let word = self.builder.word(body)?;
-        Ok(self
-            .builder
-            .redirect(builder::RedirectKind::Heredoc(src_fd, word))?)
+        let word = self
+            .builder
+            .word(body)
+            .map_err(custom_at(heredoc_start_pos))?;
+        self.builder
+            .redirect(builder::RedirectKind::Heredoc(src_fd, word))
+            .map_err(custom_at(start_pos))
+    }
+
+    /// Parses a whitespace delimited chunk of text, honoring space quoting rules,
+    /// and skipping leading and trailing whitespace.
+    ///
+    /// Since there are a large number of possible tokens that constitute a word,
+    /// (such as literals, paramters, variables, etc.) the caller may not know
+    /// for sure whether to expect a word, thus an optional result is returned
+    /// in the event that no word exists.
+    ///
+    /// Note that an error can still arise if partial tokens are present
+    /// (e.g. malformed parameter).
+    pub fn word(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        let ret = self.word_preserve_trailing_whitespace()?;
+        self.skip_whitespace();
+        Ok(ret)
+    }
+
+    /// Parses any number of whitespace delimited words, such as the words after
+    /// the `in` of a `for` command, stopping (without consuming anything) at the
+    /// first token which cannot start a word, e.g. a separator like `;` or a newline.
+    ///
+    /// Reserved words are not recognized within the list, e.g. `a do b` yields
+    /// three words.
+    pub fn word_list(&mut self) -> ParseResult<Vec<B::Word>, B::Error> {
+        let mut words = Vec::new();
+        while let Some(w) = self.word()? {
+            words.push(w);
+        }
+        Ok(words)
+    }
+
+    /// Parses the value of a variable assignment, e.g. the `~/bin:~/lib` of `PATH=~/bin:~/lib`,
+    /// skipping any trailing whitespace.
+    ///
+    /// Unlike regular words, any unquoted `:` in the value is emitted as `SimpleWordKind::Colon`
+    /// so that the boundaries for tilde expansion can be found. A `~` within the value is only
+    /// eligible for tilde expansion if it begins the value or immediately follows a `:`, any
+    /// other `~` is treated as a literal.
+    fn assignment_value(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        let mut tilde_allowed = true;
+        let mut structure = |w| {
+            let w = match w {
+                Simple(SimpleWordKind::Literal(ref s)) if s == Colon.as_str() => {
+                    Simple(SimpleWordKind::Colon)
+                }
+                Simple(SimpleWordKind::Tilde) if !tilde_allowed => {
+                    Simple(SimpleWordKind::Literal(Tilde.to_string()))
+                }
+                w => w,
+            };
+            tilde_allowed = matches!(w, Simple(SimpleWordKind::Colon));
+            w
+        };
+
+        let start_pos = self.iter.pos();
+        let value = match self.word_preserve_trailing_whitespace_raw()? {
+            Some(Single(w)) => Some(
+                self.builder
+                    .word(Single(structure(w)))
+                    .map_err(custom_at(start_pos))?,
+            ),
+            Some(Concat(words)) => {
+                let words = words.into_iter().map(structure).collect();
+                Some(
+                    self.builder
+                        .word(Concat(words))
+                        .map_err(custom_at(start_pos))?,
+                )
+            }
+            None => None,
+        };
+
+        self.skip_whitespace();
+        Ok(value)
+    }
+
+    /// Parses a single `case` pattern, skipping any trailing whitespace.
+    ///
+    /// Unlike regular words, extended glob groups such as `@(a|b)` are captured as part
+    /// of the pattern, with their operators, parentheses, and `|` separators emitted as
+    /// literals. Bracket expressions are emitted as `WordKind::BracketClass` just like
+    /// in regular words.
+    fn case_pattern(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        self.skip_whitespace();
+
+        let start_pos = self.iter.pos();
+        let mut fragments = Vec::new();
+        self.case_pattern_fragments(&mut fragments)?;
+        let mut fragments = group_bracket_classes(merge_bracket_classes(fragments));
+
+        let pattern = match fragments.len() {
+            0 => None,
+            1 => Some(
+                self.builder
+                    .word(Single(fragments.pop().unwrap()))
+                    .map_err(custom_at(start_pos))?,
+            ),
+            _ => Some(
+                self.builder
+                    .word(Concat(fragments))
+                    .map_err(custom_at(start_pos))?,
+            ),
+        };
+
+        self.skip_whitespace();
+        Ok(pattern)
     }
 
-    /// Parses a whitespace delimited chunk of text, honoring space quoting rules,
-    /// and skipping leading and trailing whitespace.
-    ///
-    /// Since there are a large number of possible tokens that constitute a word,
-    /// (such as literals, paramters, variables, etc.) the caller may not know
-    /// for sure whether to expect a word, thus an optional result is returned
-    /// in the event that no word exists.
-    ///
-    /// Note that an error can still arise if partial tokens are present
-    /// (e.g. malformed parameter).
-    pub fn word(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
-        let ret = self.word_preserve_trailing_whitespace()?;
-        self.skip_whitespace();
-        Ok(ret)
+    /// Collects the fragments of a `case` pattern until a delimiter (or the end
+    /// of an enclosing extended glob group) is reached.
+    fn case_pattern_fragments(
+        &mut self,
+        fragments: &mut Vec<WordKind<B::Command, B::Arithmetic>>,
+    ) -> ParseResult<(), B::Error> {
+        loop {
+            match self.iter.peek() {
+                Some(&ParenOpen) => match take_extglob_operator(fragments) {
+                    Some(operator) => {
+                        let open_pos = self.iter.pos();
+                        self.iter.next();
+                        fragments.push(Simple(SimpleWordKind::Literal(format!(
+                            "{}{}",
+                            operator, ParenOpen
+                        ))));
+
+                        loop {
+                            self.nested(|p| p.case_pattern_fragments(fragments))?;
+
+                            let delim = match self.iter.peek() {
+                                Some(&Pipe) | Some(&ParenClose) => self.iter.next().unwrap(),
+                                Some(_) => return Err(self.make_unexpected_err()),
+                                None => return Err(ParseError::Unmatched(ParenOpen, open_pos)),
+                            };
+
+                            let done = delim == ParenClose;
+                            fragments.push(Simple(SimpleWordKind::Literal(delim.to_string())));
+                            if done {
+                                break;
+                            }
+                        }
+                    }
+
+                    None => return Ok(()),
+                },
+
+                Some(&Whitespace(_)) => return Ok(()),
+                _ => {}
+            }
+
+            match self.word_preserve_trailing_whitespace_raw()? {
+                Some(Single(w)) => fragments.push(w),
+                Some(Concat(ws)) => fragments.extend(ws),
+                None => return Ok(()),
+            }
+        }
     }
 
     /// Identical to `Parser::word()` but preserves trailing whitespace after the word.
     pub fn word_preserve_trailing_whitespace(&mut self) -> ParseResult<Option<B::Word>, B::Error> {
+        let start_pos = self.iter.pos();
         let w = match self.word_preserve_trailing_whitespace_raw()? {
-            Some(w) => Some(self.builder.word(w)?),
+            Some(w) => Some(self.builder.word(w).map_err(custom_at(start_pos))?),
             None => None,
         };
         Ok(w)
     }
 
+    /// Checks if the next tokens open an ANSI-C quoted string, e.g. `$'...'`.
+    fn peek_ansi_c_quote(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        peeked.peek_next() == Some(&Dollar) && peeked.peek_next() == Some(&SingleQuote)
+    }
+
+    /// Parses an ANSI-C quoted string, e.g. `$'foo\n'`, and returns its contents
+    /// with all escape sequences replaced by the characters they represent.
+    /// Unlike regular single quotes, a `'` may appear within the string if escaped.
+    fn ansi_c_quoted_raw(&mut self) -> ParseResult<String, B::Error> {
+        let open = self.iter.pos();
+        eat!(self, { Dollar => {} });
+        eat!(self, { SingleQuote => {} });
+
+        let mut raw = String::new();
+        loop {
+            match self.iter.next() {
+                Some(SingleQuote) => break,
+                Some(Backslash) => {
+                    raw.push('\\');
+                    if let Some(t) = self.iter.next() {
+                        raw.push_str(t.as_str());
+                        continue;
+                    }
+                }
+                Some(t) => {
+                    raw.push_str(t.as_str());
+                    continue;
+                }
+                None => {}
+            }
+
+            return Err(ParseError::UnterminatedQuote {
+                kind: QuoteKind::DollarSingle,
+                open,
+                end: self.iter.pos(),
+            });
+        }
+
+        Ok(unescape_ansi_c(&raw))
+    }
+
     /// Identical to `Parser::word_preserve_trailing_whitespace()` but does
     /// not pass the result to the AST builder.
     fn word_preserve_trailing_whitespace_raw(
         &mut self,
-    ) -> ParseResult<Option<ComplexWordKind<B::Command>>, B::Error> {
+    ) -> ParseResult<Option<BuilderComplexWordKind<B>>, B::Error> {
         self.word_preserve_trailing_whitespace_raw_with_delim(None)
     }
 
+    /// Checks if the next token is a `^` which is not part of a larger word,
+    /// without consuming anything.
+    fn peek_standalone_caret(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        Some(&Caret) == peeked.peek_next() && ends_word(peeked.peek_next())
+    }
+
     /// Identical to `Parser::word_preserve_trailing_whitespace_raw()` but
     /// allows for specifying an arbitrary token as a word delimiter.
     fn word_preserve_trailing_whitespace_raw_with_delim(
         &mut self,
         delim: Option<Token>,
-    ) -> ParseResult<Option<ComplexWordKind<B::Command>>, B::Error> {
+    ) -> ParseResult<Option<BuilderComplexWordKind<B>>, B::Error> {
         self.skip_whitespace();
 
         // Make sure we don't consume comments,
         // e.g. if a # is at the start of a word.
-        if let Some(&Pound) = self.iter.peek() {
+        let pound_is_literal = self.config.comment_only_at_line_start && self.in_simple_command;
+        if !pound_is_literal && Some(&Pound) == self.iter.peek() {
+            return Ok(None);
+        }
+
+        // Similarly, a standalone `^` may be a pipe instead of a word.
+        if self.config.caret_is_pipe && self.peek_standalone_caret() {
             return Ok(None);
         }
 
@@ -1155,6 +2154,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 break;
             }
 
+            if self.config.ansi_c_quoting && self.peek_ansi_c_quote() {
+                words.push(SingleQuoted(self.ansi_c_quoted_raw()?));
+                continue;
+            }
+
             match self.iter.peek() {
                 Some(&CurlyOpen) | Some(&CurlyClose) | Some(&SquareOpen) | Some(&SquareClose)
                 | Some(&SingleQuote) | Some(&DoubleQuote) | Some(&Pound) | Some(&Star)
@@ -1175,9 +2179,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
                 Some(&Newline) | Some(&ParenOpen) | Some(&ParenClose) | Some(&Semi)
                 | Some(&Amp) | Some(&Pipe) | Some(&AndIf) | Some(&OrIf) | Some(&DSemi)
-                | Some(&Less) | Some(&Great) | Some(&DLess) | Some(&DGreat) | Some(&GreatAnd)
-                | Some(&LessAnd) | Some(&DLessDash) | Some(&Clobber) | Some(&LessGreat)
-                | Some(&Whitespace(_)) | None => break,
+                | Some(&SemiAmp) | Some(&DSemiAmp) | Some(&Less) | Some(&Great) | Some(&DLess)
+                | Some(&DGreat) | Some(&GreatAnd) | Some(&LessAnd) | Some(&DLessDash)
+                | Some(&Clobber) | Some(&LessGreat) | Some(&AmpGreat) | Some(&AmpDGreat)
+                | Some(&AmpClobber) | Some(&Whitespace(_)) | None => break,
             }
 
             let start_pos = self.iter.pos();
@@ -1187,7 +2192,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 //
                 // Also, comments are only recognized where a Newline is valid, thus '#'
                 // becomes a literal if it occurs in the middle of a word.
+                //
+                // A `:` is only significant (for tilde expansion) within assignment
+                // values, which will restructure it as needed.
                 tok @ Bang
+                | tok @ Colon
                 | tok @ Pound
                 | tok @ Percent
                 | tok @ Dash
@@ -1207,7 +2216,6 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 Tilde => Simple(SimpleWordKind::Tilde),
                 SquareOpen => Simple(SimpleWordKind::SquareOpen),
                 SquareClose => Simple(SimpleWordKind::SquareClose),
-                Colon => Simple(SimpleWordKind::Colon),
 
                 Backslash => match self.iter.next() {
                     // Escaped newlines become whitespace and a delimiter.
@@ -1217,9 +2225,38 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 },
 
                 SingleQuote => {
+                    // A `$` which could not start a parameter is kept as a literal,
+                    // so one directly before the quote means we're within a `$'`.
+                    let (kind, open) = match words.last() {
+                        Some(Simple(SimpleWordKind::Literal(s))) if s == "$" => {
+                            let mut open = start_pos;
+                            open.byte -= 1;
+                            if open.tracks_line_col() {
+                                open.col -= 1;
+                            }
+                            (QuoteKind::DollarSingle, open)
+                        }
+                        _ => (QuoteKind::Single, start_pos),
+                    };
+
                     let mut buf = String::new();
+                    let mut terminated = true;
                     for t in self.iter.single_quoted(start_pos) {
-                        buf.push_str(t.map_err(|e| ParseError::Unmatched(e.0, e.1))?.as_str())
+                        match t {
+                            Ok(t) => buf.push_str(t.as_str()),
+                            Err(_) => {
+                                terminated = false;
+                                break;
+                            }
+                        }
+                    }
+
+                    if !terminated {
+                        return Err(ParseError::UnterminatedQuote {
+                            kind,
+                            open,
+                            end: self.iter.pos(),
+                        });
                     }
 
                     SingleQuoted(buf)
@@ -1236,13 +2273,15 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 // All word delimiters should have
                 // broken the loop while peeking above.
                 Newline | ParenOpen | ParenClose | Semi | Amp | Pipe | AndIf | OrIf | DSemi
-                | Less | Great | DLess | DGreat | GreatAnd | LessAnd | DLessDash | Clobber
-                | LessGreat | Whitespace(_) => unreachable!(),
+                | SemiAmp | DSemiAmp | Less | Great | DLess | DGreat | GreatAnd | LessAnd
+                | DLessDash | Clobber | LessGreat | AmpGreat | AmpDGreat | AmpClobber
+                | Whitespace(_) => unreachable!(),
             };
 
             words.push(w);
         }
 
+        let mut words = group_bracket_classes(merge_bracket_classes(words));
         let ret = if words.is_empty() {
             None
         } else if words.len() == 1 {
@@ -1267,12 +2306,13 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     ///
     /// `delim` argument structure is Option<(open token, close token)>. The close
     /// token indicates when to stop parsing the word, while the open token will be
-    /// used to construct a `ParseError::Unmatched` error.
+    /// used to construct a `ParseError::Unmatched` error (or a
+    /// `ParseError::UnterminatedQuote` error if it is a double quote).
     fn word_interpolated_raw(
         &mut self,
         delim: Option<(Token, Token)>,
         start_pos: SourcePos,
-    ) -> ParseResult<Vec<SimpleWordKind<B::Command>>, B::Error> {
+    ) -> ParseResult<Vec<BuilderSimpleWordKind<B>>, B::Error> {
         let (delim_open, delim_close) = match delim {
             Some((o, c)) => (Some(o), Some(c)),
             None => (None, None),
@@ -1313,13 +2353,24 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
 
             match self.iter.next() {
+                // Within double quotes, an escaped newline is a line continuation
+                // and is removed entirely.
+                Some(Backslash)
+                    if delim_close == Some(DoubleQuote) && self.iter.peek() == Some(&Newline) =>
+                {
+                    self.iter.next();
+                }
+
                 // Backslashes only escape a few tokens when double-quoted-type words
                 Some(Backslash) => {
-                    let special = match self.iter.peek() {
-                        Some(&Dollar) | Some(&Backtick) | Some(&DoubleQuote) | Some(&Backslash)
-                        | Some(&Newline) => true,
-                        _ => false,
-                    };
+                    let special = matches!(
+                        self.iter.peek(),
+                        Some(&Dollar)
+                            | Some(&Backtick)
+                            | Some(&DoubleQuote)
+                            | Some(&Backslash)
+                            | Some(&Newline)
+                    );
 
                     if special || self.iter.peek() == delim_close.as_ref() {
                         store!(SimpleWordKind::Escaped(
@@ -1335,6 +2386,13 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
                 Some(t) => buf.push_str(t.as_str()),
                 None => match delim_open {
+                    Some(DoubleQuote) => {
+                        return Err(ParseError::UnterminatedQuote {
+                            kind: QuoteKind::Double,
+                            open: start_pos,
+                            end: self.iter.pos(),
+                        })
+                    }
                     Some(delim) => return Err(ParseError::Unmatched(delim, start_pos)),
                     None => break,
                 },
@@ -1354,13 +2412,16 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// before the contents inside the original backticks are recursively parsed
     /// as a command.
     pub fn backticked_command_substitution(&mut self) -> ParseResult<B::Word, B::Error> {
+        let start_pos = self.iter.pos();
         let word = self.backticked_raw()?;
-        Ok(self.builder.word(Single(Simple(word)))?)
+        self.builder
+            .word(Single(Simple(word)))
+            .map_err(custom_at(start_pos))
     }
 
     /// Identical to `Parser::backticked_command_substitution`, except but does not pass the
     /// result to the AST builder.
-    fn backticked_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    fn backticked_raw(&mut self) -> ParseResult<BuilderSimpleWordKind<B>, B::Error> {
         let backtick_pos = self.iter.pos();
         eat!(self, { Backtick => {} });
 
@@ -1391,12 +2452,20 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// returns an `Word`, which will capture both cases where a literal or
     /// parameter is parsed.
     pub fn parameter(&mut self) -> ParseResult<B::Word, B::Error> {
+        let start_pos = self.iter.pos();
         let param = self.parameter_raw()?;
-        Ok(self.builder.word(Single(Simple(param)))?)
+        self.builder
+            .word(Single(Simple(param)))
+            .map_err(custom_at(start_pos))
     }
 
     /// Identical to `Parser::parameter()` but does not pass the result to the AST builder.
-    fn parameter_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    fn parameter_raw(&mut self) -> ParseResult<BuilderSimpleWordKind<B>, B::Error> {
+        self.nested(Self::parameter_raw_internal)
+    }
+
+    /// Like `Parser::parameter_raw`, but does not track the nesting depth.
+    fn parameter_raw_internal(&mut self) -> ParseResult<BuilderSimpleWordKind<B>, B::Error> {
         use crate::ast::Parameter;
 
         let start_pos = self.iter.pos();
@@ -1427,7 +2496,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     fn parameter_substitution_word_raw(
         &mut self,
         curly_open_pos: SourcePos,
-    ) -> ParseResult<Option<ComplexWordKind<B::Command>>, B::Error> {
+    ) -> ParseResult<Option<BuilderComplexWordKind<B>>, B::Error> {
         let mut words = Vec::new();
         'capture_words: loop {
             'capture_literals: loop {
@@ -1447,6 +2516,8 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     | Some(t @ &AndIf)
                     | Some(t @ &OrIf)
                     | Some(t @ &DSemi)
+                    | Some(t @ &SemiAmp)
+                    | Some(t @ &DSemiAmp)
                     | Some(t @ &Less)
                     | Some(t @ &Great)
                     | Some(t @ &DLess)
@@ -1456,6 +2527,9 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     | Some(t @ &DLessDash)
                     | Some(t @ &Clobber)
                     | Some(t @ &LessGreat)
+                    | Some(t @ &AmpGreat)
+                    | Some(t @ &AmpDGreat)
+                    | Some(t @ &AmpClobber)
                     | Some(t @ &Whitespace(_))
                     | Some(t @ &Newline) => {
                         words.push(Simple(SimpleWordKind::Literal(t.as_str().to_owned())));
@@ -1543,7 +2617,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
         param: DefaultParameter,
         curly_open_pos: SourcePos,
-    ) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    ) -> ParseResult<BuilderSimpleWordKind<B>, B::Error> {
         use crate::ast::builder::ParameterSubstitutionKind::*;
         use crate::ast::Parameter;
 
@@ -1583,9 +2657,94 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         Ok(SimpleWordKind::Subst(Box::new(ret)))
     }
 
+    /// Checks if the upcoming `(...)` of a command substitution holds nothing
+    /// but a single input redirection, e.g. `$(< file)`.
+    ///
+    /// Anything which cannot be confirmed by looking ahead at the raw tokens
+    /// (such as nested substitutions or quoted parens) is conservatively left to
+    /// be parsed as a regular command substitution.
+    fn peek_read_file_subst(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        if peeked.peek_next() != Some(&ParenOpen) {
+            return false;
+        }
+
+        let mut next = peeked.peek_next().cloned();
+        if let Some(Whitespace(_)) = next {
+            next = peeked.peek_next().cloned();
+        }
+        if next != Some(Less) {
+            return false;
+        }
+
+        next = peeked.peek_next().cloned();
+        if let Some(Whitespace(_)) = next {
+            next = peeked.peek_next().cloned();
+        }
+
+        let mut saw_word = false;
+        let mut word_ended = false;
+        loop {
+            match next {
+                None => return false,
+                Some(ParenClose) => return saw_word,
+                Some(Whitespace(_)) | Some(Newline) => word_ended = true,
+                Some(_) if word_ended => return false,
+                Some(Pound) if !saw_word => return false,
+
+                Some(SingleQuote) => loop {
+                    match peeked.peek_next() {
+                        Some(&SingleQuote) => break,
+                        Some(_) => {}
+                        None => return false,
+                    }
+                },
+
+                Some(DoubleQuote) => loop {
+                    match peeked.peek_next() {
+                        Some(&DoubleQuote) => break,
+                        Some(&Backslash) => {
+                            if peeked.peek_next().is_none() {
+                                return false;
+                            }
+                        }
+                        Some(&Backtick) | Some(&ParenOpen) | Some(&ParenClose) | None => {
+                            return false
+                        }
+                        Some(_) => {}
+                    }
+                },
+
+                Some(Backslash) => {
+                    if peeked.peek_next().is_none() {
+                        return false;
+                    }
+                }
+
+                Some(Dollar) => {
+                    // The end of a `${...}` can't be found without parsing it
+                    next = peeked.peek_next().cloned();
+                    if next == Some(CurlyOpen) {
+                        return false;
+                    }
+
+                    saw_word = true;
+                    continue;
+                }
+
+                Some(Backtick) => return false,
+                Some(ref t) if t.is_word_delimiter() => return false,
+                Some(_) => {}
+            }
+
+            saw_word |= !word_ended;
+            next = peeked.peek_next().cloned();
+        }
+    }
+
     /// Parses a parameter substitution in the form of `${...}`, `$(...)`, or `$((...))`.
     /// Nothing is passed to the builder.
-    fn parameter_substitution_raw(&mut self) -> ParseResult<SimpleWordKind<B::Command>, B::Error> {
+    fn parameter_substitution_raw(&mut self) -> ParseResult<BuilderSimpleWordKind<B>, B::Error> {
         use crate::ast::builder::ParameterSubstitutionKind::*;
         use crate::ast::Parameter;
 
@@ -1609,7 +2768,9 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     let subst = if let Some(&ParenClose) = self.iter.peek() {
                         None
                     } else {
-                        Some(self.arithmetic_substitution()?)
+                        let expr_pos = self.iter.pos();
+                        let expr = self.arithmetic_substitution()?;
+                        Some(self.builder.arithmetic(expr).map_err(custom_at(expr_pos))?)
                     };
 
                     // Some shells allow the closing parens to have whitespace in between
@@ -1619,6 +2780,22 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     eat!(self, { ParenClose => {} });
 
                     Arith(subst)
+                } else if self.peek_read_file_subst() {
+                    eat!(self, { ParenOpen => {} });
+                    self.skip_whitespace();
+                    eat!(self, { Less => {} });
+
+                    let path = match self.word_preserve_trailing_whitespace_raw()? {
+                        Some(path) => path,
+                        None => return Err(self.make_unexpected_err()),
+                    };
+
+                    while let Some(&Whitespace(_)) | Some(&Newline) = self.iter.peek() {
+                        self.iter.next();
+                    }
+                    eat!(self, { ParenClose => {} });
+
+                    ReadFile(path)
                 } else {
                     Command(self.subshell_internal(true)?)
                 };
@@ -1630,6 +2807,26 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 let curly_open_pos = start_pos;
                 self.iter.next();
 
+                // Distinguish `${!prefix*}` and `${!prefix@}` from the `${!}` parameter
+                let prefix_names = {
+                    let mut peeked = self.iter.multipeek();
+                    Some(&Bang) == peeked.peek_next()
+                        && matches!(peeked.peek_next(), Some(&Name(_)))
+                        && matches!(peeked.peek_next(), Some(&Star) | Some(&At))
+                        && Some(&CurlyClose) == peeked.peek_next()
+                };
+
+                if prefix_names {
+                    self.iter.next(); // Consume the `!`
+                    let subst = match (self.iter.next(), self.iter.next()) {
+                        (Some(Name(prefix)), Some(tok)) => NamesMatchingPrefix(prefix, tok == At),
+                        _ => unreachable!(),
+                    };
+                    self.iter.next(); // Consume the `}`
+
+                    return Ok(SimpleWordKind::Subst(Box::new(subst)));
+                }
+
                 let param = self.parameter_inner()?;
                 let subst = match self.iter.peek() {
                     Some(&Percent) => {
@@ -1663,6 +2860,23 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                         })
                     }
 
+                    // A `@` following any parameter other than `#` introduces a
+                    // transformation, e.g. `${param@Q}`, while `${@}` never reaches here.
+                    Some(&At) if Parameter::Pound != param => {
+                        self.iter.next();
+                        let op_pos = self.iter.pos();
+                        let op = match self.iter.next() {
+                            Some(Name(ref s))
+                                if s.len() == 1 && s.as_bytes()[0].is_ascii_alphabetic() =>
+                            {
+                                char::from(s.as_bytes()[0])
+                            }
+                            Some(t) => return Err(ParseError::BadSubst(t, op_pos)),
+                            None => return Err(ParseError::Unmatched(CurlyOpen, curly_open_pos)),
+                        };
+                        eat!(self, { CurlyClose => { Transform(param, op) } })
+                    }
+
                     // In this case the found # is the parameter itself
                     Some(&Colon) | Some(&Dash) | Some(&Equals) | Some(&Question) | Some(&Plus)
                     | Some(&CurlyClose)
@@ -1719,19 +2933,23 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// quoted or concatenated.
     pub fn do_group(&mut self) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[DO])
-            .map_err(|_| self.make_unexpected_err())?;
-        let result = self.command_group(CommandGroupDelimiters {
+        self.keyword(&[DO])
+            .map_err(|_| self.make_expected_err(&[Expectation::ReservedWord(DO)]))?;
+        let result = self.compound_body(CommandGroupDelimiters {
             reserved_words: &[DONE],
             ..Default::default()
         })?;
-        self.reserved_word(&[DONE])
+        self.keyword(&[DONE])
             .map_err(|()| ParseError::IncompleteCmd(DO, start_pos, DONE, self.iter.pos()))?;
         Ok(result)
     }
 
     /// Parses any number of sequential commands between balanced `{` and `}`
     /// reserved words. Each of the reserved words must be a literal token, and cannot be quoted.
+    ///
+    /// As required by POSIX, the closing `}` is only recognized where a command may
+    /// begin (e.g. after a `;` or newline), otherwise it is treated as a regular word.
+    /// Thus `{ echo }; }` runs `echo }`, while `{ echo } }` is missing its closing `}`.
     pub fn brace_group(&mut self) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
         // CurlyClose must be encountered as a stand alone word,
         // even though it is represented as its own token
@@ -1784,10 +3002,13 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         self.skip_whitespace();
         if Some(&ParenOpen) == self.iter.peek() {
             Some(CompoundCmdKeyword::Subshell)
-        } else if self.peek_reserved_token(&[CurlyOpen]).is_some() {
+        } else if self.config.is_reserved_token(&CurlyOpen)
+            && self.peek_reserved_token(&[CurlyOpen]).is_some()
+        {
             Some(CompoundCmdKeyword::Brace)
         } else {
-            match self.peek_reserved_word(&[FOR, CASE, IF, WHILE, UNTIL]) {
+            match self.peek_keyword(&[FOR, CASE, IF, WHILE, UNTIL]) {
+                Some(FOR) if self.peek_arith_for() => Some(CompoundCmdKeyword::ArithFor),
                 Some(FOR) => Some(CompoundCmdKeyword::For),
                 Some(CASE) => Some(CompoundCmdKeyword::Case),
                 Some(IF) => Some(CompoundCmdKeyword::If),
@@ -1810,41 +3031,63 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
         kw: Option<CompoundCmdKeyword>,
     ) -> ParseResult<B::CompoundCommand, B::Error> {
-        let cmd = match kw.or_else(|| self.next_compound_command_type()) {
+        let kw = kw.or_else(|| self.next_compound_command_type());
+        let start_pos = self.iter.pos();
+        let cmd = match kw {
             Some(CompoundCmdKeyword::If) => {
                 let fragments = self.if_command()?;
                 let io = self.redirect_list()?;
-                self.builder.if_command(fragments, io)?
+                self.builder
+                    .if_command(fragments, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             Some(CompoundCmdKeyword::While) | Some(CompoundCmdKeyword::Until) => {
                 let (until, guard_body_pair) = self.loop_command()?;
                 let io = self.redirect_list()?;
-                self.builder.loop_command(until, guard_body_pair, io)?
+                self.builder
+                    .loop_command(until, guard_body_pair, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             Some(CompoundCmdKeyword::For) => {
                 let for_fragments = self.for_command()?;
                 let io = self.redirect_list()?;
-                self.builder.for_command(for_fragments, io)?
+                self.builder
+                    .for_command(for_fragments, io)
+                    .map_err(custom_at(start_pos))?
+            }
+
+            Some(CompoundCmdKeyword::ArithFor) => {
+                let fragments = self.arith_for_command()?;
+                let io = self.redirect_list()?;
+                self.builder
+                    .arith_for_command(fragments, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             Some(CompoundCmdKeyword::Case) => {
                 let fragments = self.case_command()?;
                 let io = self.redirect_list()?;
-                self.builder.case_command(fragments, io)?
+                self.builder
+                    .case_command(fragments, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             Some(CompoundCmdKeyword::Brace) => {
                 let cmds = self.brace_group()?;
                 let io = self.redirect_list()?;
-                self.builder.brace_group(cmds, io)?
+                self.builder
+                    .brace_group(cmds, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             Some(CompoundCmdKeyword::Subshell) => {
                 let cmds = self.subshell()?;
                 let io = self.redirect_list()?;
-                self.builder.subshell(cmds, io)?
+                self.builder
+                    .subshell(cmds, io)
+                    .map_err(custom_at(start_pos))?
             }
 
             None => return Err(self.make_unexpected_err()),
@@ -1875,7 +3118,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             reserved_words: &[DO],
             ..Default::default()
         })?;
-        match self.peek_reserved_word(&[DO]) {
+        match self.peek_keyword(&[DO]) {
             Some(_) => Ok((
                 kind,
                 builder::GuardBodyPairGroup {
@@ -1899,7 +3142,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// AST node, it so that the caller can do so with redirections.
     pub fn if_command(&mut self) -> ParseResult<builder::IfFragments<B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[IF])
+        self.keyword(&[IF])
             .map_err(|_| self.make_unexpected_err())?;
 
         macro_rules! missing_fi {
@@ -1920,25 +3163,28 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 reserved_words: &[THEN],
                 ..Default::default()
             })?;
-            self.reserved_word(&[THEN]).map_err(missing_then!())?;
+            self.keyword(&[THEN]).map_err(missing_then!())?;
 
-            let body = self.command_group(CommandGroupDelimiters {
+            let body = self.compound_body(CommandGroupDelimiters {
                 reserved_words: &[ELIF, ELSE, FI],
                 ..Default::default()
             })?;
             conditionals.push(builder::GuardBodyPairGroup { guard, body });
 
-            let els = match self
-                .reserved_word(&[ELIF, ELSE, FI])
-                .map_err(missing_fi!())?
-            {
+            let els = match self.keyword(&[ELIF, ELSE, FI]).map_err(|_| {
+                self.make_expected_err(&[
+                    Expectation::ReservedWord(ELIF),
+                    Expectation::ReservedWord(ELSE),
+                    Expectation::ReservedWord(FI),
+                ])
+            })? {
                 ELIF => continue,
                 ELSE => {
-                    let els = self.command_group(CommandGroupDelimiters {
+                    let els = self.compound_body(CommandGroupDelimiters {
                         reserved_words: &[FI],
                         ..Default::default()
                     })?;
-                    self.reserved_word(&[FI]).map_err(missing_fi!())?;
+                    self.keyword(&[FI]).map_err(missing_fi!())?;
                     Some(els)
                 }
                 FI => None,
@@ -1961,14 +3207,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
     ) -> ParseResult<builder::ForFragments<B::Word, B::Command>, B::Error> {
         let start_pos = self.iter.pos();
-        self.reserved_word(&[FOR])
+        self.keyword(&[FOR])
             .map_err(|_| self.make_unexpected_err())?;
 
         self.skip_whitespace();
 
         match self.iter.peek() {
             Some(&Name(_)) | Some(&Literal(_)) => {}
-            _ => return Err(self.make_unexpected_err()),
+            _ => return Err(self.make_expected_err(&[Expectation::Token(TokenKind::Name)])),
         }
 
         let var_pos = self.iter.pos();
@@ -1984,15 +3230,12 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         // A for command can take one of several different shapes (in pseudo regex syntax):
         // `for name [\n*] [in [word*]] [;\n* | \n+] do_group`
         // Below we'll disambiguate what situation we have as we move along.
-        let (words, pre_body_comments) = if self.peek_reserved_word(&[IN]).is_some() {
+        let (words, pre_body_comments) = if self.peek_keyword(&[IN]).is_some() {
             // Found `in` keyword, therefore we're looking at something like
             // `for name \n* in [words*] [;\n* | \n+] do_group`
-            self.reserved_word(&[IN]).unwrap();
+            self.keyword(&[IN]).unwrap();
 
-            let mut words = Vec::new();
-            while let Some(w) = self.word()? {
-                words.push(w);
-            }
+            let words = self.word_list()?;
 
             let found_semi = eat_maybe!(self, {
                 Semi => { true };
@@ -2014,7 +3257,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             // `for name \n*;\n* do_group`
             eat!(self, { Semi => {} });
             (None, self.linebreak())
-        } else if self.peek_reserved_word(&[DO]).is_none() {
+        } else if self.peek_keyword(&[DO]).is_none() {
             // If we didn't find an `in` keyword, and we havent hit the body
             // (a `do` keyword), then we can reasonably say the script has
             // words without an `in` keyword.
@@ -2029,7 +3272,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             (None, post_var_comments)
         };
 
-        if self.peek_reserved_word(&[DO]).is_none() {
+        if self.peek_keyword(&[DO]).is_none() {
             return Err(ParseError::IncompleteCmd(
                 FOR,
                 start_pos,
@@ -2048,11 +3291,102 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         })
     }
 
+    /// Checks if the upcoming `for` keyword is followed by `((`, and thus
+    /// begins an arithmetic `for` command instead of a regular one.
+    fn peek_arith_for(&mut self) -> bool {
+        let mut peeked = self.iter.multipeek();
+        peeked.peek_next(); // Skip past the `for` keyword
+
+        let mut next = peeked.peek_next();
+        while let Some(&Whitespace(_)) = next {
+            next = peeked.peek_next();
+        }
+
+        next == Some(&ParenOpen) && peeked.peek_next() == Some(&ParenOpen)
+    }
+
+    /// Parses an arithmetic `for` command, e.g. `for ((i=0; i<5; i++)); do body; done`,
+    /// but does not parse any redirections that may follow.
+    ///
+    /// Any of the three arithmetic expressions may be omitted, e.g. `for ((;;))`.
+    pub fn arith_for_command(
+        &mut self,
+    ) -> ParseResult<builder::ArithForFragments<B::Arithmetic, B::Command>, B::Error> {
+        let start_pos = self.iter.pos();
+        self.keyword(&[FOR])
+            .map_err(|_| self.make_unexpected_err())?;
+
+        self.skip_whitespace();
+        eat!(self, { ParenOpen => {} });
+        eat!(self, { ParenOpen => {} });
+
+        let init = self.arith_for_expr()?;
+        self.skip_whitespace();
+        let cond = eat!(self, {
+            // The lexer treats adjacent semicolons as a single token, e.g. `for ((;;))`
+            DSemi => { None },
+            Semi => {
+                let cond = self.arith_for_expr()?;
+                self.skip_whitespace();
+                eat!(self, { Semi => {} });
+                cond
+            },
+        });
+        let update = self.arith_for_expr()?;
+
+        // Some shells allow the closing parens to have whitespace in between
+        self.skip_whitespace();
+        eat!(self, { ParenClose => {} });
+        self.skip_whitespace();
+        eat!(self, { ParenClose => {} });
+
+        self.skip_whitespace();
+        eat_maybe!(self, { Semi => {} });
+        let pre_body_comments = self.linebreak();
+
+        if self.peek_keyword(&[DO]).is_none() {
+            return Err(ParseError::IncompleteCmd(
+                FOR,
+                start_pos,
+                DO,
+                self.iter.pos(),
+            ));
+        }
+
+        let body = self.do_group()?;
+        Ok(builder::ArithForFragments {
+            init,
+            cond,
+            update,
+            pre_body_comments,
+            body,
+        })
+    }
+
+    /// Parses a single (possibly empty) expression of an arithmetic `for` command,
+    /// stopping at the `;` or `)` which follows it.
+    fn arith_for_expr(&mut self) -> ParseResult<Option<B::Arithmetic>, B::Error> {
+        self.skip_whitespace();
+        match self.iter.peek() {
+            Some(&Semi) | Some(&DSemi) | Some(&ParenClose) => Ok(None),
+            _ => {
+                let expr_pos = self.iter.pos();
+                let expr = self.arithmetic_substitution()?;
+                let expr = self.builder.arithmetic(expr).map_err(custom_at(expr_pos))?;
+                Ok(Some(expr))
+            }
+        }
+    }
+
     /// Parses a single `case` command but does not parse any redirections that may follow.
     ///
     /// Since `case` is a compound command (and can have redirections applied to it) this
     /// method returns the relevant parts of the `case` command, without constructing an
     /// AST node, it so that the caller can do so with redirections.
+    ///
+    /// Unless `ParserConfig::case_fallthrough` is enabled, only the POSIX `;;` arm
+    /// terminator is supported: bash's `;&` and `;;&` fallthrough terminators are
+    /// rejected as an unexpected `&`.
     pub fn case_command(
         &mut self,
     ) -> ParseResult<builder::CaseFragments<B::Word, B::Command>, B::Error> {
@@ -2060,17 +3394,17 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
         macro_rules! missing_in {
             () => {
-                |_| ParseError::IncompleteCmd(CASE, start_pos, IN, self.iter.pos());
+                |_| ParseError::IncompleteCmd(CASE, start_pos, IN, self.iter.pos())
             };
         }
 
         macro_rules! missing_esac {
             () => {
-                |_| ParseError::IncompleteCmd(CASE, start_pos, ESAC, self.iter.pos());
+                |_| ParseError::IncompleteCmd(CASE, start_pos, ESAC, self.iter.pos())
             };
         }
 
-        self.reserved_word(&[CASE])
+        self.keyword(&[CASE])
             .map_err(|_| self.make_unexpected_err())?;
 
         let word = match self.word()? {
@@ -2079,14 +3413,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         };
 
         let post_word_comments = self.linebreak();
-        self.reserved_word(&[IN]).map_err(missing_in!())?;
+        self.keyword(&[IN]).map_err(missing_in!())?;
         let in_comment = self.newline();
 
         let mut pre_esac_comments = None;
         let mut arms = Vec::new();
         loop {
             let pre_pattern_comments = self.linebreak();
-            if self.peek_reserved_word(&[ESAC]).is_some() {
+            if self.peek_keyword(&[ESAC]).is_some() {
                 // Make sure we don't lose the captured comments if there are no body
                 debug_assert_eq!(pre_esac_comments, None);
                 pre_esac_comments = Some(pre_pattern_comments);
@@ -2105,7 +3439,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
             let mut patterns = Vec::new();
             loop {
-                match self.word()? {
+                match self.case_pattern()? {
                     Some(p) => patterns.push(p),
                     None => return Err(self.make_unexpected_err()),
                 }
@@ -2124,22 +3458,45 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     // Make sure we check for missing `esac` here, otherwise if we have EOF
                     // trying to parse a word will result in an `UnexpectedEOF` error
                     None => return Err(()).map_err(missing_esac!()),
-                    _ => return Err(self.make_unexpected_err()),
+                    _ => {
+                        return Err(self.make_expected_err(&[
+                            Expectation::Token(TokenKind::Pipe),
+                            Expectation::Token(TokenKind::ParenClose),
+                        ]))
+                    }
                 }
             }
 
             let pattern_comment = self.newline();
+            let terminators: &[Token] = if self.config.case_fallthrough {
+                &[DSemi, SemiAmp, DSemiAmp]
+            } else {
+                &[DSemi]
+            };
+
             let body = self.command_group_internal(CommandGroupDelimiters {
                 reserved_words: &[ESAC],
                 reserved_tokens: &[],
-                exact_tokens: &[DSemi],
+                exact_tokens: terminators,
             })?;
 
-            let (no_more_arms, arm_comment) = if Some(&DSemi) == self.iter.peek() {
-                self.iter.next();
-                (false, self.newline())
-            } else {
-                (true, None)
+            let terminator = match self.iter.peek() {
+                Some(&DSemi) => Some(ast::CaseArmTerminator::Break),
+                Some(&SemiAmp) if self.config.case_fallthrough => {
+                    Some(ast::CaseArmTerminator::FallThrough)
+                }
+                Some(&DSemiAmp) if self.config.case_fallthrough => {
+                    Some(ast::CaseArmTerminator::Continue)
+                }
+                _ => None,
+            };
+
+            let (no_more_arms, terminator, arm_comment) = match terminator {
+                Some(terminator) => {
+                    self.iter.next();
+                    (false, terminator, self.newline())
+                }
+                None => (true, ast::CaseArmTerminator::Break, None),
             };
 
             arms.push(builder::CaseArm {
@@ -2149,6 +3506,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                     pattern_comment,
                 },
                 body,
+                terminator,
                 arm_comment,
             });
 
@@ -2166,7 +3524,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             None => remaining_comments,
         };
 
-        self.reserved_word(&[ESAC]).map_err(missing_esac!())?;
+        self.keyword(&[ESAC]).map_err(missing_esac!())?;
 
         Ok(builder::CaseFragments {
             word,
@@ -2182,7 +3540,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     pub fn maybe_function_declaration(
         &mut self,
     ) -> ParseResult<Option<B::PipeableCommand>, B::Error> {
-        if self.peek_reserved_word(&[FUNCTION]).is_some() {
+        if self.peek_keyword(&[FUNCTION]).is_some() {
             return self.function_declaration().map(Some);
         }
 
@@ -2212,17 +3570,28 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// the name of the function must be followed by `()`. Whitespace is allowed between
     /// the name and `(`, and whitespace is allowed between `()`.
     pub fn function_declaration(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
-        let (name, post_name_comments, body) = self.function_declaration_internal()?;
-        Ok(self
-            .builder
-            .function_declaration(name, post_name_comments, body)?)
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
+        let (kind, name, post_name_comments, body) = self.function_declaration_internal()?;
+        self.builder
+            .function_declaration(kind, name, post_name_comments, body)
+            .map_err(custom_at(start_pos))
     }
 
     /// Like `Parser::function_declaration`, but does not pass the result to the builder
+    #[allow(clippy::type_complexity)]
     fn function_declaration_internal(
         &mut self,
-    ) -> ParseResult<(String, Vec<builder::Newline>, B::CompoundCommand), B::Error> {
-        let found_fn = match self.peek_reserved_word(&[FUNCTION]) {
+    ) -> ParseResult<
+        (
+            builder::FunctionDeclarationKind,
+            String,
+            Vec<builder::Newline>,
+            B::CompoundCommand,
+        ),
+        B::Error,
+    > {
+        let found_fn = match self.peek_keyword(&[FUNCTION]) {
             Some(_) => {
                 self.iter.next();
                 true
@@ -2246,14 +3615,14 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
         // If there is no whitespace after the function name, the only valid
         // possibility is for `()` to appear.
-        let body = if Some(&ParenOpen) == self.iter.peek() {
+        let (found_parens, body) = if Some(&ParenOpen) == self.iter.peek() {
             eat!(self, { ParenOpen => {} });
             self.skip_whitespace();
             eat!(self, { ParenClose => {} });
-            None
+            (true, None)
         } else if found_fn && Some(&Newline) == self.iter.peek() {
             // Do nothing, function declaration satisfied
-            None
+            (false, None)
         } else {
             // Enforce at least one whitespace between function declaration and body
             eat!(self, { Whitespace(_) => {} });
@@ -2264,28 +3633,110 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 eat!(self, { ParenOpen => {} });
                 self.skip_whitespace();
                 eat!(self, { ParenClose => {} });
-                None
+                (true, None)
             } else if Some(&ParenOpen) == self.iter.peek() {
                 // Otherwise it is possible for there to be a subshell as the body
+                let subshell_pos = self.iter.pos();
                 let subshell = self.subshell_internal(true)?;
                 if subshell.commands.is_empty() && subshell.trailing_comments.is_empty() {
                     // Case like `function foo () ...`
-                    None
+                    (true, None)
                 } else {
                     // Case like `function foo (subshell)`
-                    Some(self.builder.subshell(subshell, Vec::new())?)
+                    let subshell = self
+                        .builder
+                        .subshell(subshell, Vec::new())
+                        .map_err(custom_at(subshell_pos))?;
+                    (false, Some(subshell))
                 }
             } else {
-                None
+                (false, None)
             }
         };
 
+        let kind = match (found_fn, found_parens) {
+            (true, true) => builder::FunctionDeclarationKind::KeywordAndParens,
+            (true, false) => builder::FunctionDeclarationKind::Keyword,
+            (false, _) => builder::FunctionDeclarationKind::Parens,
+        };
+
         let (post_name_comments, body) = match body {
             Some(subshell) => (Vec::new(), subshell),
             None => (self.linebreak(), self.compound_command()?),
         };
 
-        Ok((name, post_name_comments, body))
+        Ok((kind, name, post_name_comments, body))
+    }
+
+    /// Parses a `coproc` command, e.g. `coproc name { cmd; }` or `coproc cmd arg`.
+    ///
+    /// A name may only be specified if the body is a compound command, otherwise
+    /// the word following `coproc` is treated as the command to be run.
+    pub fn coproc_command(&mut self) -> ParseResult<B::PipeableCommand, B::Error> {
+        self.skip_whitespace();
+        let start_pos = self.iter.pos();
+        self.reserved_word(&[COPROC])
+            .map_err(|_| self.make_unexpected_err())?;
+        self.skip_whitespace();
+
+        let has_name = {
+            let Parser {
+                ref mut iter,
+                ref config,
+                ..
+            } = *self;
+
+            let mut peeked = iter.multipeek();
+            if let Some(&Name(_)) = peeked.peek_next() {
+                let mut next = peeked.peek_next();
+                let found_whitespace = matches!(next, Some(&Whitespace(_)));
+                while let Some(&Whitespace(_)) = next {
+                    next = peeked.peek_next();
+                }
+
+                let is_reserved = match next {
+                    Some(&ParenOpen) => true,
+                    Some(&CurlyOpen) => config.is_reserved_token(&CurlyOpen),
+                    Some(&Name(ref kw)) | Some(&Literal(ref kw)) => {
+                        [FOR, CASE, IF, WHILE, UNTIL].contains(&kw.as_str())
+                            && config.is_reserved_word(kw)
+                    }
+                    _ => false,
+                };
+
+                // A subshell's opening paren need not be followed by a delimiter
+                let starts_compound =
+                    next == Some(&ParenOpen) || (is_reserved && ends_word(peeked.peek_next()));
+
+                found_whitespace && starts_compound
+            } else {
+                false
+            }
+        };
+
+        let name = if has_name {
+            match self.iter.next() {
+                Some(Name(name)) => Some(name),
+                _ => unreachable!(),
+            }
+        } else {
+            None
+        };
+
+        let body_pos = self.iter.pos();
+        let body = match self.next_compound_command_type() {
+            Some(kw) => {
+                let compound = self.compound_command_internal(Some(kw))?;
+                self.builder
+                    .compound_command_into_pipeable(compound)
+                    .map_err(custom_at(body_pos))?
+            }
+            None => self.simple_command()?,
+        };
+
+        self.builder
+            .coproc(name, body)
+            .map_err(custom_at(start_pos))
     }
 
     /// Skips over any encountered whitespace but preserves newlines.
@@ -2296,17 +3747,48 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
                 self.iter.next();
             }
 
-            let found_backslash_newline = {
+            // Count the tokens making up an escaped newline, if any,
+            // treating an escaped \r\n as an escaped newline as well.
+            let backslash_newline_len = {
                 let mut peeked = self.iter.multipeek();
-                Some(&Backslash) == peeked.peek_next() && Some(&Newline) == peeked.peek_next()
+                let len = match peeked.peek_next() {
+                    Some(&Backslash) => match peeked.peek_next() {
+                        Some(&Newline) => 2,
+                        Some(Whitespace(w)) if w == "\r" => 3,
+                        _ => 0,
+                    },
+                    _ => 0,
+                };
+
+                if len == 3 && Some(&Newline) != peeked.peek_next() {
+                    0
+                } else {
+                    len
+                }
             };
 
-            if found_backslash_newline {
-                self.iter.next();
-                self.iter.next();
-            } else {
+            if backslash_newline_len == 0 {
                 break;
             }
+
+            for _ in 0..backslash_newline_len {
+                self.iter.next();
+            }
+        }
+    }
+
+    /// Ensures that nothing other than whitespace, newlines, or comments remains in the
+    /// input, otherwise an `Unexpected` error for the next token is returned.
+    pub(crate) fn expect_eof(&mut self) -> ParseResult<(), B::Error> {
+        let start_pos = self.iter.pos();
+        let comments = self.linebreak();
+        if self.iter.peek().is_some() {
+            Err(self.make_unexpected_err())
+        } else {
+            self.builder
+                .comments(comments)
+                .map_err(custom_at(start_pos))?;
+            Ok(())
         }
     }
 
@@ -2320,17 +3802,47 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         lines
     }
 
+    /// Parses a `#!` interpreter line, e.g. `#!/bin/sh`, if it appears at the very
+    /// start of the source, returning its contents after the `#!`.
+    ///
+    /// The entire line (including the newline) is consumed. If the parser is not
+    /// at the start of the source, or the source does not begin with `#!`, nothing
+    /// is consumed and `None` is returned, leaving any `#!` to be parsed as a
+    /// regular comment.
+    pub fn shebang(&mut self) -> Option<String> {
+        if self.iter.pos().byte != 0 {
+            return None;
+        }
+
+        {
+            let mut peeked = self.iter.multipeek();
+            if peeked.peek_next() != Some(&Pound) || peeked.peek_next() != Some(&Bang) {
+                return None;
+            }
+        }
+
+        let mut line = self
+            .iter
+            .by_ref()
+            .skip(2)
+            .take_while(|t| t != &Newline)
+            .collect::<Vec<_>>();
+        strip_trailing_cr(&mut line);
+        Some(concat_tokens(&line))
+    }
+
     /// Tries to parse a `Token::Newline` (or a comment) after skipping whitespace.
     pub fn newline(&mut self) -> Option<builder::Newline> {
         self.skip_whitespace();
 
         match self.iter.peek() {
             Some(&Pound) => {
-                let comment = self
+                let mut comment = self
                     .iter
                     .by_ref()
                     .take_while(|t| t != &Newline)
                     .collect::<Vec<_>>();
+                strip_trailing_cr(&mut comment);
                 Some(builder::Newline(Some(concat_tokens(&comment))))
             }
 
@@ -2355,13 +3867,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             return None;
         }
 
-        let care_about_whitespace = tokens.iter().any(|tok| {
-            if let Whitespace(_) = *tok {
-                true
-            } else {
-                false
-            }
-        });
+        let care_about_whitespace = tokens.iter().any(|tok| tok.kind() == TokenKind::Whitespace);
 
         // If the caller cares about whitespace as a reserved word we should
         // do a reserved word check without skipping any leading whitespace.
@@ -2432,6 +3938,51 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
     }
 
+    /// Like `Parser::peek_reserved_word`, but only considers words which
+    /// are reserved by the parser's configuration.
+    fn peek_keyword<'a>(&mut self, words: &'a [&str]) -> Option<&'a str> {
+        let found = self.peek_reserved_word(words)?;
+        if self.config.is_reserved_word(found) {
+            Some(found)
+        } else {
+            None
+        }
+    }
+
+    /// Like `Parser::reserved_word`, but only considers words which
+    /// are reserved by the parser's configuration.
+    fn keyword<'a>(&mut self, words: &'a [&str]) -> Result<&'a str, ()> {
+        match self.peek_keyword(words) {
+            Some(s) => {
+                self.iter.next();
+                Ok(s)
+            }
+            None => Err(()),
+        }
+    }
+
+    /// Checks if the next word is configured as a reserved word, but is not
+    /// one the parser knows how to handle (and thus cannot begin a command).
+    fn peek_unsupported_keyword(&mut self) -> bool {
+        self.skip_whitespace();
+
+        let Parser {
+            ref mut iter,
+            ref config,
+            ..
+        } = *self;
+
+        let mut peeked = iter.multipeek();
+        let unsupported = match peeked.peek_next() {
+            Some(&Name(ref kw)) | Some(&Literal(ref kw)) => {
+                config.is_reserved_word(kw) && !DEFAULT_RESERVED_WORDS.contains(&kw.as_str())
+            }
+            _ => false,
+        };
+
+        unsupported && ends_word(peeked.peek_next())
+    }
+
     /// Checks that one of the specified tokens appears as a reserved word
     /// and consumes it, returning the token it matched in case the caller
     /// cares which specific reserved word was found.
@@ -2459,6 +4010,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     /// Checks that one of the specified strings appears as a reserved word
     /// and consumes it, returning the string it matched in case the caller
     /// cares which specific reserved word was found.
+    #[allow(clippy::result_unit_err)]
     pub fn reserved_word<'a>(&mut self, words: &'a [&str]) -> Result<&'a str, ()> {
         match self.peek_reserved_word(words) {
             Some(s) => {
@@ -2481,7 +4033,25 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         &mut self,
         cfg: CommandGroupDelimiters<'_, '_, '_>,
     ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
-        let group = self.command_group_internal(cfg)?;
+        self.command_group_until(|slf| slf.peek_command_group_delimiter(&cfg))
+    }
+
+    /// Parses commands until the provided predicate returns `true` (or EOF is reached).
+    ///
+    /// The predicate is consulted before each complete command (i.e. at the start
+    /// and after every command separator such as `;`, `&`, or a newline), and should
+    /// only peek at the upcoming tokens without consuming them. This allows parsing
+    /// command groups which are terminated by arbitrary constructs.
+    ///
+    /// It is considered an error if no commands are present.
+    pub fn command_group_until<F>(
+        &mut self,
+        stop: F,
+    ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error>
+    where
+        F: FnMut(&mut Self) -> bool,
+    {
+        let group = self.command_group_until_internal(stop)?;
         if group.commands.is_empty() {
             Err(self.make_unexpected_err())
         } else {
@@ -2489,40 +4059,86 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         }
     }
 
+    /// Parses commands until any of the provided tokens (or EOF) is reached,
+    /// without consuming the token, e.g. to parse the body of a brace group or
+    /// subshell whose delimiters are framed by the caller.
+    ///
+    /// Unlike `Parser::command_group`, the end tokens need not appear after a
+    /// command separator. Any comments trailing the last command are passed to
+    /// the builder via `Builder::comments`.
+    ///
+    /// It is considered an error if no commands are present.
+    pub fn command_list_terminated_by(
+        &mut self,
+        end: &[Token],
+    ) -> ParseResult<Vec<B::Command>, B::Error> {
+        let group = self.command_group(CommandGroupDelimiters {
+            exact_tokens: end,
+            ..Default::default()
+        })?;
+
+        // The trailing comments immediately precede the end token
+        self.builder
+            .comments(group.trailing_comments)
+            .map_err(custom_at(self.iter.pos()))?;
+        Ok(group.commands)
+    }
+
+    /// Parses the body of a compound command, e.g. the commands following `then`,
+    /// `else`, or `do`.
+    ///
+    /// Like `Parser::command_group`, it is considered an error if no commands are
+    /// present, unless the parser is configured to allow empty bodies, in which case
+    /// a lone `;` may also appear in place of the body.
+    fn compound_body(
+        &mut self,
+        cfg: CommandGroupDelimiters<'_, '_, '_>,
+    ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
+        if !self.config.allow_empty_bodies {
+            return self.command_group(cfg);
+        }
+
+        self.skip_whitespace();
+        eat_maybe!(self, {
+            Semi => {};
+            _ => {}
+        });
+
+        self.command_group_internal(cfg)
+    }
+
     /// Like `compound_list`, but allows for the list of commands to be empty.
     fn command_group_internal(
         &mut self,
         cfg: CommandGroupDelimiters<'_, '_, '_>,
     ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error> {
-        let found_delim = |slf: &mut Parser<_, _>| {
-            let found_exact = !cfg.exact_tokens.is_empty()
-                && slf
-                    .iter
-                    .peek()
-                    .map(|peeked| cfg.exact_tokens.iter().any(|tok| tok == peeked))
-                    .unwrap_or(false);
-
-            found_exact
-                || slf.peek_reserved_word(cfg.reserved_words).is_some()
-                || slf.peek_reserved_token(cfg.reserved_tokens).is_some()
-        };
+        self.command_group_until_internal(|slf| slf.peek_command_group_delimiter(&cfg))
+    }
 
+    /// Like `Parser::command_group_until`, but allows the group to be empty.
+    fn command_group_until_internal<F>(
+        &mut self,
+        mut stop: F,
+    ) -> ParseResult<builder::CommandGroup<B::Command>, B::Error>
+    where
+        F: FnMut(&mut Self) -> bool,
+    {
         let mut cmds = Vec::new();
         let mut trailing_comments = Vec::new();
         loop {
-            if found_delim(self) {
+            if stop(self) {
                 break;
             }
 
             let leading_comments = self.linebreak();
 
-            if found_delim(self) || self.iter.peek().is_none() {
+            if stop(self) || self.iter.peek().is_none() {
                 debug_assert!(trailing_comments.is_empty());
                 trailing_comments = leading_comments;
                 break;
             }
 
-            cmds.push(self.complete_command_with_leading_comments(leading_comments)?);
+            cmds.push(self.complete_command_after_comments(leading_comments)?.0);
         }
 
         Ok(builder::CommandGroup {
@@ -2531,9 +4147,29 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         })
     }
 
+    /// Checks if any of the configured command group delimiters come next,
+    /// without consuming them.
+    fn peek_command_group_delimiter(&mut self, cfg: &CommandGroupDelimiters<'_, '_, '_>) -> bool {
+        let found_exact = !cfg.exact_tokens.is_empty()
+            && self
+                .iter
+                .peek()
+                .map(|peeked| cfg.exact_tokens.iter().any(|tok| tok == peeked))
+                .unwrap_or(false);
+
+        found_exact
+            || self.peek_reserved_word(cfg.reserved_words).is_some()
+            || self.peek_reserved_token(cfg.reserved_tokens).is_some()
+    }
+
     /// Parses the body of any arbitrary arithmetic expression, e.g. `x + $y << 5`.
     /// The caller is responsible for parsing the external `$(( ))` tokens.
     pub fn arithmetic_substitution(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
+        self.nested(Self::arithmetic_substitution_internal)
+    }
+
+    /// Like `Parser::arithmetic_substitution`, but does not track the nesting depth.
+    fn arithmetic_substitution_internal(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         let mut exprs = Vec::new();
         loop {
             self.skip_whitespace();
@@ -2606,7 +4242,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             _ => unreachable!(),
         };
 
-        let value = Box::new(self.arith_assig()?);
+        let value = Box::new(self.nested(Self::arith_assig)?);
         let expr = match op {
             Star => Box::new(Mult(Box::new(Var(var.clone())), value)),
             Slash => Box::new(Div(Box::new(Var(var.clone())), value)),
@@ -2630,10 +4266,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         self.skip_whitespace();
         eat_maybe!(self, {
             Question => {
-                let body = self.arith_ternary()?;
+                let body = self.nested(Self::arith_ternary)?;
                 self.skip_whitespace();
                 eat!(self, { Colon => {} });
-                let els = self.arith_ternary()?;
+                let els = self.nested(Self::arith_ternary)?;
                 Ok(ast::Arithmetic::Ternary(Box::new(guard), Box::new(body), Box::new(els)))
             };
             _ => { Ok(guard) },
@@ -2776,7 +4412,7 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             eat!(self, { Star => {} });
             Ok(ast::Arithmetic::Pow(
                 Box::new(expr),
-                Box::new(self.arith_pow()?),
+                Box::new(self.nested(Self::arith_pow)?),
             ))
         } else {
             Ok(expr)
@@ -2785,6 +4421,11 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
 
     /// Parses expressions such as `!expr`, `~expr`, `+expr`, `-expr`, `++var` and `--var`.
     fn arith_unary_misc(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
+        self.nested(Self::arith_unary_misc_internal)
+    }
+
+    /// Like `Parser::arith_unary_misc`, but does not track the nesting depth.
+    fn arith_unary_misc_internal(&mut self) -> ParseResult<DefaultArithmetic, B::Error> {
         self.skip_whitespace();
         let expr = eat_maybe!(self, {
             Bang  => { ast::Arithmetic::LogicalNot(Box::new(self.arith_unary_misc()?)) },
@@ -2835,34 +4476,10 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
             }
         });
 
-        let num = if let Some(&Literal(ref s)) = self.iter.peek() {
-            if s.starts_with("0x") || s.starts_with("0X") {
-                // from_str_radix does not like it when 0x is present
-                // in the string to parse, thus we should strip it off.
-                // Also, if the string is empty from_str_radix will return
-                // an error; shells like bash and zsh treat `0x` as `0x0`
-                // so we will do the same.
-                let num = &s[2..];
-                if num.is_empty() {
-                    Some(0)
-                } else {
-                    isize::from_str_radix(&s[2..], 16).ok()
-                }
-            } else if s.starts_with('0') {
-                isize::from_str_radix(s, 8).ok()
-            } else {
-                isize::from_str_radix(s, 10).ok()
-            }
-        } else {
-            None
-        };
+        let num = self.arith_number()?;
 
         let expr = match num {
-            Some(num) => {
-                // Make sure we consume the Token::Literal which holds the number
-                self.iter.next();
-                ast::Arithmetic::Literal(num)
-            }
+            Some(num) => ast::Arithmetic::Literal(num),
             None => {
                 let var = self.arith_var()?;
 
@@ -2894,6 +4511,64 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
         Ok(expr)
     }
 
+    /// Parses a numeric literal such as `42`, `0x2a`, `052`, or `base#digits`
+    /// (e.g. `2#101010`) if one is present, otherwise nothing is consumed.
+    ///
+    /// Bases from 2 to 64 are supported, using the digits `0-9`, `a-z`, `A-Z`,
+    /// `@`, and `_` (letters are case insensitive for bases up to 36). Any digits
+    /// which are invalid for the base of the number result in a `BadSubst` error.
+    fn arith_number(&mut self) -> ParseResult<Option<isize>, B::Error> {
+        let start_pos = self.iter.pos();
+        let lit = match self.iter.peek() {
+            Some(Literal(s)) if s.starts_with(|c: char| c.is_ascii_digit()) => s.clone(),
+            _ => return Ok(None),
+        };
+        self.iter.next();
+
+        if Some(&Pound) != self.iter.peek() {
+            let num = if lit.starts_with("0x") || lit.starts_with("0X") {
+                // Shells like bash and zsh treat `0x` as `0x0` so we will do the same.
+                let digits = &lit[2..];
+                if digits.is_empty() {
+                    Some(0)
+                } else {
+                    parse_arith_digits(digits, 16)
+                }
+            } else if lit.starts_with('0') {
+                parse_arith_digits(&lit, 8)
+            } else {
+                parse_arith_digits(&lit, 10)
+            };
+
+            return num
+                .map(Some)
+                .ok_or(ParseError::BadSubst(Literal(lit), start_pos));
+        }
+
+        let base = match lit.parse() {
+            Ok(base) if (2..=64).contains(&base) => base,
+            _ => return Err(ParseError::BadSubst(Literal(lit), start_pos)),
+        };
+
+        let pound_pos = self.iter.pos();
+        self.iter.next();
+
+        let digits_pos = self.iter.pos();
+        let mut digit_toks = Vec::new();
+        while let Some(&Literal(_)) | Some(&Name(_)) | Some(&At) = self.iter.peek() {
+            digit_toks.push(self.iter.next().unwrap());
+        }
+
+        if digit_toks.is_empty() {
+            return Err(ParseError::BadSubst(Pound, pound_pos));
+        }
+
+        match parse_arith_digits(&concat_tokens(&digit_toks), base) {
+            Some(num) => Ok(Some(num)),
+            None => Err(ParseError::BadSubst(digit_toks.swap_remove(0), digits_pos)),
+        }
+    }
+
     /// Parses a variable name in the form `name` or `$name`.
     #[inline]
     fn arith_var(&mut self) -> ParseResult<String, B::Error> {
@@ -2912,6 +4587,288 @@ impl<I: Iterator<Item = Token>, B: Builder> Parser<I, B> {
     }
 }
 
+/// Checks whether a word would end at the provided token, i.e. the token is a
+/// word delimiter or there are no more tokens.
+fn ends_word(token: Option<&Token>) -> bool {
+    match token {
+        Some(t) => t.is_word_delimiter(),
+        None => true,
+    }
+}
+
+/// Removes a trailing `\r` (lexed as whitespace) from a line of tokens, so that
+/// `\r\n` line endings are treated the same as `\n`.
+fn strip_trailing_cr(tokens: &mut Vec<Token>) {
+    let now_empty = match tokens.last_mut() {
+        Some(Whitespace(w)) if w.ends_with('\r') => {
+            w.pop();
+            w.is_empty()
+        }
+        _ => return,
+    };
+
+    if now_empty {
+        tokens.pop();
+    }
+}
+
+/// Removes the extended glob operator (one of `?`, `*`, `+`, `@`, or `!`)
+/// which ends the provided pattern fragments, if present.
+fn take_extglob_operator<C, A>(fragments: &mut Vec<WordKind<C, A>>) -> Option<char> {
+    let operator = match fragments.last_mut() {
+        Some(Simple(SimpleWordKind::Star)) => '*',
+        Some(Simple(SimpleWordKind::Question)) => '?',
+        Some(Simple(SimpleWordKind::Literal(ref mut s))) => match s.chars().last() {
+            Some(c @ '+') | Some(c @ '@') | Some(c @ '!') => {
+                s.pop();
+                if !s.is_empty() {
+                    return Some(c);
+                }
+                c
+            }
+            _ => return None,
+        },
+        _ => return None,
+    };
+
+    fragments.pop();
+    Some(operator)
+}
+
+/// Joins the fragments of each character class (e.g. `[:digit:]`), equivalence
+/// class (e.g. `[=a=]`), or collating symbol (e.g. `[.-.]`) within a bracket
+/// expression into a single literal.
+fn merge_bracket_classes<C, A>(fragments: Vec<WordKind<C, A>>) -> Vec<WordKind<C, A>> {
+    let mut merged = Vec::with_capacity(fragments.len());
+    let mut in_bracket = false;
+    let mut iter = fragments.into_iter().peekable();
+
+    while let Some(fragment) = iter.next() {
+        match fragment {
+            Simple(SimpleWordKind::SquareOpen) if in_bracket => {
+                let mut class = String::new();
+                let mut lookahead = Vec::new();
+                while let Some(&Simple(SimpleWordKind::Literal(ref s))) = iter.peek() {
+                    class.push_str(s);
+                    lookahead.push(iter.next().unwrap());
+                }
+
+                let delim = class.chars().next();
+                let is_class = class.len() >= 2
+                    && matches!(delim, Some(':') | Some('=') | Some('.'))
+                    && class.ends_with(delim.unwrap())
+                    && matches!(iter.peek(), Some(&Simple(SimpleWordKind::SquareClose)));
+
+                if is_class {
+                    iter.next();
+                    merged.push(Simple(SimpleWordKind::Literal(format!("[{}]", class))));
+                } else {
+                    merged.push(Simple(SimpleWordKind::SquareOpen));
+                    merged.extend(lookahead);
+                }
+            }
+
+            Simple(SimpleWordKind::SquareOpen) => {
+                in_bracket = true;
+                merged.push(fragment);
+            }
+
+            Simple(SimpleWordKind::SquareClose) => {
+                in_bracket = false;
+                merged.push(fragment);
+            }
+
+            fragment => merged.push(fragment),
+        }
+    }
+
+    merged
+}
+
+/// Replaces each `[`, its members, and its closing `]` with a single
+/// `WordKind::BracketClass`, e.g. `[!a-z]`.
+///
+/// A `]` directly after the opening `[` (or after a negating `[!` or `[^`) is
+/// a member of the set rather than its end. Sets which are never closed, or
+/// which contain anything other than unquoted literals, are left as is.
+fn group_bracket_classes<C, A>(fragments: Vec<WordKind<C, A>>) -> Vec<WordKind<C, A>> {
+    let mut grouped = Vec::with_capacity(fragments.len());
+    let mut iter = fragments.into_iter().peekable();
+
+    while let Some(fragment) = iter.next() {
+        if !matches!(fragment, Simple(SimpleWordKind::SquareOpen)) {
+            grouped.push(fragment);
+            continue;
+        }
+
+        let mut members = String::new();
+        let mut lookahead = Vec::new();
+        let mut closed = false;
+        while let Some(Simple(next)) = iter.peek() {
+            match *next {
+                SimpleWordKind::Literal(ref s) => members.push_str(s),
+                SimpleWordKind::Star => members.push('*'),
+                SimpleWordKind::Question => members.push('?'),
+                SimpleWordKind::Tilde => members.push('~'),
+                SimpleWordKind::Colon => members.push(':'),
+                SimpleWordKind::SquareOpen => members.push('['),
+                SimpleWordKind::SquareClose => match members.as_str() {
+                    "" | "!" | "^" => members.push(']'),
+                    _ => {
+                        iter.next();
+                        closed = true;
+                        break;
+                    }
+                },
+
+                SimpleWordKind::Escaped(_)
+                | SimpleWordKind::Param(_)
+                | SimpleWordKind::Subst(_)
+                | SimpleWordKind::CommandSubst(_) => break,
+            }
+
+            lookahead.push(iter.next().unwrap());
+        }
+
+        if closed {
+            let negated = members.starts_with('!') || members.starts_with('^');
+            if negated {
+                members.remove(0);
+            }
+            grouped.push(BracketClass(negated, members));
+        } else {
+            // Any `]` which could close a later `[` that we've already scanned
+            // past would have closed this one as well, so everything up to
+            // where we stopped can be kept as is.
+            grouped.push(fragment);
+            grouped.extend(lookahead);
+        }
+    }
+
+    grouped
+}
+
+/// Parses the digits of an arithmetic literal in the specified base (between 2 and 64),
+/// returning `None` if any digit is invalid for the base or the value overflows.
+fn parse_arith_digits(digits: &str, base: u32) -> Option<isize> {
+    digits.chars().try_fold(0isize, |num, c| {
+        let digit = match c {
+            '0'..='9' => c as u32 - '0' as u32,
+            'a'..='z' => c as u32 - 'a' as u32 + 10,
+            'A'..='Z' if base <= 36 => c as u32 - 'A' as u32 + 10,
+            'A'..='Z' => c as u32 - 'A' as u32 + 36,
+            '@' => 62,
+            '_' => 63,
+            _ => return None,
+        };
+
+        if digit >= base {
+            return None;
+        }
+
+        num.checked_mul(base as isize)?.checked_add(digit as isize)
+    })
+}
+
+/// Returns a closure which attaches the position of the source that the AST
+/// builder was constructing to any error it returns.
+fn custom_at<E>(pos: SourcePos) -> impl FnOnce(E) -> ParseError<E> {
+    move |e| ParseError::CustomAt(e, pos)
+}
+
+/// Replaces the escape sequences recognized within an ANSI-C quoted string
+/// (e.g. `\n`, `\x41`, or `\cA`) with the characters they represent.
+/// Unrecognized escapes are left untouched, backslash included.
+fn unescape_ansi_c(raw: &str) -> String {
+    fn take_digits<I: Iterator<Item = char>>(
+        chars: &mut ::std::iter::Peekable<I>,
+        radix: u32,
+        max: usize,
+        value: &mut u32,
+    ) -> usize {
+        let mut count = 0;
+        while count < max {
+            match chars.peek().and_then(|c| c.to_digit(radix)) {
+                Some(d) => {
+                    *value = *value * radix + d;
+                    chars.next();
+                    count += 1;
+                }
+                None => break,
+            }
+        }
+        count
+    }
+
+    let mut s = String::with_capacity(raw.len());
+    let mut chars = raw.chars().peekable();
+    while let Some(c) = chars.next() {
+        if c != '\\' {
+            s.push(c);
+            continue;
+        }
+
+        let escaped = match chars.next() {
+            Some(c) => c,
+            None => {
+                s.push('\\');
+                break;
+            }
+        };
+
+        let ch = match escaped {
+            'a' => Some('\u{07}'),
+            'b' => Some('\u{08}'),
+            'e' | 'E' => Some('\u{1b}'),
+            'f' => Some('\u{0c}'),
+            'n' => Some('\n'),
+            'r' => Some('\r'),
+            't' => Some('\t'),
+            'v' => Some('\u{0b}'),
+            '\\' | '\'' | '"' | '?' => Some(escaped),
+
+            '0'..='7' => {
+                let mut value = escaped.to_digit(8).unwrap();
+                take_digits(&mut chars, 8, 2, &mut value);
+                ::std::char::from_u32(value & 0xff)
+            }
+
+            'x' | 'u' | 'U' => {
+                let max = match escaped {
+                    'x' => 2,
+                    'u' => 4,
+                    _ => 8,
+                };
+
+                let mut value = 0;
+                match take_digits(&mut chars, 16, max, &mut value) {
+                    0 => None,
+                    _ => ::std::char::from_u32(value).or(Some(::std::char::REPLACEMENT_CHARACTER)),
+                }
+            }
+
+            'c' => match chars.next() {
+                // Like bash, `\c?` yields DEL rather than the unit separator
+                Some('?') => Some('\x7f'),
+                Some(c) => ::std::char::from_u32((c as u32) & 0x1f),
+                None => None,
+            },
+
+            _ => None,
+        };
+
+        match ch {
+            Some(ch) => s.push(ch),
+            None => {
+                s.push('\\');
+                s.push(escaped);
+            }
+        }
+    }
+
+    s
+}
+
 fn concat_tokens(tokens: &[Token]) -> String {
     let len = tokens.iter().fold(0, |len, t| len + t.len());
     let mut s = String::with_capacity(len);
@@ -2962,25 +4919,65 @@ mod tests {
 
     #[test]
     fn test_function_declaration_comments_before_body() {
+        use crate::ast::builder::FunctionDeclarationKind::*;
         use std::iter::repeat;
 
         let cases_brace = vec![
-            "function foo() #comment1\n\n#comment2\n { echo body; }",
-            "function foo () #comment1\n\n#comment2\n { echo body; }",
-            "function foo (  ) #comment1\n\n#comment2\n { echo body; }",
-            "function foo(  ) #comment1\n\n#comment2\n { echo body; }",
-            "function foo #comment1\n\n#comment2\n   { echo body; }",
-            "foo() #comment1\n\n#comment2\n          { echo body; }",
-            "foo () #comment1\n\n#comment2\n         { echo body; }",
-            "foo (  ) #comment1\n\n#comment2\n         { echo body; }",
-            "foo(  ) #comment1\n\n#comment2\n         { echo body; }",
+            (
+                "function foo() #comment1\n\n#comment2\n { echo body; }",
+                KeywordAndParens,
+            ),
+            (
+                "function foo () #comment1\n\n#comment2\n { echo body; }",
+                KeywordAndParens,
+            ),
+            (
+                "function foo (  ) #comment1\n\n#comment2\n { echo body; }",
+                KeywordAndParens,
+            ),
+            (
+                "function foo(  ) #comment1\n\n#comment2\n { echo body; }",
+                KeywordAndParens,
+            ),
+            (
+                "function foo #comment1\n\n#comment2\n   { echo body; }",
+                Keyword,
+            ),
+            (
+                "foo() #comment1\n\n#comment2\n          { echo body; }",
+                Parens,
+            ),
+            (
+                "foo () #comment1\n\n#comment2\n         { echo body; }",
+                Parens,
+            ),
+            (
+                "foo (  ) #comment1\n\n#comment2\n         { echo body; }",
+                Parens,
+            ),
+            (
+                "foo(  ) #comment1\n\n#comment2\n         { echo body; }",
+                Parens,
+            ),
         ];
 
         let cases_subshell = vec![
-            "function foo() #comment1\n\n#comment2\n (echo body)",
-            "function foo #comment1\n\n#comment2\n   (echo body)",
-            "foo() #comment1\n\n#comment2\n          (echo body)",
-            "foo () #comment1\n\n#comment2\n         (echo body)",
+            (
+                "function foo() #comment1\n\n#comment2\n (echo body)",
+                KeywordAndParens,
+            ),
+            (
+                "function foo #comment1\n\n#comment2\n   (echo body)",
+                Keyword,
+            ),
+            (
+                "foo() #comment1\n\n#comment2\n          (echo body)",
+                Parens,
+            ),
+            (
+                "foo () #comment1\n\n#comment2\n         (echo body)",
+                Parens,
+            ),
         ];
 
         let comments = vec![
@@ -3004,7 +5001,7 @@ mod tests {
             .into_iter()
             .zip(repeat(body_brace))
             .chain(cases_subshell.into_iter().zip(repeat(body_subshell)))
-            .map(|(src, body)| (src, (name.clone(), comments.clone(), body)));
+            .map(|((src, kind), body)| (src, (kind, name.clone(), comments.clone(), body)));
 
         for (src, correct) in iter {
             assert_eq!(
@@ -3014,6 +5011,27 @@ mod tests {
         }
     }
 
+    #[test]
+    fn test_function_declaration_kind() {
+        use crate::ast::builder::FunctionDeclarationKind::*;
+
+        let cases = vec![
+            ("foo() { echo; }", Parens),
+            ("function foo() { echo; }", KeywordAndParens),
+            ("function foo { echo; }", Keyword),
+            ("function foo (echo)", Keyword),
+        ];
+
+        for (src, kind) in cases {
+            let (found, _, _, _) = make_parser(src).function_declaration_internal().unwrap();
+            assert_eq!(kind, found, "{}", src);
+        }
+
+        assert!(make_parser("foo { echo; }")
+            .function_declaration_internal()
+            .is_err());
+    }
+
     #[test]
     fn test_word_preserve_trailing_whitespace() {
         let mut p = make_parser("test       ");
@@ -3023,7 +5041,7 @@ mod tests {
 
     #[test]
     fn test_parameter_substitution_command_can_contain_comments() {
-        let param_subst = builder::SimpleWordKind::Subst(Box::new(
+        let param_subst = SimpleWordKind::Subst(Box::new(
             builder::ParameterSubstitutionKind::Command(builder::CommandGroup {
                 commands: vec![cmd("foo")],
                 trailing_comments: vec![Newline(Some("#comment".into()))],
@@ -3037,7 +5055,7 @@ mod tests {
 
     #[test]
     fn test_backticked_command_can_contain_comments() {
-        let cmd_subst = builder::SimpleWordKind::CommandSubst(builder::CommandGroup {
+        let cmd_subst = SimpleWordKind::CommandSubst(builder::CommandGroup {
             commands: vec![cmd("foo")],
             trailing_comments: vec![Newline(Some("#comment".into()))],
         });
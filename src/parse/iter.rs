@@ -14,7 +14,7 @@ pub struct UnmatchedError(pub Token, pub SourcePos);
 
 /// An internal variant that indicates if a token should be yielded
 /// or the current position updated to some value.
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 enum TokenOrPos {
     /// A consumed token which should be yielded.
     Tok(Token),
@@ -33,13 +33,23 @@ impl TokenOrPos {
     }
 }
 
+/// Returns the starting position of a source, tracking lines and columns
+/// only if `pos` does so as well.
+fn start_pos_like(pos: SourcePos) -> SourcePos {
+    if pos.tracks_line_col() {
+        SourcePos::new()
+    } else {
+        SourcePos::new_byte_only()
+    }
+}
+
 /// An iterator that can track its internal position in the stream.
 pub trait PositionIterator: Iterator {
     /// Get the current position of the iterator.
     fn pos(&self) -> SourcePos;
 }
 
-impl<'a, T: PositionIterator> PositionIterator for &'a mut T {
+impl<T: PositionIterator> PositionIterator for &mut T {
     fn pos(&self) -> SourcePos {
         (**self).pos()
     }
@@ -53,7 +63,7 @@ pub trait PeekableIterator: Iterator {
     fn peek(&mut self) -> Option<&Self::Item>;
 }
 
-impl<'a, T: PeekableIterator> PeekableIterator for &'a mut T {
+impl<T: PeekableIterator> PeekableIterator for &mut T {
     fn peek(&mut self) -> Option<&Self::Item> {
         (**self).peek()
     }
@@ -125,7 +135,7 @@ trait RewindableTokenIterator {
 
 /// A Token iterator that keeps track of how many lines have been read.
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub struct TokenIter<I> {
     /// The underlying token iterator being wrapped. Iterator is fused to avoid
     /// inconsistent behavior when doing multiple peek ahead operations.
@@ -153,7 +163,7 @@ impl<I: Iterator<Item = Token>> PeekableIterator for TokenIter<I> {
         // the borrow checker.
         let _ = self.multipeek().peek_next()?;
 
-        if let Some(&TokenOrPos::Tok(ref t)) = self.prev_buffered.last() {
+        if let Some(TokenOrPos::Tok(t)) = self.prev_buffered.last() {
             Some(t)
         } else {
             unreachable!("unexpected state: peeking next token failed. This is a bug!")
@@ -165,20 +175,16 @@ impl<I: Iterator<Item = Token>> Iterator for TokenIter<I> {
     type Item = Token;
 
     fn next(&mut self) -> Option<Token> {
-        let mut ret = None;
-        loop {
-            // Make sure we update our current position before continuing.
-            match self.next_token_or_pos() {
-                Some(TokenOrPos::Tok(next)) => {
-                    self.pos.advance(&next);
-                    ret = Some(next);
-                    break;
-                }
-
-                Some(TokenOrPos::Pos(_)) => panic!("unexpected state. This is a bug!"),
-                None => break,
+        // Make sure we update our current position before continuing.
+        let ret = match self.next_token_or_pos() {
+            Some(TokenOrPos::Tok(next)) => {
+                self.pos.advance(&next);
+                Some(next)
             }
-        }
+
+            Some(TokenOrPos::Pos(_)) => panic!("unexpected state. This is a bug!"),
+            None => None,
+        };
 
         // Make sure we update our position according to any trailing `Pos` points.
         // The parser expects that polling our current position will give it the
@@ -231,6 +237,15 @@ impl<I: Iterator<Item = Token>> TokenIter<I> {
         iter
     }
 
+    /// Replaces the underlying Token iterator, discarding any buffered tokens
+    /// and resetting the current position (without changing whether lines and
+    /// columns are tracked), while reusing existing allocations.
+    pub fn reset(&mut self, iter: I) {
+        self.iter = iter.fuse();
+        self.prev_buffered.clear();
+        self.pos = start_pos_like(self.pos);
+    }
+
     /// Return a wrapper which allows for arbitrary look ahead. Dropping the
     /// wrapper will restore the internal stream back to what it was.
     pub fn multipeek(&mut self) -> Multipeek<'_> {
@@ -313,7 +328,7 @@ pub struct Multipeek<'a> {
 
 impl<'a> Drop for Multipeek<'a> {
     fn drop(&mut self) {
-        let tokens = mem::replace(&mut self.buf, Vec::new());
+        let tokens = mem::take(&mut self.buf);
         self.iter.rewind(tokens);
     }
 }
@@ -345,7 +360,7 @@ impl<'a> Multipeek<'a> {
             }
         }
 
-        if let Some(&TokenOrPos::Tok(ref t)) = self.buf.last() {
+        if let Some(TokenOrPos::Tok(t)) = self.buf.last() {
             Some(t)
         } else {
             None
@@ -356,7 +371,7 @@ impl<'a> Multipeek<'a> {
 /// A wrapper which allows treating `TokenIter<I>` and `TokenIter<Empty<_>>` as
 /// the same thing, even though they are technically different types.
 #[must_use = "iterator adaptors are lazy and do nothing unless consumed"]
-#[derive(Debug)]
+#[derive(Debug, Clone)]
 pub enum TokenIterWrapper<I> {
     /// A `TokenIter` which holds an aribtrary `Iterator` over `Token`s.
     Regular(TokenIter<I>),
@@ -406,6 +421,17 @@ impl<I: Iterator<Item = Token>> TokenIterWrapper<I> {
         }
     }
 
+    /// Delegates to `TokenIter::reset`, replacing a `Buffered` iterator if necessary.
+    pub fn reset(&mut self, iter: I) {
+        match *self {
+            TokenIterWrapper::Regular(ref mut inner) => inner.reset(iter),
+            TokenIterWrapper::Buffered(ref inner) => {
+                let pos = start_pos_like(inner.pos());
+                *self = TokenIterWrapper::Regular(TokenIter::with_position(iter, pos))
+            }
+        }
+    }
+
     /// Delegates to `TokenIter::buffer_tokens_to_yield_first`.
     pub fn buffer_tokens_to_yield_first(&mut self, buf: Vec<Token>, buf_start: SourcePos) {
         match *self {
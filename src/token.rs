@@ -63,9 +63,9 @@ impl Positional {
     }
 }
 
-impl Into<u8> for Positional {
-    fn into(self) -> u8 {
-        self.as_num()
+impl From<Positional> for u8 {
+    fn from(p: Positional) -> u8 {
+        p.as_num()
     }
 }
 
@@ -138,6 +138,10 @@ pub enum Token {
     OrIf,
     /// ;;
     DSemi,
+    /// ;&
+    SemiAmp,
+    /// ;;&
+    DSemiAmp,
 
     /// <
     Less,
@@ -157,6 +161,12 @@ pub enum Token {
     Clobber,
     /// <>
     LessGreat,
+    /// &>
+    AmpGreat,
+    /// &>>
+    AmpDGreat,
+    /// &>|
+    AmpClobber,
 
     /// $
     Dollar,
@@ -193,13 +203,69 @@ impl Token {
         self.as_str().len()
     }
 
+    /// Returns the kind of the token, discarding any data it carries.
+    pub fn kind(&self) -> TokenKind {
+        match *self {
+            Newline => TokenKind::Newline,
+            ParenOpen => TokenKind::ParenOpen,
+            ParenClose => TokenKind::ParenClose,
+            CurlyOpen => TokenKind::CurlyOpen,
+            CurlyClose => TokenKind::CurlyClose,
+            SquareOpen => TokenKind::SquareOpen,
+            SquareClose => TokenKind::SquareClose,
+            Bang => TokenKind::Bang,
+            Tilde => TokenKind::Tilde,
+            Pound => TokenKind::Pound,
+            Star => TokenKind::Star,
+            Question => TokenKind::Question,
+            Backslash => TokenKind::Backslash,
+            Percent => TokenKind::Percent,
+            Dash => TokenKind::Dash,
+            Equals => TokenKind::Equals,
+            Plus => TokenKind::Plus,
+            Colon => TokenKind::Colon,
+            At => TokenKind::At,
+            Caret => TokenKind::Caret,
+            Slash => TokenKind::Slash,
+            Comma => TokenKind::Comma,
+            SingleQuote => TokenKind::SingleQuote,
+            DoubleQuote => TokenKind::DoubleQuote,
+            Backtick => TokenKind::Backtick,
+            Semi => TokenKind::Semi,
+            Amp => TokenKind::Amp,
+            Pipe => TokenKind::Pipe,
+            AndIf => TokenKind::AndIf,
+            OrIf => TokenKind::OrIf,
+            DSemi => TokenKind::DSemi,
+            SemiAmp => TokenKind::SemiAmp,
+            DSemiAmp => TokenKind::DSemiAmp,
+            Less => TokenKind::Less,
+            Great => TokenKind::Great,
+            DLess => TokenKind::DLess,
+            DGreat => TokenKind::DGreat,
+            GreatAnd => TokenKind::GreatAnd,
+            LessAnd => TokenKind::LessAnd,
+            DLessDash => TokenKind::DLessDash,
+            Clobber => TokenKind::Clobber,
+            LessGreat => TokenKind::LessGreat,
+            AmpGreat => TokenKind::AmpGreat,
+            AmpDGreat => TokenKind::AmpDGreat,
+            AmpClobber => TokenKind::AmpClobber,
+            Dollar => TokenKind::Dollar,
+            ParamPositional(_) => TokenKind::ParamPositional,
+            Whitespace(_) => TokenKind::Whitespace,
+            Literal(_) => TokenKind::Literal,
+            Name(_) => TokenKind::Name,
+        }
+    }
+
     /// Indicates whether a word can be delimited by this token
     /// when the token is **not** quoted or escaped.
     pub fn is_word_delimiter(&self) -> bool {
         match *self {
             Newline | ParenOpen | ParenClose | Semi | Amp | Less | Great | Pipe | AndIf | OrIf
-            | DSemi | DLess | DGreat | GreatAnd | LessAnd | DLessDash | Clobber | LessGreat
-            | Whitespace(_) => true,
+            | DSemi | SemiAmp | DSemiAmp | DLess | DGreat | GreatAnd | LessAnd | DLessDash
+            | Clobber | LessGreat | AmpGreat | AmpDGreat | AmpClobber | Whitespace(_) => true,
 
             Bang | Star | Question | Backslash | SingleQuote | DoubleQuote | Backtick | Percent
             | Dash | Equals | Plus | Colon | At | Caret | Slash | Comma | CurlyOpen
@@ -245,6 +311,8 @@ impl Token {
             AndIf => "&&",
             OrIf => "||",
             DSemi => ";;",
+            SemiAmp => ";&",
+            DSemiAmp => ";;&",
             DLess => "<<",
             DGreat => ">>",
             GreatAnd => ">&",
@@ -252,6 +320,9 @@ impl Token {
             DLessDash => "<<-",
             Clobber => ">|",
             LessGreat => "<>",
+            AmpGreat => "&>",
+            AmpDGreat => "&>>",
+            AmpClobber => "&>|",
 
             ParamPositional(Positional::Zero) => "$0",
             ParamPositional(Positional::One) => "$1",
@@ -268,3 +339,196 @@ impl Token {
         }
     }
 }
+
+/// The kind of a `Token`, without any of the data it may carry.
+///
+/// Useful for describing which tokens are acceptable without having to
+/// make up a payload, e.g. that any `Token::Name` would be valid.
+#[derive(PartialEq, Eq, Debug, Clone, Copy, Hash)]
+pub enum TokenKind {
+    /// \n
+    Newline,
+
+    /// (
+    ParenOpen,
+    /// )
+    ParenClose,
+    /// {
+    CurlyOpen,
+    /// }
+    CurlyClose,
+    /// [
+    SquareOpen,
+    /// ]
+    SquareClose,
+
+    /// !
+    Bang,
+    /// ~
+    Tilde,
+    /// \#
+    Pound,
+    /// *
+    Star,
+    /// ?
+    Question,
+    /// \\
+    Backslash,
+    /// %
+    Percent,
+    /// \-
+    Dash,
+    /// \=
+    Equals,
+    /// +
+    Plus,
+    /// :
+    Colon,
+    /// @
+    At,
+    /// ^
+    Caret,
+    /// /
+    Slash,
+    /// ,
+    Comma,
+
+    /// '
+    SingleQuote,
+    /// "
+    DoubleQuote,
+    /// `
+    Backtick,
+
+    /// ;
+    Semi,
+    /// &
+    Amp,
+    /// |
+    Pipe,
+    /// &&
+    AndIf,
+    /// ||
+    OrIf,
+    /// ;;
+    DSemi,
+    /// ;&
+    SemiAmp,
+    /// ;;&
+    DSemiAmp,
+
+    /// <
+    Less,
+    /// \>
+    Great,
+    /// <<
+    DLess,
+    /// \>>
+    DGreat,
+    /// \>&
+    GreatAnd,
+    /// <&
+    LessAnd,
+    /// <<-
+    DLessDash,
+    /// \>|
+    Clobber,
+    /// <>
+    LessGreat,
+    /// &>
+    AmpGreat,
+    /// &>>
+    AmpDGreat,
+    /// &>|
+    AmpClobber,
+
+    /// $
+    Dollar,
+    /// $0, $1, ..., $9
+    ///
+    /// Must be its own token to avoid lumping the positional parameter
+    /// as a `Literal` if the parameter is concatenated to something.
+    ParamPositional,
+
+    /// Any string of whitespace characters NOT including a newline.
+    Whitespace,
+
+    /// Any literal delimited by whitespace.
+    Literal,
+    /// A `Literal` capable of being used as a variable or function name. According to the POSIX
+    /// standard it should only contain alphanumerics or underscores, and does not start with a digit.
+    Name,
+}
+
+impl TokenKind {
+    /// Indicates whether tokens of this kind carry data, in which case they
+    /// are described by what they are rather than how they are written.
+    pub fn has_payload(&self) -> bool {
+        matches!(
+            *self,
+            TokenKind::ParamPositional
+                | TokenKind::Whitespace
+                | TokenKind::Literal
+                | TokenKind::Name
+        )
+    }
+}
+
+impl fmt::Display for TokenKind {
+    fn fmt(&self, fmt: &mut fmt::Formatter<'_>) -> fmt::Result {
+        let fixed = match *self {
+            TokenKind::ParamPositional => return fmt.write_str("a positional parameter"),
+            TokenKind::Whitespace => return fmt.write_str("whitespace"),
+            TokenKind::Literal => return fmt.write_str("a literal"),
+            TokenKind::Name => return fmt.write_str("a name"),
+            TokenKind::Newline => Newline,
+            TokenKind::ParenOpen => ParenOpen,
+            TokenKind::ParenClose => ParenClose,
+            TokenKind::CurlyOpen => CurlyOpen,
+            TokenKind::CurlyClose => CurlyClose,
+            TokenKind::SquareOpen => SquareOpen,
+            TokenKind::SquareClose => SquareClose,
+            TokenKind::Bang => Bang,
+            TokenKind::Tilde => Tilde,
+            TokenKind::Pound => Pound,
+            TokenKind::Star => Star,
+            TokenKind::Question => Question,
+            TokenKind::Backslash => Backslash,
+            TokenKind::Percent => Percent,
+            TokenKind::Dash => Dash,
+            TokenKind::Equals => Equals,
+            TokenKind::Plus => Plus,
+            TokenKind::Colon => Colon,
+            TokenKind::At => At,
+            TokenKind::Caret => Caret,
+            TokenKind::Slash => Slash,
+            TokenKind::Comma => Comma,
+            TokenKind::SingleQuote => SingleQuote,
+            TokenKind::DoubleQuote => DoubleQuote,
+            TokenKind::Backtick => Backtick,
+            TokenKind::Semi => Semi,
+            TokenKind::Amp => Amp,
+            TokenKind::Pipe => Pipe,
+            TokenKind::AndIf => AndIf,
+            TokenKind::OrIf => OrIf,
+            TokenKind::DSemi => DSemi,
+            TokenKind::SemiAmp => SemiAmp,
+            TokenKind::DSemiAmp => DSemiAmp,
+            TokenKind::Less => Less,
+            TokenKind::Great => Great,
+            TokenKind::DLess => DLess,
+            TokenKind::DGreat => DGreat,
+            TokenKind::GreatAnd => GreatAnd,
+            TokenKind::LessAnd => LessAnd,
+            TokenKind::DLessDash => DLessDash,
+            TokenKind::Clobber => Clobber,
+            TokenKind::LessGreat => LessGreat,
+            TokenKind::AmpGreat => AmpGreat,
+            TokenKind::AmpDGreat => AmpDGreat,
+            TokenKind::AmpClobber => AmpClobber,
+            TokenKind::Dollar => Dollar,
+        };
+
+        write!(fmt, "{}", fixed)
+    }
+}
@@ -33,6 +33,26 @@ fn test_and_or_valid_with_newlines_after_operator() {
     assert_eq!(correct, p.and_or_list().unwrap());
 }
 
+#[test]
+fn test_and_or_and_pipeline_valid_with_line_continuations_around_operators() {
+    let cases = [
+        ("foo | bar", "foo |\\\nbar"),
+        ("foo | bar", "foo \\\n| bar"),
+        ("foo && bar", "foo &&\\\nbar"),
+        ("foo && bar", "foo \\\n&& bar"),
+        ("foo || bar", "foo ||\\\n  bar"),
+        ("foo || bar", "foo\\\n|| bar"),
+        ("foo | bar && baz", "foo \\\n| bar \\\n&&\\\n baz"),
+    ];
+
+    for &(single_line, continued) in &cases {
+        let correct = make_parser(single_line).and_or_list().unwrap();
+        let mut p = make_parser(continued);
+        assert_eq!(correct, p.and_or_list().unwrap(), "source: {:?}", continued);
+        assert_eq!(None, p.complete_command().unwrap());
+    }
+}
+
 #[test]
 fn test_and_or_invalid_with_newlines_before_operator() {
     let mut p = make_parser("foo || bar\n\n&& baz");
@@ -43,3 +63,52 @@ fn test_and_or_invalid_with_newlines_before_operator() {
         p.complete_command()
     );
 }
+
+#[test]
+fn test_and_or_list_iter_yields_commands_in_evaluation_order() {
+    let list = make_parser("a && b || c").and_or_list().unwrap();
+    let a = ListableCommand::Single(Simple(cmd_simple("a")));
+    let b = ListableCommand::Single(Simple(cmd_simple("b")));
+    let c = ListableCommand::Single(Simple(cmd_simple("c")));
+
+    let iter = list.iter();
+    assert_eq!(3, iter.len());
+    assert_eq!(
+        vec![
+            (None, &a),
+            (Some(AndOrKind::And), &b),
+            (Some(AndOrKind::Or), &c)
+        ],
+        iter.collect::<Vec<_>>()
+    );
+
+    let list = make_parser("a").and_or_list().unwrap();
+    assert_eq!(vec![(None, &a)], (&list).into_iter().collect::<Vec<_>>());
+}
+
+#[test]
+fn test_and_or_binds_at_shell_level_between_double_parens() {
+    // There is no dedicated `(( expr ))` arithmetic command, so double parens are
+    // parsed as nested subshells, but the `&&` and `||` must still apply to them.
+    let nested = |src: &str| make_parser(src).pipeline().unwrap();
+
+    let correct = CommandList {
+        first: nested("( ( a++ ) )"),
+        rest: vec![AndOr::And(ListableCommand::Single(Simple(
+            cmd_args_simple("echo", &["ok"]),
+        )))],
+    };
+    assert_eq!(
+        correct,
+        make_parser("(( a++ )) && echo ok").and_or_list().unwrap()
+    );
+
+    let correct = CommandList {
+        first: nested("( ( x ) )"),
+        rest: vec![AndOr::Or(nested("( ( y ) )"))],
+    };
+    assert_eq!(
+        correct,
+        make_parser("(( x )) || (( y ))").and_or_list().unwrap()
+    );
+}
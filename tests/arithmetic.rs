@@ -2,6 +2,7 @@
 use conch_parser::ast::Arithmetic::*;
 use conch_parser::ast::DefaultArithmetic as Arithmetic;
 use conch_parser::ast::ParameterSubstitution::Arith;
+use conch_parser::ast::{ComplexWord::Single, SimpleWord, TopLevelWord, Word};
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -29,6 +30,13 @@ fn test_arithmetic_substitution_valid() {
         ("$(( 0Xa ))", Literal(10)),
         ("$(( 0xA ))", Literal(10)),
         ("$(( 0XA ))", Literal(10)),
+        ("$(( 0xff ))", Literal(255)),
+        ("$(( 2#1010 ))", Literal(10)),
+        ("$(( 8#17 ))", Literal(15)),
+        ("$(( 16#fF ))", Literal(255)),
+        ("$(( 36#Zz ))", Literal(1295)),
+        ("$(( 64#A ))", Literal(36)),
+        ("$(( 64#@_ ))", Literal(4031)),
         ("$(( x++ ))", PostIncr(String::from("x"))),
         ("$(( x-- ))", PostDecr(String::from("x"))),
         ("$(( ++x ))", PreIncr(String::from("x"))),
@@ -382,6 +390,26 @@ fn test_arithmetic_substitution_invalid() {
         ("$(( != y  ))", Unexpected(Token::Equals, src(5, 1, 6))),
         ("$(( += y  ))", Unexpected(Token::Equals, src(5, 1, 6))),
         ("$(( -= y  ))", Unexpected(Token::Equals, src(5, 1, 6))),
+        // Digits must be valid for the base of the number
+        (
+            "$(( 2#12 ))",
+            BadSubst(Token::Literal(String::from("12")), src(6, 1, 7)),
+        ),
+        (
+            "$(( 08 ))",
+            BadSubst(Token::Literal(String::from("08")), src(4, 1, 5)),
+        ),
+        (
+            "$(( 0xfg ))",
+            BadSubst(Token::Literal(String::from("0xfg")), src(4, 1, 5)),
+        ),
+        (
+            "$(( 65#1 ))",
+            BadSubst(Token::Literal(String::from("65")), src(4, 1, 5)),
+        ),
+        ("$(( 2# ))", BadSubst(Token::Pound, src(5, 1, 6))),
+        // A `#` does not start a comment within an arithmetic context
+        ("$(( 1 + # ))", Unexpected(Token::Pound, src(8, 1, 9))),
     ];
 
     for (s, correct) in cases.into_iter() {
@@ -613,3 +641,207 @@ fn test_arithmetic_substitution_operators_of_equal_precedence() {
         }
     }
 }
+
+#[test]
+fn test_arithmetic_substitution_line_continuation() {
+    let correct = word_subst(Arith(Some(Add(Box::new(Literal(1)), Box::new(Literal(2))))));
+
+    for s in &[
+        "$(( 1 + \\\n 2 ))",
+        "$(( 1 \\\n+ 2 ))",
+        "$((\\\n1 + 2\\\n))",
+    ] {
+        match make_parser(s).parameter() {
+            Ok(w) => assert_eq!(correct, w, "source: {:?}", s),
+            Err(err) => panic!("Failed to parse the source \"{}\": {}", s, err),
+        }
+    }
+}
+
+#[test]
+fn test_arithmetic_substitution_passed_to_builder() {
+    use conch_parser::ast::{RedirectOrCmdWord, SimpleCommand};
+    use conch_parser::lexer::Lexer;
+    use conch_parser::parse::Parser;
+
+    #[derive(Default)]
+    struct RecordArithmetic {
+        seen: Vec<Arithmetic>,
+    }
+
+    impl BuilderHooks for RecordArithmetic {
+        fn arithmetic(&mut self, expr: &Arithmetic) -> Result<(), String> {
+            self.seen.push(expr.clone());
+            Ok(())
+        }
+    }
+
+    let mut builder = HookedBuilder::<RecordArithmetic>::default();
+    let lex = Lexer::new("echo $((1+2)) $((a*b))".chars());
+    let cmd = Parser::with_builder(lex, &mut builder)
+        .complete_command()
+        .unwrap();
+
+    let one_plus_two = Add(Box::new(Literal(1)), Box::new(Literal(2)));
+    let a_times_b = Mult(
+        Box::new(Var(String::from("a"))),
+        Box::new(Var(String::from("b"))),
+    );
+
+    assert_eq!(
+        builder.hooks.seen,
+        vec![one_plus_two.clone(), a_times_b.clone()]
+    );
+    assert_eq!(
+        cmd,
+        Some(cmd_from_simple(SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: vec![
+                RedirectOrCmdWord::CmdWord(word("echo")),
+                RedirectOrCmdWord::CmdWord(word_subst(Arith(Some(one_plus_two)))),
+                RedirectOrCmdWord::CmdWord(word_subst(Arith(Some(a_times_b)))),
+            ],
+        }))
+    );
+}
+
+fn parse_arith(src: &str) -> Arithmetic {
+    let word = make_parser(&format!("$(( {} ))", src)).parameter().unwrap();
+    match word {
+        TopLevelWord(Single(Word::Simple(SimpleWord::Subst(subst)))) => match *subst {
+            Arith(Some(arith)) => arith,
+            other => panic!("unexpected substitution: {:?}", other),
+        },
+        other => panic!("unexpected word: {:?}", other),
+    }
+}
+
+#[test]
+fn test_arithmetic_display_adds_parens_only_where_needed() {
+    fn var(s: &str) -> Box<Arithmetic> {
+        Box::new(Var(String::from(s)))
+    }
+
+    let cases = vec![
+        (
+            Add(var("a"), Box::new(Mult(var("b"), var("c")))),
+            "a + b * c",
+        ),
+        (
+            Mult(Box::new(Add(var("a"), var("b"))), var("c")),
+            "(a + b) * c",
+        ),
+        (
+            Sub(var("a"), Box::new(Sub(var("b"), var("c")))),
+            "a - (b - c)",
+        ),
+        (
+            Sub(Box::new(Sub(var("a"), var("b"))), var("c")),
+            "a - b - c",
+        ),
+        (
+            Pow(var("a"), Box::new(Pow(var("b"), var("c")))),
+            "a ** b ** c",
+        ),
+        (
+            Pow(Box::new(Pow(var("a"), var("b"))), var("c")),
+            "(a ** b) ** c",
+        ),
+        (UnaryMinus(Box::new(UnaryMinus(var("a")))), "- -a"),
+        (UnaryMinus(Box::new(Pow(var("a"), var("b")))), "-(a ** b)"),
+        (
+            Assign(
+                String::from("x"),
+                Box::new(Sequence(vec![*var("a"), *var("b")])),
+            ),
+            "x = (a, b)",
+        ),
+    ];
+
+    for (expr, expected) in cases {
+        assert_eq!(expected, expr.to_string());
+        assert_eq!(expr, parse_arith(&expr.to_string()));
+    }
+}
+
+#[test]
+fn test_arithmetic_display_round_trips_through_parser() {
+    let cases = [
+        "x = y += 2 ? a || b && c : d | e ^ f & g",
+        "a == b != c < d <= e > f >= g << 1 >> 2",
+        "(a, b), c ? (d, e) : f",
+        "x++ + ++y - z-- - --w",
+        "!a + ~b * -(c + +d) % 2 ** -e",
+        "(a ? b : c) ? d : e",
+        "a - -1",
+    ];
+
+    for src in &cases {
+        let expr = parse_arith(src);
+        assert_eq!(expr, parse_arith(&expr.to_string()), "source: {}", src);
+    }
+}
+
+#[test]
+fn test_arithmetic_simplify_folds_literal_only_subexpressions() {
+    assert_eq!(Literal(9), parse_arith("(1+2)*3").simplify());
+    assert_eq!(Literal(1), parse_arith("2 ** 3 == 8 && !0").simplify());
+    assert_eq!(Literal(4), parse_arith("0 ? 1 : 1 << 2").simplify());
+    assert_eq!(parse_arith("a + 3"), parse_arith("a + (1 + 2)").simplify());
+    assert_eq!(parse_arith("x = 6"), parse_arith("x = 2 * 3").simplify());
+}
+
+#[test]
+fn test_arithmetic_simplify_leaves_variables_and_invalid_operations_untouched() {
+    let cases = [
+        "a + 1 + 2",
+        "x * 0",
+        "a ? 1 : 2",
+        "x++ - 1",
+        "1, x",
+        "1 / 0",
+        "5 % 0",
+        "1 << 64",
+        "2 ** -1",
+        "-9223372036854775807 - 1",
+    ];
+
+    for src in &cases {
+        let expr = parse_arith(src);
+        assert_eq!(expr, expr.clone().simplify(), "source: {}", src);
+    }
+}
+
+#[test]
+fn test_arithmetic_simplify_represents_negative_results_like_the_parser() {
+    assert_eq!(parse_arith("-4"), parse_arith("1 - 5").simplify());
+    assert_eq!(parse_arith("x * -4"), parse_arith("x * (1 - 5)").simplify());
+    assert_eq!(Literal(4), parse_arith("-(1 - 5)").simplify());
+    assert_eq!(parse_arith("-2"), parse_arith("(1 - 5) / 2").simplify());
+}
+
+#[test]
+fn test_arithmetic_simplify_round_trips_through_display_and_parser() {
+    let cases = [
+        "1 - 5",
+        "x * (1 - 5)",
+        "(1 - 5) ? x : y",
+        "0 ? 1 : 2 - 3",
+        "1, 2 - 9",
+        "y = ~0",
+        "x - (3 - 10) ** 2",
+        "2 ** -1",
+        "-9223372036854775807 - 1",
+        "(-9223372036854775807 - 1) * x",
+    ];
+
+    for src in &cases {
+        let simplified = parse_arith(src).simplify();
+        assert_eq!(
+            simplified,
+            parse_arith(&simplified.to_string()),
+            "source: {}",
+            src
+        );
+    }
+}
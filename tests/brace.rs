@@ -1,8 +1,14 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
-use conch_parser::parse::ParseError::*;
+use conch_parser::ast::CompoundCommandKind::*;
+use conch_parser::ast::PipeableCommand::*;
+use conch_parser::ast::*;
+use conch_parser::parse::ParseError::{self, *};
+use conch_parser::parse::ParserConfig;
 use conch_parser::token::Token;
 
+use std::rc::Rc;
+
 mod parse_support;
 use crate::parse_support::*;
 
@@ -27,6 +33,49 @@ fn test_brace_group_invalid_missing_separator() {
     );
 }
 
+#[test]
+fn test_brace_group_closing_brace_without_separator_is_an_argument() {
+    let correct = CommandGroup {
+        commands: vec![cmd_args("echo", &["}"])],
+        trailing_comments: vec![],
+    };
+    assert_eq!(correct, make_parser("{ echo }; }").brace_group().unwrap());
+
+    let correct = CommandGroup {
+        commands: vec![cmd_args("echo", &["}", "}"])],
+        trailing_comments: vec![],
+    };
+    assert_eq!(
+        correct,
+        make_parser(
+            "{ echo } }
+}"
+        )
+        .brace_group()
+        .unwrap()
+    );
+
+    // Neither `}` follows a separator, so both are arguments to `echo`
+    assert_eq!(
+        Err(Unmatched(Token::CurlyOpen, src(0, 1, 1))),
+        make_parser("{ echo } }").brace_group()
+    );
+
+    let correct = FunctionDef(
+        String::from("f"),
+        Rc::new(CompoundCommand {
+            kind: Brace(vec![cmd_args("echo", &["}"])]),
+            io: vec![],
+        }),
+    );
+    assert_eq!(
+        correct,
+        make_parser("f() { echo }; }")
+            .function_declaration()
+            .unwrap()
+    );
+}
+
 #[test]
 fn test_brace_group_invalid_start_must_be_whitespace_delimited() {
     let mut p = make_parser("{foo\nbar; baz; }");
@@ -113,3 +162,57 @@ fn test_brace_group_invalid_missing_body() {
         make_parser("{\n}").brace_group()
     );
 }
+
+fn parse_all(
+    src: &str,
+    strict_close_brace: bool,
+) -> Result<Vec<TopLevelCommand<String>>, ParseError<void::Void>> {
+    let cfg = ParserConfig {
+        strict_close_brace,
+        ..ParserConfig::default()
+    };
+    make_parser_with_config(src, cfg).into_iter().collect()
+}
+
+#[test]
+fn test_brace_group_stray_close_brace_is_a_word_by_default() {
+    assert_eq!(Ok(vec![cmd("}")]), parse_all("}", false));
+    assert_eq!(
+        Ok(vec![cmd("foo"), cmd_args("}", &["bar"])]),
+        parse_all("foo; } bar", false)
+    );
+}
+
+#[test]
+fn test_brace_group_stray_close_brace_is_an_error_when_strict() {
+    let cases = vec![
+        ("}", src(0, 1, 1)),
+        ("foo; }", src(5, 1, 6)),
+        ("foo\n  } bar", src(6, 2, 3)),
+        ("foo | }", src(6, 1, 7)),
+        ("{ foo; }; }", src(10, 1, 11)),
+        ("if true; then }; fi", src(14, 1, 15)),
+    ];
+
+    for (s, pos) in cases {
+        assert_eq!(
+            Err(Unexpected(Token::CurlyClose, pos)),
+            parse_all(s, true),
+            "source: {}",
+            s
+        );
+    }
+
+    // Closing braces and braces which are not the first word are still allowed
+    let correct = vec![
+        TopLevelCommand(Command::List(CommandList {
+            first: ListableCommand::Single(Compound(Box::new(CompoundCommand {
+                kind: Brace(vec![cmd_args("echo", &["}"])]),
+                io: vec![],
+            }))),
+            rest: vec![],
+        })),
+        cmd_args("echo", &["}"]),
+    ];
+    assert_eq!(Ok(correct), parse_all("{ echo }; }\necho }", true));
+}
@@ -1,7 +1,12 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
+use conch_parser::ast::{
+    CaseArmTerminator, ComplexWord, DefaultWord, Parameter, SimpleWord, TopLevelCommand,
+    TopLevelWord, Word,
+};
 use conch_parser::parse::ParseError::*;
-use conch_parser::token::Token;
+use conch_parser::parse::{Expectation, ParserConfig};
+use conch_parser::token::{Token, TokenKind};
 
 mod parse_support;
 use crate::parse_support::*;
@@ -23,6 +28,7 @@ fn test_case_command_valid() {
                     commands: vec![cmd_args("echo", &["greeting"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseArmTerminator::Break,
                 arm_comment: None,
             },
             CaseArm {
@@ -35,6 +41,7 @@ fn test_case_command_valid() {
                     commands: vec![cmd_args("echo", &["noun"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseArmTerminator::Break,
                 arm_comment: None,
             },
         ],
@@ -84,6 +91,7 @@ fn test_case_command_valid_with_comments() {
                         Newline(Some(String::from("#post_body_a"))),
                     ],
                 },
+                terminator: CaseArmTerminator::Break,
                 arm_comment: Some(Newline(Some(String::from("#arm_a")))),
             },
             CaseArm {
@@ -99,6 +107,7 @@ fn test_case_command_valid_with_comments() {
                     commands: vec![cmd_args("echo", &["noun"])],
                     trailing_comments: vec![],
                 },
+                terminator: CaseArmTerminator::Break,
                 arm_comment: Some(Newline(Some(String::from("#arm_b")))),
             },
         ],
@@ -182,6 +191,55 @@ fn test_case_command_valid_branch_with_no_command() {
     p.case_command().unwrap();
 }
 
+#[test]
+fn test_case_command_valid_esac_without_preceding_whitespace() {
+    let bodies = |src: &str| -> Vec<Vec<_>> {
+        make_parser(src)
+            .case_command()
+            .unwrap()
+            .arms
+            .into_iter()
+            .map(|arm| arm.body.commands)
+            .collect()
+    };
+
+    assert_eq!(vec![vec![cmd("foo")]], bodies("case x in a) foo;;esac"));
+    assert_eq!(vec![vec![cmd("foo")]], bodies("case x in a) foo;esac"));
+    let empty: Vec<TopLevelCommand<String>> = vec![];
+    assert_eq!(vec![empty], bodies("case x in a)esac"));
+    assert_eq!(
+        vec![vec![cmd("foo")], vec![cmd("bar")]],
+        bodies("case x in a) foo;;b) bar;esac")
+    );
+}
+
+#[test]
+fn test_case_command_nested_case_consumes_its_own_dsemi() {
+    let inner = make_parser("case y in b) foo;; esac")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+
+    let arms = make_parser("case x in a) case y in b) foo;; esac;; c) bar;; esac")
+        .case_command()
+        .unwrap()
+        .arms;
+
+    assert_eq!(2, arms.len());
+    assert_eq!(vec![word("a")], arms[0].patterns.pattern_alternatives);
+    assert_eq!(vec![inner.clone()], arms[0].body.commands);
+    assert_eq!(vec![word("c")], arms[1].patterns.pattern_alternatives);
+    assert_eq!(vec![cmd("bar")], arms[1].body.commands);
+
+    let arms = make_parser("case x in a) case y in b) foo;; esac;; esac")
+        .case_command()
+        .unwrap()
+        .arms;
+
+    assert_eq!(1, arms.len());
+    assert_eq!(vec![inner], arms[0].body.commands);
+}
+
 #[test]
 fn test_case_command_invalid_missing_keyword() {
     let mut p = make_parser("foo in foo) echo foo;; bar) echo bar;; esac");
@@ -201,6 +259,22 @@ fn test_case_command_invalid_missing_keyword() {
     );
 }
 
+#[test]
+fn test_case_command_invalid_truncated_pattern_reports_expected_tokens() {
+    let mut p = make_parser("case foo in bar\necho bar;; esac");
+    assert_eq!(
+        Err(Expected(
+            vec![
+                Expectation::Token(TokenKind::Pipe),
+                Expectation::Token(TokenKind::ParenClose)
+            ],
+            Some(Token::Newline),
+            src(15, 1, 16)
+        )),
+        p.case_command()
+    );
+}
+
 #[test]
 fn test_case_command_invalid_missing_word() {
     let mut p = make_parser("case in foo) echo foo;; bar) echo bar;; esac");
@@ -223,7 +297,14 @@ fn test_case_command_invalid_quoted() {
         ),
         (
             "case foo in foo) echo foo;; bar')' echo bar;; esac",
-            Unexpected(Token::Name(String::from("echo")), src(35, 1, 36)),
+            Expected(
+                vec![
+                    Expectation::Token(TokenKind::Pipe),
+                    Expectation::Token(TokenKind::ParenClose),
+                ],
+                Some(Token::Name(String::from("echo"))),
+                src(35, 1, 36),
+            ),
         ),
         (
             "case foo in foo) echo foo;; bar) echo bar;; 'esac'",
@@ -239,7 +320,14 @@ fn test_case_command_invalid_quoted() {
         ),
         (
             "case foo in foo) echo foo;; bar\")\" echo bar;; esac",
-            Unexpected(Token::Name(String::from("echo")), src(35, 1, 36)),
+            Expected(
+                vec![
+                    Expectation::Token(TokenKind::Pipe),
+                    Expectation::Token(TokenKind::ParenClose),
+                ],
+                Some(Token::Name(String::from("echo"))),
+                src(35, 1, 36),
+            ),
         ),
         (
             "case foo in foo) echo foo;; bar) echo bar;; \"esac\"",
@@ -352,9 +440,9 @@ fn test_case_command_should_recognize_literals_and_names() {
     let case_str = String::from("case");
     let in_str = String::from("in");
     let esac_str = String::from("esac");
-    for case_tok in vec![Token::Literal(case_str.clone()), Token::Name(case_str)] {
-        for in_tok in vec![Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
-            for esac_tok in vec![
+    for case_tok in [Token::Literal(case_str.clone()), Token::Name(case_str)] {
+        for in_tok in [Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
+            for esac_tok in [
                 Token::Literal(esac_str.clone()),
                 Token::Name(esac_str.clone()),
             ] {
@@ -382,3 +470,183 @@ fn test_case_command_should_recognize_literals_and_names() {
         }
     }
 }
+
+fn case_patterns(src: &str) -> Vec<Vec<TopLevelWord<String>>> {
+    make_parser(src)
+        .case_command()
+        .unwrap()
+        .arms
+        .into_iter()
+        .map(|arm| arm.patterns.pattern_alternatives)
+        .collect()
+}
+
+fn pattern(fragments: Vec<DefaultWord>) -> TopLevelWord<String> {
+    TopLevelWord(ComplexWord::Concat(fragments))
+}
+
+#[test]
+fn test_case_command_valid_bracket_expression_patterns() {
+    let correct = vec![
+        vec![TopLevelWord(ComplexWord::Single(Word::BracketClass(
+            false,
+            String::from("[:digit:]"),
+        )))],
+        vec![pattern(vec![
+            Word::BracketClass(false, String::from("a[=e=][.-.]")),
+            Word::Simple(SimpleWord::Star),
+        ])],
+        // Not a character class since the brackets aren't nested
+        vec![TopLevelWord(ComplexWord::Single(Word::BracketClass(
+            false,
+            String::from(":x:"),
+        )))],
+        vec![TopLevelWord(ComplexWord::Single(Word::BracketClass(
+            true,
+            String::from("[:space:]"),
+        )))],
+    ];
+
+    assert_eq!(
+        correct,
+        case_patterns(
+            "case x in [[:digit:]]) ;; [a[=e=][.-.]]*) ;; [:x:]) ;; [![:space:]]) ;; esac"
+        )
+    );
+}
+
+#[test]
+fn test_case_command_valid_extglob_patterns() {
+    let correct = vec![
+        vec![word("@(a|b)")],
+        vec![word("foo+(x)"), word("!(y|z)bar")],
+        vec![pattern(vec![
+            lit("*(a|?(b|"),
+            Word::Simple(SimpleWord::Param(Parameter::Var(String::from("c")))),
+            lit("))"),
+            Word::Simple(SimpleWord::Star),
+        ])],
+    ];
+
+    assert_eq!(
+        correct,
+        case_patterns("case x in @(a|b)) ;; (foo+(x) | !(y|z)bar) ;; *(a|?(b|$c))*) ;; esac")
+    );
+}
+
+#[test]
+fn test_case_command_extglob_pipes_do_not_split_alternatives() {
+    let correct = vec![
+        vec![word("@(a|b)"), word("c")],
+        vec![word("a"), word("!(b|c|d)"), word("e")],
+        vec![word("+(x|@(y|z))")],
+    ];
+
+    assert_eq!(
+        correct,
+        case_patterns("case x in @(a|b)|c) ;; a | !(b|c|d) | e) ;; +(x|@(y|z))) ;; esac")
+    );
+}
+
+#[test]
+fn test_case_command_invalid_extglob_patterns() {
+    assert_eq!(
+        Err(Unmatched(Token::ParenOpen, src(11, 1, 12))),
+        make_parser("case x in @(a|b").case_command()
+    );
+    assert_eq!(
+        Err(Unexpected(
+            Token::Whitespace(String::from(" ")),
+            src(13, 1, 14)
+        )),
+        make_parser("case x in @(a b)) ;; esac").case_command()
+    );
+}
+
+#[test]
+fn test_case_command_escaped_pattern_metacharacters_are_distinct() {
+    let correct = vec![
+        vec![TopLevelWord(ComplexWord::Single(escaped("*")))],
+        vec![TopLevelWord(ComplexWord::Single(Word::Simple(
+            SimpleWord::Star,
+        )))],
+        vec![pattern(vec![
+            escaped("["),
+            lit("a"),
+            escaped("?"),
+            Word::Simple(SimpleWord::SquareClose),
+        ])],
+        vec![pattern(vec![
+            lit("@"),
+            escaped("("),
+            lit("x"),
+            escaped(")"),
+        ])],
+    ];
+
+    assert_eq!(
+        correct,
+        case_patterns("case x in \\*) ;; *) ;; \\[a\\?]) ;; @\\(x\\)) ;; esac")
+    );
+}
+
+#[test]
+fn test_case_command_invalid_bash_fallthrough_terminators() {
+    // Rejected unless explicitly enabled, as POSIX requires
+    assert_eq!(
+        Err(Unexpected(Token::Amp, src(17, 1, 18))),
+        make_parser("case x in a) foo;& esac").case_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Amp, src(18, 1, 19))),
+        make_parser("case x in a) foo;;& esac").case_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Amp, src(17, 1, 18))),
+        make_parser("case x in a) foo;& b) bar;; esac").case_command()
+    );
+}
+
+#[test]
+fn test_case_command_valid_bash_fallthrough_terminators_if_enabled() {
+    let cfg = ParserConfig {
+        case_fallthrough: true,
+        ..ParserConfig::default()
+    };
+    let parser = |src: &'static str| make_parser_with_config(src, cfg.clone());
+    let terminators = |src| {
+        parser(src)
+            .case_command()
+            .unwrap()
+            .arms
+            .into_iter()
+            .map(|arm| arm.terminator)
+            .collect::<Vec<_>>()
+    };
+
+    assert_eq!(
+        vec![CaseArmTerminator::FallThrough],
+        terminators("case x in a) foo;& esac")
+    );
+    assert_eq!(
+        vec![CaseArmTerminator::Continue],
+        terminators("case x in a) foo;;& esac")
+    );
+    assert_eq!(
+        vec![
+            CaseArmTerminator::FallThrough,
+            CaseArmTerminator::Continue,
+            CaseArmTerminator::Break,
+            CaseArmTerminator::Break,
+        ],
+        terminators("case x in a) foo;& b) ;;&\nc) bar;; d) baz\nesac")
+    );
+
+    // Still only valid as the terminator of a case arm
+    let mut p = parser("foo;& bar");
+    assert_eq!(Some(cmd("foo")), p.complete_command().unwrap());
+    assert_eq!(
+        Err(Unexpected(Token::SemiAmp, src(3, 1, 4))),
+        p.complete_command()
+    );
+}
@@ -1,10 +1,13 @@
 #![deny(rust_2018_idioms)]
 use std::rc::Rc;
 
+use conch_parser::ast::builder::*;
 use conch_parser::ast::Command::*;
 use conch_parser::ast::CompoundCommandKind::*;
 use conch_parser::ast::PipeableCommand::*;
 use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::Parser;
 use conch_parser::token::Token;
 
 mod parse_support;
@@ -34,6 +37,20 @@ fn test_complete_command_job() {
     assert_eq!(correct2, cmd2);
 }
 
+#[test]
+fn test_complete_command_job_at_end_of_input() {
+    let correct = TopLevelCommand(Job(CommandList {
+        first: ListableCommand::Single(Simple(cmd_simple("foo"))),
+        rest: vec![],
+    }));
+
+    for src in &["foo &", "foo&", "foo &\n", "foo & # comment"] {
+        let mut p = make_parser(src);
+        assert_eq!(Some(correct.clone()), p.complete_command().unwrap());
+        assert_eq!(None, p.complete_command().unwrap());
+    }
+}
+
 #[test]
 fn test_complete_command_non_eager_parse() {
     let mut p = make_parser("foo && bar; baz\n\nqux");
@@ -255,7 +272,7 @@ fn test_command_parses_quoted_compound_commands_as_simple_commands() {
 
 #[test]
 fn test_command_should_delegate_literals_and_names_loop_while() {
-    for kw in vec![
+    for kw in [
         Token::Literal(String::from("while")),
         Token::Name(String::from("while")),
     ] {
@@ -284,7 +301,7 @@ fn test_command_should_delegate_literals_and_names_loop_while() {
 
 #[test]
 fn test_command_should_delegate_literals_and_names_loop_until() {
-    for kw in vec![
+    for kw in [
         Token::Literal(String::from("until")),
         Token::Name(String::from("until")),
     ] {
@@ -313,23 +330,23 @@ fn test_command_should_delegate_literals_and_names_loop_until() {
 
 #[test]
 fn test_command_should_delegate_literals_and_names_if() {
-    for if_tok in vec![
+    for if_tok in [
         Token::Literal(String::from("if")),
         Token::Name(String::from("if")),
     ] {
-        for then_tok in vec![
+        for then_tok in [
             Token::Literal(String::from("then")),
             Token::Name(String::from("then")),
         ] {
-            for elif_tok in vec![
+            for elif_tok in [
                 Token::Literal(String::from("elif")),
                 Token::Name(String::from("elif")),
             ] {
-                for else_tok in vec![
+                for else_tok in [
                     Token::Literal(String::from("else")),
                     Token::Name(String::from("else")),
                 ] {
-                    for fi_tok in vec![
+                    for fi_tok in [
                         Token::Literal(String::from("fi")),
                         Token::Name(String::from("fi")),
                     ] {
@@ -373,11 +390,11 @@ fn test_command_should_delegate_literals_and_names_if() {
 
 #[test]
 fn test_command_should_delegate_literals_and_names_for() {
-    for for_tok in vec![
+    for for_tok in [
         Token::Literal(String::from("for")),
         Token::Name(String::from("for")),
     ] {
-        for in_tok in vec![
+        for in_tok in [
             Token::Literal(String::from("in")),
             Token::Name(String::from("in")),
         ] {
@@ -422,9 +439,9 @@ fn test_command_should_delegate_literals_and_names_case() {
     let case_str = String::from("case");
     let in_str = String::from("in");
     let esac_str = String::from("esac");
-    for case_tok in vec![Token::Literal(case_str.clone()), Token::Name(case_str)] {
-        for in_tok in vec![Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
-            for esac_tok in vec![
+    for case_tok in [Token::Literal(case_str.clone()), Token::Name(case_str)] {
+        for in_tok in [Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
+            for esac_tok in [
                 Token::Literal(esac_str.clone()),
                 Token::Name(esac_str.clone()),
             ] {
@@ -463,7 +480,7 @@ fn test_command_should_delegate_literals_and_names_case() {
 
 #[test]
 fn test_command_should_delegate_literals_and_names_for_function_declaration() {
-    for fn_tok in vec![
+    for fn_tok in [
         Token::Literal(String::from("function")),
         Token::Name(String::from("function")),
     ] {
@@ -535,3 +552,118 @@ fn test_command_delegate_functions_only_if_fn_name_is_a_name_token() {
         Err(err) => panic!("Failed to parse command: {}", err),
     }
 }
+
+/// Records the comments and separator of each complete command it builds,
+/// as well as the comments following each segment of a pipeline.
+#[derive(Default)]
+struct CommentRecorder {
+    commands: Vec<(Vec<Newline>, SeparatorKind, Option<Newline>)>,
+    pipelines: Vec<Vec<Vec<Newline>>>,
+    trailing: Vec<Newline>,
+}
+
+impl BuilderHooks for CommentRecorder {
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: &[Newline],
+        separator: SeparatorKind,
+        cmd_comment: Option<&Newline>,
+    ) -> Result<(), String> {
+        self.commands
+            .push((pre_cmd_comments.to_vec(), separator, cmd_comment.cloned()));
+        Ok(())
+    }
+
+    fn pipeline(
+        &mut self,
+        _bang: bool,
+        cmds: &[(Vec<Newline>, DefaultPipeableCommand)],
+    ) -> Result<(), String> {
+        self.pipelines
+            .push(cmds.iter().map(|(comments, _)| comments.clone()).collect());
+        Ok(())
+    }
+
+    fn comments(&mut self, comments: &[Newline]) -> Result<(), String> {
+        self.trailing.extend(comments.iter().cloned());
+        Ok(())
+    }
+}
+
+#[test]
+fn test_complete_command_attributes_comments_across_blank_lines() {
+    let source = "\n\n# leading 1\n\n# leading 2\nfoo # foo comment\n\n\n\
+                  # before bar\nbar; baz # baz comment\n# trailing\n\n";
+
+    let mut builder = HookedBuilder::<CommentRecorder>::default();
+    let cmds = Parser::with_builder(Lexer::new(source.chars()), &mut builder)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(vec![cmd("foo"), cmd("bar"), cmd("baz")], cmds);
+
+    let blank = || Newline(None);
+    let comment = |s: &str| Newline(Some(String::from(s)));
+
+    assert_eq!(
+        builder.hooks.commands,
+        vec![
+            (
+                vec![
+                    blank(),
+                    blank(),
+                    comment("# leading 1"),
+                    blank(),
+                    comment("# leading 2"),
+                ],
+                SeparatorKind::Newline,
+                Some(comment("# foo comment")),
+            ),
+            (
+                vec![blank(), blank(), comment("# before bar")],
+                SeparatorKind::Semi,
+                None,
+            ),
+            (
+                vec![],
+                SeparatorKind::Newline,
+                Some(comment("# baz comment")),
+            ),
+        ]
+    );
+    assert_eq!(builder.hooks.trailing, vec![comment("# trailing"), blank()]);
+}
+
+#[test]
+fn test_pipeline_passes_comments_between_segments_to_builder() {
+    let source = "foo | # comment\n\n# another\nbar | baz";
+
+    let mut builder = HookedBuilder::<CommentRecorder>::default();
+    let cmds = Parser::with_builder(Lexer::new(source.chars()), &mut builder)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let correct = TopLevelCommand(List(CommandList {
+        first: ListableCommand::Pipe(
+            false,
+            vec![
+                Simple(cmd_simple("foo")),
+                Simple(cmd_simple("bar")),
+                Simple(cmd_simple("baz")),
+            ],
+        ),
+        rest: vec![],
+    }));
+    assert_eq!(vec![correct], cmds);
+
+    let comment = |s: &str| Newline(Some(String::from(s)));
+    assert_eq!(
+        builder.hooks.pipelines,
+        vec![vec![
+            vec![comment("# comment"), Newline(None), comment("# another")],
+            vec![],
+            vec![],
+        ]]
+    );
+}
@@ -2,6 +2,7 @@
 use conch_parser::ast::builder::*;
 use conch_parser::ast::CompoundCommandKind::*;
 use conch_parser::ast::*;
+use conch_parser::parse::Expectation::ReservedWord;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -41,7 +42,11 @@ fn test_do_group_valid_keyword_delimited_by_separator() {
 fn test_do_group_invalid_missing_keyword() {
     let mut p = make_parser("foo\nbar; baz; done");
     assert_eq!(
-        Err(Unexpected(Token::Name(String::from("foo")), src(0, 1, 1))),
+        Err(Expected(
+            vec![ReservedWord("do")],
+            Some(Token::Name(String::from("foo"))),
+            src(0, 1, 1)
+        )),
         p.do_group()
     );
     let mut p = make_parser("do foo\nbar; baz");
@@ -56,7 +61,11 @@ fn test_do_group_invalid_quoted() {
     let cmds = [
         (
             "'do' foo\nbar; baz; done",
-            Unexpected(Token::SingleQuote, src(0, 1, 1)),
+            Expected(
+                vec![ReservedWord("do")],
+                Some(Token::SingleQuote),
+                src(0, 1, 1),
+            ),
         ),
         (
             "do foo\nbar; baz; 'done'",
@@ -64,7 +73,11 @@ fn test_do_group_invalid_quoted() {
         ),
         (
             "\"do\" foo\nbar; baz; done",
-            Unexpected(Token::DoubleQuote, src(0, 1, 1)),
+            Expected(
+                vec![ReservedWord("do")],
+                Some(Token::DoubleQuote),
+                src(0, 1, 1),
+            ),
         ),
         (
             "do foo\nbar; baz; \"done\"",
@@ -98,7 +111,11 @@ fn test_do_group_invalid_concat() {
         Token::Literal(String::from("done")),
     ]);
     assert_eq!(
-        Err(Unexpected(Token::Literal(String::from("d")), src(0, 1, 1))),
+        Err(Expected(
+            vec![ReservedWord("do")],
+            Some(Token::Literal(String::from("d"))),
+            src(0, 1, 1)
+        )),
         p.do_group()
     );
     let mut p = make_parser_from_tokens(vec![
@@ -117,11 +134,11 @@ fn test_do_group_invalid_concat() {
 
 #[test]
 fn test_do_group_should_recognize_literals_and_names() {
-    for do_tok in vec![
+    for do_tok in [
         Token::Literal(String::from("do")),
         Token::Name(String::from("do")),
     ] {
-        for done_tok in vec![
+        for done_tok in [
             Token::Literal(String::from("done")),
             Token::Name(String::from("done")),
         ] {
@@ -336,6 +353,68 @@ fn test_compound_command_errors_on_quoted_commands() {
     }
 }
 
+#[test]
+fn test_compound_command_quoted_terminators_are_regular_words() {
+    let echo_done = |done| {
+        TopLevelCommand(Command::List(CommandList {
+            first: ListableCommand::Single(PipeableCommand::Simple(Box::new(SimpleCommand {
+                redirects_or_env_vars: vec![],
+                redirects_or_cmd_words: vec![
+                    RedirectOrCmdWord::CmdWord(word("echo")),
+                    RedirectOrCmdWord::CmdWord(done),
+                ],
+            }))),
+            rest: vec![],
+        }))
+    };
+
+    let cases = vec![
+        ("while x; do echo 'done'; done", single_quoted("done")),
+        ("while x; do echo \"done\"; done", double_quoted("done")),
+        (
+            "while x; do echo \\done; done",
+            TopLevelWord(ComplexWord::Concat(vec![escaped("d"), lit("one")])),
+        ),
+    ];
+
+    for (src, done) in cases {
+        let correct = CompoundCommand {
+            kind: While(GuardBodyPair {
+                guard: vec![cmd("x")],
+                body: vec![echo_done(done)],
+            }),
+            io: vec![],
+        };
+        assert_eq!(correct, make_parser(src).compound_command().unwrap());
+    }
+
+    let templates = [
+        "while x; do echo {kw}; {kw} arg; done",
+        "until x; do echo {kw}; {kw} arg; done",
+        "for x in {kw}; do echo {kw}; {kw} arg; done",
+        "if {kw}; then echo {kw}; elif {kw}; then {kw}; else {kw} arg; fi",
+        "case {kw} in {kw}) echo {kw}; {kw} arg;; esac",
+        "{ echo {kw}; {kw} arg; }",
+    ];
+    let keywords = [
+        "do", "done", "then", "elif", "else", "fi", "in", "esac", "{", "}",
+    ];
+
+    for template in &templates {
+        for kw in &keywords {
+            for quoted in &[format!("'{}'", kw), format!("\"{}\"", kw)] {
+                let src = template.replace("{kw}", quoted);
+                let mut p = make_parser(&src);
+                match p.complete_command() {
+                    Ok(Some(_)) => {}
+                    result => panic!("Failed to parse \"{}\": {:?}", src, result),
+                }
+                assert_eq!(None, p.complete_command().unwrap(), "{}", src);
+            }
+        }
+    }
+}
+
 #[test]
 fn test_compound_command_captures_redirections_after_command() {
     let cases = [
@@ -366,7 +445,7 @@ fn test_compound_command_captures_redirections_after_command() {
 
 #[test]
 fn test_compound_command_should_delegate_literals_and_names_loop() {
-    for kw in vec![
+    for kw in [
         Token::Literal(String::from("while")),
         Token::Name(String::from("while")),
         Token::Literal(String::from("until")),
@@ -389,23 +468,23 @@ fn test_compound_command_should_delegate_literals_and_names_loop() {
 
 #[test]
 fn test_compound_command_should_delegate_literals_and_names_if() {
-    for if_tok in vec![
+    for if_tok in [
         Token::Literal(String::from("if")),
         Token::Name(String::from("if")),
     ] {
-        for then_tok in vec![
+        for then_tok in [
             Token::Literal(String::from("then")),
             Token::Name(String::from("then")),
         ] {
-            for elif_tok in vec![
+            for elif_tok in [
                 Token::Literal(String::from("elif")),
                 Token::Name(String::from("elif")),
             ] {
-                for else_tok in vec![
+                for else_tok in [
                     Token::Literal(String::from("else")),
                     Token::Name(String::from("else")),
                 ] {
-                    for fi_tok in vec![
+                    for fi_tok in [
                         Token::Literal(String::from("fi")),
                         Token::Name(String::from("fi")),
                     ] {
@@ -441,11 +520,11 @@ fn test_compound_command_should_delegate_literals_and_names_if() {
 
 #[test]
 fn test_compound_command_should_delegate_literals_and_names_for() {
-    for for_tok in vec![
+    for for_tok in [
         Token::Literal(String::from("for")),
         Token::Name(String::from("for")),
     ] {
-        for in_tok in vec![
+        for in_tok in [
             Token::Literal(String::from("in")),
             Token::Name(String::from("in")),
         ] {
@@ -482,9 +561,9 @@ fn test_compound_command_should_delegate_literals_and_names_case() {
     let case_str = String::from("case");
     let in_str = String::from("in");
     let esac_str = String::from("esac");
-    for case_tok in vec![Token::Literal(case_str.clone()), Token::Name(case_str)] {
-        for in_tok in vec![Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
-            for esac_tok in vec![
+    for case_tok in [Token::Literal(case_str.clone()), Token::Name(case_str)] {
+        for in_tok in [Token::Literal(in_str.clone()), Token::Name(in_str.clone())] {
+            for esac_tok in [
                 Token::Literal(esac_str.clone()),
                 Token::Name(esac_str.clone()),
             ] {
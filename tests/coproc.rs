@@ -0,0 +1,97 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::CompoundCommandKind::*;
+use conch_parser::ast::PipeableCommand::*;
+use conch_parser::ast::*;
+use conch_parser::parse::*;
+
+mod parse_support;
+use crate::parse_support::*;
+
+fn coproc(name: Option<&str>, body: DefaultPipeableCommand) -> DefaultPipeableCommand {
+    Coproc {
+        name: name.map(String::from),
+        body: Box::new(body),
+    }
+}
+
+fn compound(kind: DefaultCompoundCommandKind) -> DefaultPipeableCommand {
+    Compound(Box::new(CompoundCommand { kind, io: vec![] }))
+}
+
+#[test]
+fn test_coproc_unnamed_compound() {
+    let correct = coproc(None, compound(Brace(vec![cmd_args("sleep", &["1"])])));
+    assert_eq!(
+        correct,
+        make_parser("coproc { sleep 1; }").command().unwrap()
+    );
+}
+
+#[test]
+fn test_coproc_named_compound() {
+    let correct = coproc(Some("mycop"), compound(Brace(vec![cmd("cat")])));
+    assert_eq!(
+        correct,
+        make_parser("coproc mycop { cat; }").command().unwrap()
+    );
+    assert_eq!(
+        correct,
+        make_parser("coproc   mycop   {   cat; }")
+            .command()
+            .unwrap()
+    );
+
+    let correct = coproc(Some("mycop"), compound(Subshell(vec![cmd("cat")])));
+    assert_eq!(
+        correct,
+        make_parser("coproc mycop (cat)").command().unwrap()
+    );
+}
+
+#[test]
+fn test_coproc_simple_command() {
+    let correct = coproc(None, Simple(cmd_simple("cat")));
+    assert_eq!(correct, make_parser("coproc cat").command().unwrap());
+
+    // A name is only recognized before a compound command
+    let correct = coproc(None, Simple(cmd_args_simple("foo", &["bar"])));
+    assert_eq!(correct, make_parser("coproc foo bar").command().unwrap());
+}
+
+#[test]
+fn test_coproc_as_part_of_pipeline_and_job() {
+    let correct = TopLevelCommand(Command::Job(CommandList {
+        first: ListableCommand::Single(coproc(None, Simple(cmd_simple("cat")))),
+        rest: vec![],
+    }));
+    assert_eq!(
+        Some(correct),
+        make_parser("coproc cat &").complete_command().unwrap()
+    );
+}
+
+#[test]
+fn test_coproc_not_delimited_is_simple_command() {
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar(
+            String::from("coproc"),
+            Some(word("1")),
+        )],
+        redirects_or_cmd_words: vec![],
+    }));
+    assert_eq!(correct, make_parser("coproc=1").command().unwrap());
+
+    let correct = Simple(cmd_simple("coprocs"));
+    assert_eq!(correct, make_parser("coprocs").command().unwrap());
+}
+
+#[test]
+fn test_coproc_without_reserved_word_is_simple_command() {
+    let mut cfg = ParserConfig::default();
+    cfg.reserved_words.retain(|&w| w != "coproc");
+
+    let mut p = make_parser_with_config("coproc cat", cfg);
+
+    let correct = Simple(cmd_args_simple("coproc", &["cat"]));
+    assert_eq!(correct, p.command().unwrap());
+}
@@ -0,0 +1,150 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::fold::{self, Fold};
+use conch_parser::ast::*;
+
+mod parse_support;
+use crate::parse_support::*;
+
+struct Rename;
+
+impl Fold<String> for Rename {
+    fn fold_parameter(&mut self, param: DefaultParameter) -> DefaultParameter {
+        match param {
+            Parameter::Var(ref name) if name == "old" => Parameter::Var(String::from("new")),
+            param => param,
+        }
+    }
+}
+
+fn parse_all(src: &str) -> Vec<TopLevelCommand<String>> {
+    make_parser(src)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap()
+}
+
+#[test]
+fn test_fold_renames_parameters_throughout_script() {
+    let src = r#"
+        echo $old "${old:-$old}" $other >"$old"
+        f() { for x in $old; do echo $(cat "$old"); done; }
+        case $old in old) echo old ;; esac
+        echo $(( old + 1 ))
+    "#;
+
+    let correct = parse_all(
+        r#"
+        echo $new "${new:-$new}" $other >"$new"
+        f() { for x in $new; do echo $(cat "$new"); done; }
+        case $new in old) echo old ;; esac
+        echo $(( new + 1 ))
+    "#,
+    );
+
+    let folded = parse_all(src)
+        .into_iter()
+        .map(|cmd| Rename.fold_command(cmd))
+        .collect::<Vec<_>>();
+    assert_eq!(correct, folded);
+}
+
+#[test]
+fn test_fold_renames_variable_names_throughout_script() {
+    let src = r#"
+        old=1 other=$old cmd
+        export old=2 other
+        declare old
+        for old in a b; do :; done
+        echo $(( old = old++ + --old ))
+        for (( old = 0; old < 3; old++ )); do :; done
+    "#;
+
+    let correct = parse_all(
+        r#"
+        new=1 other=$new cmd
+        export new=2 other
+        declare new
+        for new in a b; do :; done
+        echo $(( new = new++ + --new ))
+        for (( new = 0; new < 3; new++ )); do :; done
+    "#,
+    );
+
+    let folded = parse_all(src)
+        .into_iter()
+        .map(|cmd| Rename.fold_command(cmd))
+        .collect::<Vec<_>>();
+    assert_eq!(correct, folded);
+}
+
+#[test]
+fn test_fold_renames_function_definitions() {
+    struct RenameFn;
+
+    impl Fold<String> for RenameFn {
+        fn fold_function_def(
+            &mut self,
+            name: String,
+            body: DefaultCompoundCommand,
+        ) -> (String, DefaultCompoundCommand) {
+            let name = if name == "old" {
+                String::from("new")
+            } else {
+                name
+            };
+            fold::walk_function_def(self, name, body)
+        }
+    }
+
+    let cmd = make_parser("old() { old() { :; }; }")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    let correct = make_parser("new() { new() { :; }; }")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(correct, RenameFn.fold_command(cmd));
+}
+
+#[test]
+fn test_fold_overrides_can_rebuild_nodes() {
+    struct DropRedirects;
+
+    impl Fold<String> for DropRedirects {
+        fn fold_simple_command(&mut self, cmd: DefaultSimpleCommand) -> DefaultSimpleCommand {
+            let mut cmd = fold::walk_simple_command(self, cmd);
+            cmd.redirects_or_cmd_words
+                .retain(|w| !matches!(*w, RedirectOrCmdWord::Redirect(_)));
+            cmd
+        }
+    }
+
+    let cmd = make_parser("foo >out | bar 2>&1")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    let correct = make_parser("foo | bar")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    assert_eq!(correct, DropRedirects.fold_command(cmd));
+}
+
+#[test]
+fn test_atomic_fold_rewrites_arithmetic() {
+    struct Double;
+
+    impl fold::atomic::Fold<String> for Double {
+        fn fold_arithmetic(&mut self, expr: DefaultArithmetic) -> DefaultArithmetic {
+            match fold::atomic::walk_arithmetic(self, expr) {
+                Arithmetic::Literal(n) => Arithmetic::Literal(n * 2),
+                expr => expr,
+            }
+        }
+    }
+
+    let cmd: AtomicTopLevelCommand<String> = "echo $(( 1 + x * 3 ))".parse().unwrap();
+    let correct: AtomicTopLevelCommand<String> = "echo $(( 2 + x * 6 ))".parse().unwrap();
+    assert_eq!(correct, fold::atomic::Fold::fold_command(&mut Double, cmd));
+}
@@ -1,7 +1,8 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
+use conch_parser::parse::Expectation;
 use conch_parser::parse::ParseError::*;
-use conch_parser::token::Token;
+use conch_parser::token::{Token, TokenKind};
 
 mod parse_support;
 use crate::parse_support::*;
@@ -110,6 +111,39 @@ fn test_for_command_valid_with_separator() {
     p.for_command().unwrap();
 }
 
+#[test]
+fn test_for_command_valid_with_substitution_words() {
+    use conch_parser::ast::ComplexWord::Single;
+    use conch_parser::ast::Parameter::At;
+    use conch_parser::ast::ParameterSubstitution::Command;
+    use conch_parser::ast::{SimpleWord, TopLevelWord, Word};
+
+    let correct_words = vec![
+        word_param(At),
+        word_subst(Command(vec![cmd("ls")])),
+        TopLevelWord(Single(Word::DoubleQuoted(vec![SimpleWord::Param(At)]))),
+    ];
+
+    for src in &[
+        "for x in $@ $(ls) \"$@\"; do echo $x; done",
+        "for x in $@ $(ls) \"$@\"\ndo echo $x; done",
+        "for x in $@ $(ls) \"$@\" #comment\ndo echo $x; done",
+    ] {
+        let fragments = make_parser(src).for_command().unwrap();
+        let (_, words, _) = fragments.words.expect("missing words");
+        assert_eq!(correct_words, words, "source: {:?}", src);
+    }
+}
+
+#[test]
+fn test_for_command_invalid_substitution_words_missing_separator() {
+    let mut p = make_parser("for x in $@ $(ls) do echo $x; done");
+    assert_eq!(
+        Err(IncompleteCmd("for", src(0, 1, 1), "do", src(30, 1, 31))),
+        p.for_command()
+    );
+}
+
 #[test]
 fn test_for_command_invalid_with_in_no_words_no_with_separator() {
     let mut p = make_parser("for var in do echo $var; done");
@@ -206,12 +240,20 @@ fn test_for_command_invalid_var_must_be_name() {
     );
     let mut p = make_parser("for 'var' in one two three\ndo echo $var; done");
     assert_eq!(
-        Err(Unexpected(Token::SingleQuote, src(4, 1, 5))),
+        Err(Expected(
+            vec![Expectation::Token(TokenKind::Name)],
+            Some(Token::SingleQuote),
+            src(4, 1, 5)
+        )),
         p.for_command()
     );
     let mut p = make_parser("for \"var\" in one two three\ndo echo $var; done");
     assert_eq!(
-        Err(Unexpected(Token::DoubleQuote, src(4, 1, 5))),
+        Err(Expected(
+            vec![Expectation::Token(TokenKind::Name)],
+            Some(Token::DoubleQuote),
+            src(4, 1, 5)
+        )),
         p.for_command()
     );
     let mut p = make_parser("for var*% in one two three\ndo echo $var; done");
@@ -282,11 +324,11 @@ fn test_for_command_invalid_concat() {
 
 #[test]
 fn test_for_command_should_recognize_literals_and_names() {
-    for for_tok in vec![
+    for for_tok in [
         Token::Literal(String::from("for")),
         Token::Name(String::from("for")),
     ] {
-        for in_tok in vec![
+        for in_tok in [
             Token::Literal(String::from("in")),
             Token::Name(String::from("in")),
         ] {
@@ -317,3 +359,107 @@ fn test_for_command_should_recognize_literals_and_names() {
         }
     }
 }
+
+#[test]
+fn test_arith_for_command_valid() {
+    use conch_parser::ast::Arithmetic::*;
+
+    let i = || String::from("i");
+    let correct = ArithForFragments {
+        init: Some(Assign(i(), Box::new(Literal(0)))),
+        cond: Some(Less(Box::new(Var(i())), Box::new(Literal(10)))),
+        update: Some(PostIncr(i())),
+        pre_body_comments: vec![],
+        body: CommandGroup {
+            commands: vec![cmd_args("echo", &["x"])],
+            trailing_comments: vec![],
+        },
+    };
+
+    let cases = [
+        "for ((i=0; i<10; i++)); do echo x; done",
+        "for (( i = 0 ; i < 10 ; i++ )) do echo x; done",
+    ];
+
+    for &src in &cases {
+        let mut p = make_parser(src);
+        assert_eq!(Ok(correct.clone()), p.arith_for_command(), "{}", src);
+    }
+
+    let correct = ArithForFragments {
+        pre_body_comments: vec![Newline(None)],
+        ..correct
+    };
+    let mut p = make_parser("for ((i=0;i<10;i++))\ndo echo x; done");
+    assert_eq!(Ok(correct), p.arith_for_command());
+}
+
+#[test]
+fn test_arith_for_command_valid_with_empty_sections() {
+    let correct = ArithForFragments {
+        init: None,
+        cond: None,
+        update: None,
+        pre_body_comments: vec![Newline(Some(String::from("#comment")))],
+        body: CommandGroup {
+            commands: vec![cmd("break")],
+            trailing_comments: vec![],
+        },
+    };
+
+    for &src in &[
+        "for (( ; ; )) #comment\ndo break; done",
+        "for ((;;)) #comment\ndo break; done",
+    ] {
+        let mut p = make_parser(src);
+        assert_eq!(Ok(correct.clone()), p.arith_for_command(), "{}", src);
+    }
+
+    let mut p = make_parser("for ((; i < 3;)); do break; done");
+    let fragments = p.arith_for_command().unwrap();
+    assert_eq!(None, fragments.init);
+    assert!(fragments.cond.is_some());
+    assert_eq!(None, fragments.update);
+}
+
+#[test]
+fn test_arith_for_command_dispatched_from_compound_command() {
+    use conch_parser::ast::Arithmetic::*;
+    use conch_parser::ast::{CompoundCommand, CompoundCommandKind};
+
+    let correct = CompoundCommand {
+        kind: CompoundCommandKind::ArithFor {
+            init: None,
+            cond: Some(Var(String::from("x"))),
+            update: None,
+            body: vec![cmd("foo")],
+        },
+        io: vec![],
+    };
+
+    let mut p = make_parser("for ((; x; )) do foo; done");
+    assert_eq!(correct, p.compound_command().unwrap());
+
+    // A regular `for` command is still recognized
+    let mut p = make_parser("for x in a; do foo; done");
+    match p.compound_command().unwrap().kind {
+        CompoundCommandKind::For { .. } => {}
+        kind => panic!("expected a regular for command, found {:?}", kind),
+    }
+}
+
+#[test]
+fn test_arith_for_command_invalid() {
+    assert_eq!(
+        Err(IncompleteCmd("for", src(0, 1, 1), "do", src(12, 1, 13))),
+        make_parser("for ((;;)); echo x").arith_for_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::ParenClose, src(9, 1, 10))),
+        make_parser("for ((i=0)); do echo; done").arith_for_command()
+    );
+    assert_eq!(
+        Err(UnexpectedEOF),
+        make_parser("for ((;;").arith_for_command()
+    );
+}
@@ -0,0 +1,59 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::*;
+use conch_parser::parse::ParseError::*;
+use conch_parser::token::Token;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[test]
+fn test_top_level_command_from_str() {
+    let correct = cmd_args("echo", &["hi"]);
+    assert_eq!(Ok(correct.clone()), "echo hi".parse());
+    assert_eq!(Ok(correct.clone()), "  echo hi;\n\n".parse());
+    assert_eq!(Ok(correct), "echo hi # comment\n# another".parse());
+
+    let cmd: AtomicTopLevelCommand<String> = "echo hi".parse().unwrap();
+    assert!(matches!(cmd.0, Command::List(_)));
+}
+
+#[test]
+fn test_top_level_command_from_str_rejects_trailing_input() {
+    assert_eq!(
+        Err::<TopLevelCommand<String>, _>(Unexpected(
+            Token::Name(String::from("echo")),
+            src(9, 1, 10)
+        )),
+        "echo hi; echo bye".parse()
+    );
+    assert_eq!(
+        Err::<TopLevelCommand<String>, _>(Unexpected(Token::ParenClose, src(8, 1, 9))),
+        "echo hi )".parse()
+    );
+}
+
+#[test]
+fn test_top_level_command_from_str_empty() {
+    for src in &["", "  \n\n", "# just a comment"] {
+        assert_eq!(
+            Err::<TopLevelCommand<String>, _>(UnexpectedEOF),
+            src.parse(),
+            "source: {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_top_level_word_from_str() {
+    assert_eq!(Ok(word("foo")), "foo".parse());
+    assert_eq!(Ok(word("foo")), " foo \n".parse());
+    assert_eq!(Err::<TopLevelWord<String>, _>(UnexpectedEOF), "".parse());
+    assert_eq!(
+        Err::<TopLevelWord<String>, _>(Unexpected(Token::Name(String::from("bar")), src(4, 1, 5))),
+        "foo bar".parse()
+    );
+
+    let word: AtomicTopLevelWord<String> = "foo".parse().unwrap();
+    assert!(matches!(word.0, ComplexWord::Single(_)));
+}
@@ -133,6 +133,7 @@ fn test_function_declaration_valid() {
 
 #[test]
 fn test_function_declaration_valid_body_need_not_be_a_compound_command() {
+    // Function bodies must be compound commands, so simple commands are rejected
     let src = vec![
         ("function foo()      echo body;", src(20, 1, 21)),
         ("function foo ()     echo body;", src(20, 1, 21)),
@@ -170,6 +171,63 @@ fn test_function_declaration_valid_body_need_not_be_a_compound_command() {
     }
 }
 
+#[test]
+fn test_function_declaration_body_can_be_any_compound_command() {
+    let bodies = vec![
+        "for x in a; do echo $x; done",
+        "while a; do b; done",
+        "until a; do b; done",
+        "if a; then b; else c; fi",
+        "case $x in a) b;; esac",
+        "(echo body)",
+        "if a; then b; fi > out 2>&1",
+    ];
+
+    for body in bodies {
+        let correct = FunctionDef(
+            String::from("f"),
+            Rc::new(make_parser(body).compound_command().unwrap()),
+        );
+
+        for decl in &["f() ", "f ()\n", "function f ", "function f() "] {
+            let s = format!("{}{}", decl, body);
+            match make_parser(&s).function_declaration() {
+                Ok(ref f) if *f == correct => {}
+                Ok(f) => panic!("Parsed the source \"{}\" as\n{:?}", s, f),
+                Err(e) => panic!("Failed to parse the source \"{}\": {}", s, e),
+            }
+        }
+    }
+}
+
+#[test]
+fn test_function_declaration_trailing_redirects_attach_to_body() {
+    let correct = FunctionDef(
+        String::from("f"),
+        Rc::new(CompoundCommand {
+            kind: Brace(vec![cmd_args("echo", &["hi"])]),
+            io: vec![
+                Redirect::Write(None, word("out")),
+                Redirect::DupWrite(Some(2), word("1")),
+            ],
+        }),
+    );
+
+    for src in &[
+        "f() { echo hi; } >out 2>&1",
+        "function f { echo hi; } > out 2>&1",
+    ] {
+        let src = format!("{}\nfoo", src);
+        let mut p = make_parser(&src);
+        let expected = TopLevelCommand(Command::List(CommandList {
+            first: ListableCommand::Single(correct.clone()),
+            rest: vec![],
+        }));
+        assert_eq!(Some(expected), p.complete_command().unwrap(), "{}", src);
+        assert_eq!(Some(cmd("foo")), p.complete_command().unwrap(), "{}", src);
+    }
+}
+
 #[test]
 fn test_function_declaration_parens_can_be_subshell_if_function_keyword_present() {
     let correct = FunctionDef(
@@ -417,7 +475,7 @@ fn test_function_declaration_invalid_concat() {
 
 #[test]
 fn test_function_declaration_should_recognize_literals_and_names_for_fn_keyword() {
-    for fn_tok in vec![
+    for fn_tok in [
         Token::Literal(String::from("function")),
         Token::Name(String::from("function")),
     ] {
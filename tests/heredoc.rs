@@ -1,6 +1,7 @@
 #![deny(rust_2018_idioms)]
+use conch_parser::ast::builder;
 use conch_parser::ast::ComplexWord::*;
-use conch_parser::ast::Redirect::Heredoc;
+use conch_parser::ast::Redirect::{Heredoc, Write};
 use conch_parser::ast::SimpleWord::*;
 use conch_parser::ast::*;
 use conch_parser::parse::ParseError::*;
@@ -79,6 +80,104 @@ fn test_heredoc_valid_does_not_lose_tokens_up_to_next_newline() {
     assert_eq!(second, p.complete_command().unwrap());
 }
 
+#[test]
+fn test_heredoc_valid_keeps_source_order_with_other_redirects() {
+    let correct = Some(cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("body\n"))),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+        ],
+    }));
+
+    let mut p = make_parser("cmd <<EOF >out\nbody\nEOF\necho");
+    assert_eq!(correct, p.complete_command().unwrap());
+    assert_eq!(Some(cmd("echo")), p.complete_command().unwrap());
+
+    let correct = Some(cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("body\n"))),
+            RedirectOrCmdWord::Redirect(Write(Some(2), word("err"))),
+        ],
+    }));
+
+    let mut p = make_parser("cmd >out <<EOF 2>err\nbody\nEOF\n");
+    assert_eq!(correct, p.complete_command().unwrap());
+}
+
+#[test]
+fn test_heredoc_valid_attached_to_compound_command() {
+    fn compound_io(cmd: TopLevelCommand<String>) -> (String, Vec<DefaultRedirect>) {
+        match cmd.0 {
+            Command::List(AndOrList {
+                first: ListableCommand::Single(PipeableCommand::Compound(compound)),
+                ..
+            }) => {
+                let CompoundCommand { kind, io } = *compound;
+                let kind = match kind {
+                    CompoundCommandKind::While(_) => "while",
+                    CompoundCommandKind::Brace(_) => "brace",
+                    _ => "other",
+                };
+                (String::from(kind), io)
+            }
+            cmd => panic!("expected a compound command, found {:?}", cmd),
+        }
+    }
+
+    let mut p = make_parser("while x; do y; done <<EOF >out\nbody\nEOF\necho");
+    assert_eq!(
+        (
+            String::from("while"),
+            vec![Heredoc(None, word("body\n")), Write(None, word("out"))],
+        ),
+        compound_io(p.complete_command().unwrap().unwrap())
+    );
+    assert_eq!(Some(cmd("echo")), p.complete_command().unwrap());
+
+    let mut p = make_parser("{ cat <<A; } 3<<B\nfirst\nA\nsecond\nB\necho");
+    let (kind, io) = compound_io(p.complete_command().unwrap().unwrap());
+    assert_eq!("brace", kind);
+    assert_eq!(vec![Heredoc(Some(3), word("second\n"))], io);
+    assert_eq!(Some(cmd("echo")), p.complete_command().unwrap());
+}
+
+#[test]
+fn test_heredoc_valid_multiple_bodies_assigned_in_order() {
+    let correct = Some(cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("first\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(Some(3), word("second\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(Some(4), word("third\n"))),
+            RedirectOrCmdWord::CmdWord(word("arg")),
+        ],
+    }));
+
+    let mut p = make_parser("cmd <<A 3<<B 4<<C arg\nfirst\nA\nsecond\nB\nthird\nC\necho");
+    assert_eq!(correct, p.complete_command().unwrap());
+    assert_eq!(Some(cmd("echo")), p.complete_command().unwrap());
+
+    let mut p = make_parser("cat <<A; cat 3<<B; cat 4<<C\nfirst\nA\nsecond\nB\nthird\nC\n");
+    assert_eq!(
+        Some(cat_heredoc(None, "first\n")),
+        p.complete_command().unwrap()
+    );
+    assert_eq!(
+        Some(cat_heredoc(Some(3), "second\n")),
+        p.complete_command().unwrap()
+    );
+    assert_eq!(
+        Some(cat_heredoc(Some(4), "third\n")),
+        p.complete_command().unwrap()
+    );
+}
+
 #[test]
 fn test_heredoc_valid_space_before_delimeter_allowed() {
     let mut p = make_parser("cat <<   eof1; cat 3<<- eof2\nhello\neof1\nworld\neof2");
@@ -154,6 +253,16 @@ fn test_heredoc_valid_leading_tab_removal_works() {
     assert_eq!(second, p.complete_command().unwrap());
 }
 
+#[test]
+fn test_heredoc_valid_leading_tab_removal_only_on_delimiter() {
+    let mut p = make_parser("cat <<-eof\nhello\n world\n\t\teof\necho");
+    let correct = Some(cat_heredoc(None, "hello\n world\n"));
+
+    assert_eq!(correct, p.complete_command().unwrap());
+    assert_eq!(Some(cmd("echo")), p.complete_command().unwrap());
+    assert_eq!(None, p.complete_command().unwrap());
+}
+
 #[test]
 fn test_heredoc_valid_leading_tab_removal_works_if_dash_immediately_after_dless() {
     let mut p = make_parser("cat 3<< -eof\n\t\t \t\nworld\n\t\teof\n\t\t-eof\n-eof");
@@ -161,6 +270,42 @@ fn test_heredoc_valid_leading_tab_removal_works_if_dash_immediately_after_dless(
     assert_eq!(correct, p.complete_command().unwrap());
 }
 
+#[test]
+fn test_heredoc_dash_only_strips_tabs_when_attached_to_dless() {
+    // `<<-` strips leading tabs regardless of whitespace before the delimiter
+    let correct = Some(cat_heredoc(None, "hello\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<-eof\n\thello\n\teof")
+            .complete_command()
+            .unwrap()
+    );
+    assert_eq!(
+        correct,
+        make_parser("cat <<- eof\n\thello\n\teof")
+            .complete_command()
+            .unwrap()
+    );
+
+    // A `-` separated from `<<` is part of the delimiter, and nothing is stripped
+    let correct = Some(cat_heredoc(None, "\thello\n\t-eof\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat << -eof\n\thello\n\t-eof\n-eof")
+            .complete_command()
+            .unwrap()
+    );
+
+    // A `-` following `<<-` is part of the delimiter
+    let correct = Some(cat_heredoc(None, "hello\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<--eof\n\thello\n\t-eof")
+            .complete_command()
+            .unwrap()
+    );
+}
+
 #[test]
 fn test_heredoc_valid_unquoted_backslashes_in_delimeter_disappear() {
     let correct = Some(cat_heredoc(None, "hello\n"));
@@ -205,6 +350,43 @@ fn test_heredoc_valid_balanced_backticks_in_delimeter() {
     );
 }
 
+#[test]
+fn test_heredoc_valid_dollar_quoted_delimeter() {
+    let correct = Some(cat_heredoc(None, "body $foo\n"));
+    for src in &[
+        "cat <<$'EOF'\nbody $foo\nEOF",
+        "cat <<$\"EOF\"\nbody $foo\nEOF",
+        "cat <<E$'O'F\nbody $foo\nEOF",
+    ] {
+        assert_eq!(
+            correct,
+            make_parser(src).complete_command().unwrap(),
+            "source: {:?}",
+            src
+        );
+    }
+
+    // A `$` not followed by quotes remains part of the delimeter
+    let correct = Some(cat_heredoc(None, "body\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<'E'$F\nbody\nE$F")
+            .complete_command()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_heredoc_valid_backticked_delimeter() {
+    let correct = Some(cat_heredoc(None, "body $foo\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<`EOF`\nbody $foo\n`EOF`")
+            .complete_command()
+            .unwrap()
+    );
+}
+
 #[test]
 fn test_heredoc_valid_balanced_parens_in_delimeter() {
     let correct = Some(cat_heredoc(None, "hello\n"));
@@ -238,6 +420,28 @@ fn test_heredoc_valid_param_subst_in_delimeter() {
     );
 }
 
+#[test]
+fn test_heredoc_valid_special_characters_in_delimeter_are_literal() {
+    let correct = Some(cat_heredoc(None, "e\nef\ne$\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<e$f\ne\nef\ne$\ne$f\n")
+            .complete_command()
+            .unwrap()
+    );
+
+    let correct = Some(cat_heredoc(None, "eof\n"));
+    for delim in &["eof$", "$eof", "e#f", "e`f`", "e$$f"] {
+        let src = format!("cat <<{0}\neof\n{0}\n", delim);
+        assert_eq!(
+            correct,
+            make_parser(&src).complete_command().unwrap(),
+            "{}",
+            delim
+        );
+    }
+}
+
 #[test]
 fn test_heredoc_valid_skip_past_newlines_in_single_quotes() {
     let correct = Some(cmd_from_simple(SimpleCommand {
@@ -413,7 +617,7 @@ fn test_heredoc_valid_delimeter_can_be_followed_by_carriage_return_newline() {
         redirects_or_env_vars: vec![],
         redirects_or_cmd_words: vec![
             RedirectOrCmdWord::CmdWord(word("cat")),
-            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\r\n"))),
+            RedirectOrCmdWord::Redirect(Heredoc(None, word("here\n"))),
             RedirectOrCmdWord::CmdWord(word("arg")),
         ],
     }));
@@ -425,6 +629,47 @@ fn test_heredoc_valid_delimeter_can_be_followed_by_carriage_return_newline() {
     );
 }
 
+#[test]
+fn test_heredoc_crlf_script() {
+    let mut p = make_parser(
+        "# comment\r\ncat <<EOF\r\nhello\r\n  world \r\nEOF\r\necho foo \\\r\nbar\r\n)",
+    );
+
+    assert_eq!(
+        p.linebreak(),
+        vec!(builder::Newline(Some(String::from("# comment"))))
+    );
+    assert_eq!(
+        Some(cat_heredoc(None, "hello\n  world \n")),
+        p.complete_command().unwrap()
+    );
+    assert_eq!(
+        Some(cmd_args("echo", &["foo", "bar"])),
+        p.complete_command().unwrap()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::ParenClose, src(61, 8, 1))),
+        p.complete_command()
+    );
+}
+
+#[test]
+fn test_heredoc_crlf_preserves_lone_carriage_return_in_quotes() {
+    let correct = Some(cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(TopLevelWord(Single(Word::SingleQuoted(String::from(
+                "a\rb",
+            ))))),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("echo 'a\rb'\r\n").complete_command().unwrap()
+    );
+}
+
 #[test]
 fn test_heredoc_valid_delimiter_can_start_with() {
     let correct = Some(cat_heredoc(None, "\thello\n\t\tworld\n"));
@@ -515,3 +760,22 @@ fn test_heredoc_keeps_track_of_correct_position_after_redirect() {
         p.complete_command()
     );
 }
+
+#[test]
+fn test_heredoc_preserves_nul_bytes() {
+    let correct = Some(cat_heredoc(None, "a\0b\n\0\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<eof\na\0b\n\0\neof\n")
+            .complete_command()
+            .unwrap()
+    );
+
+    let correct = Some(cat_heredoc(None, "a\0b\n"));
+    assert_eq!(
+        correct,
+        make_parser("cat <<'eof'\na\0b\neof\n")
+            .complete_command()
+            .unwrap()
+    );
+}
@@ -1,5 +1,6 @@
 #![deny(rust_2018_idioms)]
 use conch_parser::ast::builder::*;
+use conch_parser::parse::Expectation::ReservedWord;
 use conch_parser::parse::ParseError::*;
 use conch_parser::token::Token;
 
@@ -139,6 +140,37 @@ fn test_if_command_invalid_missing_keyword() {
     );
 }
 
+#[test]
+fn test_if_command_invalid_truncated_reports_expected_keywords() {
+    let mut p = make_parser("if guard; then body;");
+    assert_eq!(
+        Err(Expected(
+            vec![
+                ReservedWord("elif"),
+                ReservedWord("else"),
+                ReservedWord("fi")
+            ],
+            None,
+            src(20, 1, 21)
+        )),
+        p.if_command()
+    );
+
+    let mut p = make_parser("if guard\nthen\n  body\n");
+    assert_eq!(
+        Err(Expected(
+            vec![
+                ReservedWord("elif"),
+                ReservedWord("else"),
+                ReservedWord("fi")
+            ],
+            None,
+            src(21, 4, 1)
+        )),
+        p.if_command()
+    );
+}
+
 #[test]
 fn test_if_command_invalid_missing_guard() {
     let mut p = make_parser("if; then body1; elif guard2; then body2; else else; fi");
@@ -155,6 +187,51 @@ fn test_if_command_invalid_missing_body() {
     assert_eq!(Err(Unexpected(Token::Semi, src(52, 1, 53))), p.if_command());
 }
 
+#[test]
+fn test_if_command_empty_body_depends_on_config() {
+    use conch_parser::parse::ParserConfig;
+
+    let source = "if x; then; fi";
+    assert_eq!(
+        Err(Unexpected(Token::Semi, src(10, 1, 11))),
+        make_parser(source).if_command()
+    );
+
+    let cfg = ParserConfig {
+        allow_empty_bodies: true,
+        ..ParserConfig::default()
+    };
+
+    let correct = IfFragments {
+        conditionals: vec![GuardBodyPairGroup {
+            guard: CommandGroup {
+                commands: vec![cmd("x")],
+                trailing_comments: vec![],
+            },
+            body: CommandGroup {
+                commands: vec![],
+                trailing_comments: vec![],
+            },
+        }],
+        else_branch: Some(CommandGroup {
+            commands: vec![],
+            trailing_comments: vec![Newline(None)],
+        }),
+    };
+
+    let mut p = make_parser_with_config("if x; then; else\nfi", cfg.clone());
+    assert_eq!(correct, p.if_command().unwrap());
+
+    let mut p = make_parser_with_config(source, cfg);
+    assert_eq!(
+        IfFragments {
+            else_branch: None,
+            ..correct
+        },
+        p.if_command().unwrap()
+    );
+}
+
 #[test]
 fn test_if_command_invalid_quoted() {
     let cmds = [
@@ -258,23 +335,23 @@ fn test_if_command_invalid_concat() {
 
 #[test]
 fn test_if_command_should_recognize_literals_and_names() {
-    for if_tok in vec![
+    for if_tok in [
         Token::Literal(String::from("if")),
         Token::Name(String::from("if")),
     ] {
-        for then_tok in vec![
+        for then_tok in [
             Token::Literal(String::from("then")),
             Token::Name(String::from("then")),
         ] {
-            for elif_tok in vec![
+            for elif_tok in [
                 Token::Literal(String::from("elif")),
                 Token::Name(String::from("elif")),
             ] {
-                for else_tok in vec![
+                for else_tok in [
                     Token::Literal(String::from("else")),
                     Token::Name(String::from("else")),
                 ] {
-                    for fi_tok in vec![
+                    for fi_tok in [
                         Token::Literal(String::from("fi")),
                         Token::Name(String::from("fi")),
                     ] {
@@ -307,3 +384,45 @@ fn test_if_command_should_recognize_literals_and_names() {
         }
     }
 }
+
+#[test]
+fn test_if_command_valid_backgrounded_guard() {
+    use conch_parser::ast::Command::Job;
+    use conch_parser::ast::{CommandList, ListableCommand, PipeableCommand, TopLevelCommand};
+
+    let guard = TopLevelCommand(Job(CommandList {
+        first: ListableCommand::Single(PipeableCommand::Simple(cmd_simple("foo"))),
+        rest: vec![],
+    }));
+
+    let correct = IfFragments {
+        conditionals: vec![GuardBodyPairGroup {
+            guard: CommandGroup {
+                commands: vec![guard],
+                trailing_comments: vec![],
+            },
+            body: CommandGroup {
+                commands: vec![cmd("bar")],
+                trailing_comments: vec![],
+            },
+        }],
+        else_branch: None,
+    };
+
+    assert_eq!(
+        correct,
+        make_parser("if foo & then bar; fi").if_command().unwrap()
+    );
+}
+
+#[test]
+fn test_if_command_invalid_dsemi_in_guard() {
+    assert_eq!(
+        Err(Unexpected(Token::DSemi, src(3, 1, 4))),
+        make_parser("if ;; then bar; fi").if_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::DSemi, src(6, 1, 7))),
+        make_parser("if foo;; then bar; fi").if_command()
+    );
+}
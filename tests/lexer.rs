@@ -1,7 +1,8 @@
 #![deny(rust_2018_idioms)]
-use conch_parser::lexer::Lexer;
+use conch_parser::lexer::{Lexer, NameChars, Utf8Chars};
 use conch_parser::token::Token::*;
-use conch_parser::token::{Positional, Token};
+use conch_parser::token::{Positional, Token, TokenKind};
+use std::io::{self, BufReader, Read};
 
 macro_rules! check_tok {
     ($fn_name:ident, $tok:expr) => {
@@ -66,6 +67,9 @@ check_tok!(check_LessAnd, LessAnd);
 check_tok!(check_DLessDash, DLessDash);
 check_tok!(check_Clobber, Clobber);
 check_tok!(check_LessGreat, LessGreat);
+check_tok!(check_AmpGreat, AmpGreat);
+check_tok!(check_AmpDGreat, AmpDGreat);
+check_tok!(check_AmpClobber, AmpClobber);
 check_tok!(check_Whitespace, Whitespace(String::from(" \t\r")));
 check_tok!(check_Name, Name(String::from("abc_23_defg")));
 check_tok!(check_Literal, Literal(String::from("5abcdefg80hijklmnop")));
@@ -77,6 +81,15 @@ lex_str!(check_greedy_Semi, ";;;", DSemi, Semi);
 lex_str!(check_greedy_Less, "<<<", DLess, Less);
 lex_str!(check_greedy_Great, ">>>", DGreat, Great);
 lex_str!(check_greedy_Less2, "<<<-", DLess, Less, Dash);
+lex_str!(check_greedy_AmpGreat, "&>>>", AmpDGreat, Great);
+lex_str!(check_greedy_AmpClobber, "&>||", AmpClobber, Pipe);
+lex_str!(
+    check_AmpGreat_then_Pipe,
+    "&> |",
+    AmpGreat,
+    Whitespace(String::from(" ")),
+    Pipe
+);
 
 lex_str!(
     check_bad_Assigmnent_and_value,
@@ -106,6 +119,7 @@ lex_str!(
 lex_str!(check_escape_Backslash, "\\\\", Backslash, Backslash);
 lex_str!(check_escape_AndIf, "\\&&", Backslash, Amp, Amp);
 lex_str!(check_escape_DSemi, "\\;;", Backslash, Semi, Semi);
+lex_str!(check_escape_AmpClobber, "\\&>|", Backslash, Amp, Clobber);
 lex_str!(check_escape_DLess, "\\<<", Backslash, Less, Less);
 lex_str!(check_escape_DLessDash, "\\<<-", Backslash, Less, Less, Dash);
 lex_str!(
@@ -144,3 +158,126 @@ lex_str!(
     Backslash,
     SingleQuote
 );
+
+/// A reader which never returns more than a couple of bytes at a time,
+/// forcing multi-byte characters to be split across reads.
+struct ChunkedReader<'a>(&'a [u8]);
+
+impl Read for ChunkedReader<'_> {
+    fn read(&mut self, buf: &mut [u8]) -> io::Result<usize> {
+        let n = self.0.len().min(buf.len()).min(2);
+        buf[..n].copy_from_slice(&self.0[..n]);
+        self.0 = &self.0[n..];
+        Ok(n)
+    }
+}
+
+fn decode_chunked(bytes: &[u8]) -> Vec<Result<char, io::ErrorKind>> {
+    Utf8Chars::new(BufReader::with_capacity(3, ChunkedReader(bytes)))
+        .map(|c| c.map_err(|e| e.kind()))
+        .collect()
+}
+
+#[test]
+fn test_utf8_chars_decodes_characters_split_across_reads() {
+    let src = "echo h\u{e9}llo \u{2603} \u{1F600}\nfoo | bar";
+    let decoded = decode_chunked(src.as_bytes());
+    let correct: Vec<_> = src.chars().map(Ok).collect();
+    assert_eq!(correct, decoded);
+}
+
+#[test]
+fn test_utf8_chars_reports_invalid_data_and_resumes() {
+    use std::io::ErrorKind::InvalidData;
+
+    assert_eq!(
+        vec![Ok('a'), Err(InvalidData), Ok('b')],
+        decode_chunked(b"a\xffb")
+    );
+    // A truncated character does not swallow the byte which follows it
+    assert_eq!(
+        vec![Ok('a'), Err(InvalidData), Ok('b')],
+        decode_chunked(b"a\xe2\x98b")
+    );
+    assert_eq!(
+        vec![Ok('a'), Err(InvalidData)],
+        decode_chunked(b"a\xe2\x98")
+    );
+}
+
+#[test]
+fn test_utf8_chars_lexes_same_as_str() {
+    let src = "foo=\u{e9}t\u{e9} bar 'caf\u{e9}' && \u{2603}";
+    let chars = Utf8Chars::new(BufReader::with_capacity(3, ChunkedReader(src.as_bytes())))
+        .map(Result::unwrap);
+    let tokens: Vec<Token> = Lexer::new(chars).collect();
+    assert_eq!(Lexer::new(src.chars()).collect::<Vec<_>>(), tokens);
+}
+
+#[test]
+fn test_token_kind_discards_payloads() {
+    let cases = vec![
+        (Name(String::from("foo")), TokenKind::Name),
+        (Name(String::new()), TokenKind::Name),
+        (Literal(String::from("1foo")), TokenKind::Literal),
+        (Whitespace(String::from(" \t")), TokenKind::Whitespace),
+        (
+            ParamPositional(Positional::Zero),
+            TokenKind::ParamPositional,
+        ),
+        (
+            ParamPositional(Positional::Nine),
+            TokenKind::ParamPositional,
+        ),
+        (Newline, TokenKind::Newline),
+        (DLessDash, TokenKind::DLessDash),
+        (Pipe, TokenKind::Pipe),
+    ];
+
+    for (tok, kind) in cases {
+        assert_eq!(kind, tok.kind());
+        assert_eq!(!kind.has_payload(), kind.to_string() == tok.to_string());
+    }
+
+    assert_eq!("a name", TokenKind::Name.to_string());
+    assert_eq!("<<-", TokenKind::DLessDash.to_string());
+}
+
+#[test]
+fn check_name_chars() {
+    let lex =
+        |src: &str, name_chars| Lexer::with_name_chars(src.chars(), name_chars).collect::<Vec<_>>();
+
+    assert_eq!(
+        vec![
+            Name(String::from("caf")),
+            Literal(String::from("é_1")),
+            Whitespace(String::from(" ")),
+            Literal(String::from("ñame")),
+        ],
+        lex("café_1 ñame", NameChars::Posix)
+    );
+    assert_eq!(
+        vec![
+            Name(String::from("café_1")),
+            Whitespace(String::from(" ")),
+            Name(String::from("ñame")),
+        ],
+        lex("café_1 ñame", NameChars::Unicode)
+    );
+
+    assert_eq!(NameChars::Unicode, NameChars::default());
+    assert!(NameChars::Unicode.is_name_start('é'));
+    assert!(!NameChars::Posix.is_name_start('é'));
+    assert!(NameChars::Posix.is_name_char('1'));
+    assert!(!NameChars::Posix.is_name_start('1'));
+}
+
+lex_str!(
+    check_nul_is_a_literal,
+    "a\0b \0",
+    Name(String::from("a")),
+    Literal(String::from("\0b")),
+    Whitespace(String::from(" ")),
+    Literal(String::from("\0"))
+);
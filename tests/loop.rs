@@ -27,6 +27,26 @@ fn test_loop_command_while_valid() {
     assert_eq!(correct_body, body);
 }
 
+#[test]
+fn test_loop_command_body_preserves_background_jobs() {
+    use conch_parser::ast::Command::Job;
+    use conch_parser::ast::{CommandList, ListableCommand, PipeableCommand, TopLevelCommand};
+
+    let mut p = make_parser("while x; do foo & bar; done");
+    let (_, GuardBodyPairGroup { body, .. }) = p.loop_command().unwrap();
+
+    let job = TopLevelCommand(Job(CommandList {
+        first: ListableCommand::Single(PipeableCommand::Simple(cmd_simple("foo"))),
+        rest: vec![],
+    }));
+
+    let correct_body = CommandGroup {
+        commands: vec![job, cmd("bar")],
+        trailing_comments: vec![],
+    };
+    assert_eq!(correct_body, body);
+}
+
 #[test]
 fn test_loop_command_until_valid() {
     let mut p = make_parser(
@@ -167,7 +187,7 @@ fn test_loop_command_invalid_concat() {
 
 #[test]
 fn test_loop_command_should_recognize_literals_and_names() {
-    for kw in vec![
+    for kw in [
         Token::Literal(String::from("while")),
         Token::Name(String::from("while")),
         Token::Literal(String::from("until")),
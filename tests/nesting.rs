@@ -0,0 +1,88 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::*;
+
+fn nested(open: &str, body: &str, close: &str, depth: usize) -> String {
+    format!("{}{}{}", open.repeat(depth), body, close.repeat(depth))
+}
+
+fn parse_all(src: &str, cfg: ParserConfig) -> Result<(), ParseError<void::Void>> {
+    let lex = Lexer::new(src.chars());
+    let mut p = DefaultParser::with_config(lex, Default::default(), cfg);
+    while p.complete_command()?.is_some() {}
+    Ok(())
+}
+
+#[test]
+fn test_nesting_too_deep_is_an_error() {
+    let cases = vec![
+        nested("(", "foo", ")", 100_000),
+        nested("{ ", "foo;", " }", 100_000),
+        format!("echo {}", nested("$(", "foo", ")", 100_000)),
+        format!("echo {}", nested("${x:-", "foo", "}", 100_000)),
+        format!("echo $(( {} ))", nested("(", "1", ")", 100_000)),
+        format!("echo $(( {} ))", nested("!", "1", "", 100_000)),
+        format!("case x in {}) ;; esac", nested("@(", "a", ")", 100_000)),
+        format!("echo $(( {} ))", nested("2**", "1", "", 100_000)),
+        format!("echo $(( {} ))", nested("x=", "1", "", 100_000)),
+        format!("echo $(( {} ))", nested("1?1:", "1", "", 100_000)),
+        format!("echo $(( {} ))", nested("1?", "1", ":1", 100_000)),
+    ];
+
+    for src in cases {
+        match parse_all(&src, ParserConfig::default()) {
+            Err(ParseError::NestingTooDeep(_)) => {}
+            other => panic!(
+                "expected nesting error for {}..., got: {:?}",
+                &src[..20],
+                other
+            ),
+        }
+    }
+}
+
+#[test]
+fn test_nesting_within_limit_is_accepted() {
+    let depth = DEFAULT_MAX_NESTING_DEPTH / 2;
+    let cases = vec![
+        nested("(", "foo", ")", depth),
+        format!("echo {}", nested("$(", "foo", ")", depth / 2)),
+        format!("echo {}", nested("${x:-", "foo", "}", depth)),
+        format!("echo $(( {} ))", nested("(", "1", ")", depth / 2)),
+        format!("case x in {}) ;; esac", nested("@(", "a", ")", depth)),
+        format!("echo $(( {} ))", nested("2**", "1", "", depth / 2)),
+        format!("echo $(( {} ))", nested("x=", "1", "", depth / 2)),
+        format!("echo $(( {} ))", nested("1?1:", "1", "", depth / 2)),
+    ];
+
+    for src in cases {
+        if let Err(e) = parse_all(&src, ParserConfig::default()) {
+            panic!("failed to parse {}...: {}", &src[..20], e);
+        }
+    }
+}
+
+#[test]
+fn test_nesting_limit_is_configurable() {
+    // Three subshells wrapped around a simple command, four commands deep
+    let source = nested("(", "foo", ")", 3);
+
+    let cfg = ParserConfig {
+        max_nesting_depth: 4,
+        ..ParserConfig::default()
+    };
+    assert_eq!(parse_all(&source, cfg), Ok(()));
+
+    let cfg = ParserConfig {
+        max_nesting_depth: 3,
+        ..ParserConfig::default()
+    };
+    assert_eq!(
+        parse_all(&source, cfg),
+        Err(ParseError::NestingTooDeep(SourcePos {
+            byte: 3,
+            line: 1,
+            col: 4
+        }))
+    );
+}
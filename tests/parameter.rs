@@ -42,6 +42,40 @@ fn test_parameter_short_in_curlies() {
     assert_eq!(Err(UnexpectedEOF), p.parameter()); // Stream should be exhausted
 }
 
+#[test]
+fn test_parameter_unbraced_positional_is_single_digit() {
+    use conch_parser::ast::{ComplexWord, SimpleWord, TopLevelWord, Word};
+
+    let correct = TopLevelWord(ComplexWord::Concat(vec![
+        Word::Simple(SimpleWord::Param(Positional(1))),
+        lit("2"),
+    ]));
+    assert_eq!(correct, make_parser("$12").word().unwrap().unwrap());
+
+    assert_eq!(
+        word_param(Positional(12)),
+        make_parser("${12}").word().unwrap().unwrap()
+    );
+}
+
+#[test]
+fn test_parameter_underscore_is_a_var() {
+    let words = vec![
+        Var(String::from("_")),
+        Var(String::from("_")),
+        Var(String::from("__")),
+        Var(String::from("_a")),
+    ];
+
+    let mut p = make_parser("$_ ${_} $__ $_a");
+    for param in words {
+        assert_eq!(p.parameter().unwrap(), word_param(param));
+        p.skip_whitespace();
+    }
+
+    assert_eq!(Err(UnexpectedEOF), p.parameter()); // Stream should be exhausted
+}
+
 #[test]
 fn test_parameter_command_substitution() {
     let correct = word_subst(Command(vec![
@@ -2,7 +2,10 @@
 #![recursion_limit = "128"]
 
 use conch_parser::ast::builder::*;
+use conch_parser::lexer::{Lexer, Utf8Chars};
+use conch_parser::parse::Expectation::ReservedWord;
 use conch_parser::parse::*;
+use conch_parser::token::{Token, TokenKind};
 
 mod parse_support;
 use crate::parse_support::*;
@@ -14,6 +17,221 @@ fn test_parser_should_yield_none_after_error() {
     assert_eq!(iter.next(), None);
 }
 
+#[test]
+fn test_parser_reset_reuses_parser_across_sources() {
+    let mut p = make_parser("foo; bar");
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("foo")));
+
+    // Leave some tokens unconsumed, they should be discarded on reset
+    p.reset(Lexer::new("baz qux\nfoo".chars()));
+    assert_eq!(p.pos(), SourcePos::new());
+    assert_eq!(
+        p.complete_command().unwrap(),
+        Some(cmd_args("baz", &["qux"]))
+    );
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("foo")));
+    assert_eq!(p.complete_command().unwrap(), None);
+
+    p.reset(Lexer::new("foo &&\n;".chars()));
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unexpected(Token::Semi, src(7, 2, 1)))
+    );
+
+    p.reset(Lexer::new("bar".chars()));
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("bar")));
+    assert_eq!(p.complete_command().unwrap(), None);
+}
+
+#[test]
+fn test_skip_to_next_command_ignores_nested_separators() {
+    let mut p = make_parser("echo $(a; b) 'c;d' \"e&f\" g\\;h (i\nj) ${k:-;}; echo next\nlast");
+    p.skip_to_next_command();
+    assert_eq!(p.pos(), src(44, 2, 12));
+    assert_eq!(
+        p.complete_command().unwrap(),
+        Some(cmd_args("echo", &["next"]))
+    );
+
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), None);
+}
+
+#[test]
+fn test_skip_to_next_command_stops_at_each_separator() {
+    let mut p = make_parser("a & b\nc; d");
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("b")));
+
+    let mut p = make_parser("a & b\nc; d");
+    p.skip_to_next_command();
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("c")));
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), None);
+
+    // Separators within comments are ignored
+    let mut p = make_parser("a # x; y\nnext; a#b; c");
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("next")));
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("c")));
+
+    // Unbalanced input is skipped through to the end
+    let mut p = make_parser("a $(b; c");
+    p.skip_to_next_command();
+    assert_eq!(p.complete_command().unwrap(), None);
+}
+
+#[test]
+fn test_skip_to_next_command_recovers_after_error() {
+    let mut p = make_parser("echo )bad $(x; y); echo ok");
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("echo")));
+    assert_eq!(
+        p.complete_command(),
+        Err(ParseError::Unexpected(Token::ParenClose, src(5, 1, 6)))
+    );
+
+    p.skip_to_next_command();
+    assert_eq!(
+        p.complete_command().unwrap(),
+        Some(cmd_args("echo", &["ok"]))
+    );
+}
+
+#[test]
+fn test_command_group_until_custom_predicate() {
+    let mut p = make_parser("foo\nbar; baz\nqux");
+    let group = p.command_group_until(|p| p.pos().line > 2).unwrap();
+    assert_eq!(
+        group,
+        CommandGroup {
+            commands: vec![cmd("foo"), cmd("bar"), cmd("baz")],
+            trailing_comments: vec![],
+        }
+    );
+    assert_eq!(p.complete_command().unwrap(), Some(cmd("qux")));
+
+    let mut p = make_parser("");
+    assert_eq!(
+        p.command_group_until(|_| false),
+        Err(ParseError::UnexpectedEOF)
+    );
+}
+
+#[test]
+fn test_command_list_terminated_by_custom_tokens() {
+    use conch_parser::ast::{
+        Command, CommandList, ListableCommand, PipeableCommand, TopLevelCommand,
+    };
+
+    let mut p = make_parser("foo; bar | baz\n# comment\n) qux");
+    let correct = vec![
+        cmd("foo"),
+        TopLevelCommand(Command::List(CommandList {
+            first: ListableCommand::Pipe(
+                false,
+                vec![
+                    PipeableCommand::Simple(cmd_simple("bar")),
+                    PipeableCommand::Simple(cmd_simple("baz")),
+                ],
+            ),
+            rest: vec![],
+        })),
+    ];
+    assert_eq!(
+        correct,
+        p.command_list_terminated_by(&[Token::ParenClose]).unwrap()
+    );
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::ParenClose, src(25, 3, 1))),
+        p.complete_command()
+    );
+
+    let mut p = make_parser("foo\nbar ;; baz");
+    let correct = vec![cmd("foo"), cmd("bar")];
+    assert_eq!(
+        correct,
+        p.command_list_terminated_by(&[Token::DSemi, Token::ParenClose])
+            .unwrap()
+    );
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::DSemi, src(8, 2, 5))),
+        p.complete_command()
+    );
+
+    let mut p = make_parser(";; foo");
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::DSemi, src(0, 1, 1))),
+        p.command_list_terminated_by(&[Token::DSemi])
+    );
+}
+
+#[test]
+fn test_command_group_until_matches_command_group() {
+    let cfg = CommandGroupDelimiters {
+        reserved_words: &["end"],
+        reserved_tokens: &[Token::CurlyClose],
+        ..Default::default()
+    };
+    let stop = |p: &mut DefaultParser<_>| {
+        p.peek_reserved_word(&["end"]).is_some()
+            || p.peek_reserved_token(&[Token::CurlyClose]).is_some()
+    };
+
+    for src in &["foo; bar end\n#comment\nend", "foo\n}", "end", "foo & end"] {
+        assert_eq!(
+            make_parser(src).command_group(cfg.clone()),
+            make_parser(src).command_group_until(stop),
+            "source: {:?}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_complete_command_with_leading_comments_returns_attached_comments() {
+    let mut p = make_parser("# lead\nfoo # trail\nbar\n\n# eof");
+    let correct = CommentedCommand {
+        leading_comments: vec![Newline(Some(String::from("# lead")))],
+        command: cmd("foo"),
+        trailing_comment: Some(Newline(Some(String::from("# trail")))),
+    };
+    assert_eq!(
+        Some(correct),
+        p.complete_command_with_leading_comments().unwrap()
+    );
+
+    let correct = CommentedCommand {
+        leading_comments: vec![],
+        command: cmd("bar"),
+        trailing_comment: Some(Newline(None)),
+    };
+    assert_eq!(
+        Some(correct),
+        p.complete_command_with_leading_comments().unwrap()
+    );
+
+    // Comments which aren't followed by a command aren't returned
+    assert_eq!(None, p.complete_command_with_leading_comments().unwrap());
+}
+
+#[test]
+fn test_complete_command_with_leading_comments_independent_of_builder() {
+    let source = "\n# lead\nfoo # trail";
+    let mut p = Parser::with_builder(Lexer::new(source.chars()), EmptyBuilder);
+    let correct = CommentedCommand {
+        leading_comments: vec![Newline(None), Newline(Some(String::from("# lead")))],
+        command: (),
+        trailing_comment: Some(Newline(Some(String::from("# trail")))),
+    };
+    assert_eq!(
+        Some(correct),
+        p.complete_command_with_leading_comments().unwrap()
+    );
+    assert_eq!(None, p.complete_command_with_leading_comments().unwrap());
+}
+
 #[test]
 fn test_linebreak_valid_with_comments_and_whitespace() {
     let mut p = make_parser("\n\t\t\t\n # comment1\n#comment2\n   \n");
@@ -141,6 +359,31 @@ fn test_comment_can_start_if_whitespace_before_pound() {
     assert_eq!(comment, vec!(Newline(Some(String::from("#world")))));
 }
 
+#[test]
+fn test_comment_after_command_word_unless_only_at_line_start() {
+    let source = "echo foo #bar\n#baz\necho qux; #quux\n";
+
+    let correct = vec![cmd_args("echo", &["foo"]), cmd_args("echo", &["qux"])];
+    let cmds = make_parser(source)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(correct, cmds);
+
+    let cfg = ParserConfig {
+        comment_only_at_line_start: true,
+        ..ParserConfig::default()
+    };
+    let p = make_parser_with_config(source, cfg);
+
+    let correct = vec![
+        cmd_args("echo", &["foo", "#bar"]),
+        cmd_args("echo", &["qux"]),
+    ];
+    let cmds = p.into_iter().collect::<Result<Vec<_>, _>>().unwrap();
+    assert_eq!(correct, cmds);
+}
+
 #[test]
 fn test_braces_literal_unless_brace_group_expected() {
     let source = "echo {} } {";
@@ -162,8 +405,387 @@ fn ensure_parse_errors_are_send_and_sync() {
 
 #[test]
 fn ensure_parser_could_be_send_and_sync() {
-    use conch_parser::token::Token;
-
     fn send_and_sync<T: Send + Sync>() {}
     send_and_sync::<Parser<std::vec::IntoIter<Token>, ArcBuilder>>();
 }
+
+#[test]
+fn test_default_config_reserves_default_words_and_tokens() {
+    let cfg = ParserConfig::default();
+    for &w in DEFAULT_RESERVED_WORDS {
+        assert!(cfg.is_reserved_word(w), "{} is not reserved", w);
+    }
+    for t in DEFAULT_RESERVED_TOKENS {
+        assert!(cfg.is_reserved_token(t), "{} is not reserved", t);
+    }
+    assert_eq!(*make_parser("").config(), cfg);
+}
+
+#[test]
+fn test_config_without_function_keyword_parses_simple_command() {
+    let mut cfg = ParserConfig::default();
+    cfg.reserved_words.retain(|&w| w != "function");
+
+    let mut p = make_parser_with_config("function foo", cfg);
+    assert_eq!(
+        Some(cmd_args("function", &["foo"])),
+        p.complete_command().unwrap()
+    );
+}
+
+#[test]
+fn test_config_without_if_keyword_parses_simple_command() {
+    let mut cfg = ParserConfig::default();
+    cfg.reserved_words.retain(|&w| w != "if");
+
+    let mut p = make_parser_with_config("if foo", cfg);
+    assert_eq!(
+        Some(cmd_args("if", &["foo"])),
+        p.complete_command().unwrap()
+    );
+}
+
+#[test]
+fn test_config_with_unsupported_keyword_rejects_command() {
+    let source = "select foo";
+    assert_eq!(
+        Some(cmd_args("select", &["foo"])),
+        make_parser(source).complete_command().unwrap()
+    );
+
+    let mut cfg = ParserConfig::default();
+    cfg.reserved_words.push("select");
+
+    let mut p = make_parser_with_config(source, cfg);
+    assert_eq!(
+        Err(ParseError::Unexpected(
+            Token::Name(String::from("select")),
+            src(0, 1, 1)
+        )),
+        p.complete_command()
+    );
+}
+
+#[test]
+fn test_config_without_bang_token_parses_literal() {
+    let mut cfg = ParserConfig::default();
+    cfg.reserved_tokens.retain(|t| *t != Token::Bang);
+
+    let mut p = make_parser_with_config("! foo", cfg);
+    assert_eq!(Some(cmd_args("!", &["foo"])), p.complete_command().unwrap());
+}
+
+#[test]
+fn test_parse_error_display() {
+    use conch_parser::parse::ParseError::*;
+
+    let cases: Vec<(ParseError<String>, &str)> = vec![
+        (
+            BadFd(src(4, 3, 5), src(8, 3, 9)),
+            "file descriptor found at 3:5-3:9 cannot possibly be valid",
+        ),
+        (
+            BadIdent(String::from("foo-bar"), src(4, 1, 5)),
+            "not a valid identifier at 1:5: foo-bar",
+        ),
+        (
+            BadSubst(Token::Semi, src(4, 2, 3)),
+            "bad substitution at 2:3: invalid token: ;",
+        ),
+        (
+            Unmatched(Token::ParenOpen, src(0, 1, 1)),
+            "unmatched `(` starting at 1:1",
+        ),
+        (
+            IncompleteCmd("if", src(0, 1, 1), "fi", src(20, 2, 7)),
+            "did not find `fi` keyword at 2:7, in `if` command which starts at 1:1",
+        ),
+        (
+            Unexpected(Token::Newline, src(3, 1, 4)),
+            "found unexpected token at 1:4: \\n",
+        ),
+        (
+            Unexpected(Token::Semi, src(3, 1, 4)),
+            "found unexpected token at 1:4: ;",
+        ),
+        (
+            Expected(vec![ReservedWord("do")], None, src(4, 1, 5)),
+            "expected `do` at 1:5, found end of input",
+        ),
+        (
+            Expected(
+                vec![
+                    ReservedWord("elif"),
+                    ReservedWord("else"),
+                    ReservedWord("fi"),
+                ],
+                Some(Token::Name(String::from("done"))),
+                src(21, 1, 22),
+            ),
+            "expected one of `elif`, `else`, or `fi` at 1:22, found: done",
+        ),
+        (
+            Expected(
+                vec![Expectation::Token(TokenKind::Name)],
+                Some(Token::SingleQuote),
+                src(4, 1, 5),
+            ),
+            "expected a name at 1:5, found: '",
+        ),
+        (
+            Expected(
+                vec![
+                    Expectation::Token(TokenKind::Pipe),
+                    Expectation::Token(TokenKind::ParenClose),
+                    Expectation::Token(TokenKind::Newline),
+                ],
+                None,
+                src(4, 1, 5),
+            ),
+            "expected one of `|`, `)`, or `\\n` at 1:5, found end of input",
+        ),
+        (UnexpectedEOF, "unexpected end of input"),
+        (
+            NestingTooDeep(src(64, 1, 65)),
+            "exceeded the maximum nesting depth at 1:65",
+        ),
+        (Custom(String::from("custom error")), "custom error"),
+    ];
+
+    for (err, msg) in cases {
+        assert_eq!(err.to_string(), msg);
+    }
+}
+
+#[test]
+fn test_parse_error_span() {
+    let cases = vec![
+        ("echo 1>&123foo", (src(8, 1, 9), src(14, 1, 15))),
+        ("{ echo foo", (src(0, 1, 1), src(1, 1, 2))),
+        ("echo foo; ;", (src(10, 1, 11), src(11, 1, 12))),
+    ];
+
+    for (s, span) in cases {
+        let err = make_parser(s)
+            .into_iter()
+            .collect::<Result<Vec<_>, _>>()
+            .unwrap_err();
+        assert_eq!(err.span(), Some(span), "source: {:?}, error: {:?}", s, err);
+        assert_eq!(err.primary_pos(), Some(span.0), "source: {:?}", s);
+    }
+
+    let err: ParseError<String> = ParseError::UnexpectedEOF;
+    assert_eq!(err.span(), None);
+    assert_eq!(err.primary_pos(), None);
+
+    let err = ParseError::Custom(String::from("custom error"));
+    assert_eq!(err.span(), None);
+    assert_eq!(err.primary_pos(), None);
+}
+
+#[test]
+fn test_source_pos_advance_counts_columns_in_chars() {
+    let mut pos = SourcePos::new();
+    pos.advance(&Token::Literal(String::from("café")));
+    assert_eq!(pos, src(5, 1, 5));
+
+    pos.advance(&Token::Whitespace(String::from(" ")));
+    pos.advance(&Token::Literal(String::from("→→")));
+    assert_eq!(pos, src(12, 1, 8));
+
+    pos.advance(&Token::Semi);
+    assert_eq!(pos, src(13, 1, 9));
+
+    pos.advance(&Token::Newline);
+    assert_eq!(pos, src(14, 2, 1));
+}
+
+#[test]
+fn test_source_pos_ordered_by_byte_offset_first() {
+    assert!(src(0, 1, 1) < src(1, 1, 2));
+    assert!(src(10, 1, 11) > src(9, 2, 1));
+    assert!(src(4, 3, 1) < src(5, 1, 1));
+
+    // Lines and columns only break ties between equal byte offsets
+    assert!(src(4, 1, 5) < src(4, 2, 1));
+    assert_eq!(src(4, 2, 1).cmp(&src(4, 2, 1)), std::cmp::Ordering::Equal);
+
+    let mut positions = vec![src(7, 2, 3), src(0, 1, 1), src(3, 1, 4)];
+    positions.sort();
+    assert_eq!(positions, vec![src(0, 1, 1), src(3, 1, 4), src(7, 2, 3)]);
+}
+
+#[test]
+fn test_positioned_tokens_reports_token_start_positions() {
+    let mut tokens = PositionedTokens::new(Lexer::new("echo é\n  $x;\n".chars()));
+
+    let correct = vec![
+        (Token::Name(String::from("echo")), src(0, 1, 1)),
+        (Token::Whitespace(String::from(" ")), src(4, 1, 5)),
+        (Token::Name(String::from("é")), src(5, 1, 6)),
+        (Token::Newline, src(7, 1, 7)),
+        (Token::Whitespace(String::from("  ")), src(8, 2, 1)),
+        (Token::Dollar, src(10, 2, 3)),
+        (Token::Name(String::from("x")), src(11, 2, 4)),
+        (Token::Semi, src(12, 2, 5)),
+        (Token::Newline, src(13, 2, 6)),
+    ];
+
+    assert_eq!(correct, tokens.by_ref().collect::<Vec<_>>());
+    assert_eq!(src(14, 3, 1), tokens.pos());
+    assert_eq!(None, tokens.next());
+}
+
+#[test]
+fn test_error_position_after_multi_byte_literal() {
+    let mut p = make_parser("echo é→ )");
+    p.complete_command().unwrap();
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::ParenClose, src(11, 1, 9))),
+        p.complete_command()
+    );
+}
+
+#[test]
+fn test_byte_only_positions_keep_accurate_byte_offsets() {
+    let byte_only = |byte| SourcePos {
+        byte,
+        line: 0,
+        col: 0,
+    };
+
+    let cfg = ParserConfig {
+        track_line_col: false,
+        ..ParserConfig::default()
+    };
+    let source = "echo é→\ncat <<-EOF\n\tfoo\n\tEOF\nfoo &&\n )";
+    let mut p = make_parser_with_config(source, cfg.clone());
+    assert_eq!(byte_only(0), p.pos());
+
+    p.complete_command().unwrap();
+    p.complete_command().unwrap();
+    let err = p.complete_command().unwrap_err();
+    assert_eq!(
+        ParseError::Unexpected(Token::ParenClose, byte_only(40)),
+        err
+    );
+    assert_eq!("found unexpected token at byte 40: )", err.to_string());
+
+    // Byte offsets should match those tracked along with lines and columns
+    let mut p = make_parser(source);
+    p.complete_command().unwrap();
+    p.complete_command().unwrap();
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::ParenClose, src(40, 6, 2))),
+        p.complete_command()
+    );
+
+    // Resetting the parser should not start tracking lines and columns again
+    let mut p = make_parser_with_config(source, cfg);
+    p.reset(Lexer::new("foo\n)".chars()));
+    assert_eq!(byte_only(0), p.pos());
+    p.complete_command().unwrap();
+    assert_eq!(
+        Err(ParseError::Unexpected(Token::ParenClose, byte_only(4))),
+        p.complete_command()
+    );
+}
+
+#[test]
+fn test_shebang_only_captured_at_start_of_source() {
+    let mut p = make_parser("#!/bin/bash -e\r\n# comment\nfoo");
+    assert_eq!(Some(String::from("/bin/bash -e")), p.shebang());
+    assert_eq!(None, p.shebang());
+    assert_eq!(
+        vec![Newline(Some(String::from("# comment")))],
+        p.linebreak()
+    );
+    assert_eq!(Some(cmd("foo")), p.complete_command().unwrap());
+
+    let mut p = make_parser("\n#!/bin/sh\n");
+    assert_eq!(None, p.shebang());
+    assert_eq!(
+        vec![Newline(None), Newline(Some(String::from("#!/bin/sh")))],
+        p.linebreak()
+    );
+
+    for src in &[" #!/bin/sh", "# !/bin/sh", "foo"] {
+        let mut p = make_parser(src);
+        assert_eq!(None, p.shebang());
+        assert_eq!(SourcePos::new(), p.pos());
+    }
+}
+
+#[test]
+fn test_builder_errors_report_position_of_rejected_command() {
+    use conch_parser::ast::RedirectOrCmdWord;
+
+    /// Rejects any simple command whose name is `bad`.
+    struct RejectBad;
+
+    impl BuilderHooks for RejectBad {
+        fn simple_command(
+            &mut self,
+            _redirects_or_env_vars: &[DefaultRedirectOrEnvVar],
+            redirects_or_cmd_words: &[DefaultRedirectOrCmdWord],
+        ) -> Result<(), String> {
+            match redirects_or_cmd_words.first() {
+                Some(RedirectOrCmdWord::CmdWord(w)) if *w == word("bad") => {
+                    Err(String::from("rejected"))
+                }
+                _ => Ok(()),
+            }
+        }
+    }
+
+    let source = "foo\nif x; then\n  bad arg\nfi";
+    let mut p = Parser::with_builder(Lexer::new(source.chars()), HookedBuilder::new(RejectBad));
+    assert_eq!(Some(cmd("foo")), p.complete_command().unwrap());
+
+    let err = p.complete_command().unwrap_err();
+    assert_eq!(
+        ParseError::CustomAt(String::from("rejected"), src(17, 3, 3)),
+        err
+    );
+    assert_eq!(Some(src(17, 3, 3)), err.primary_pos());
+    assert_eq!("rejected at 3:3", err.to_string());
+}
+
+#[test]
+fn test_cloned_parser_is_unaffected_by_original() {
+    let mut p = make_parser("foo; bar $(baz)\nqux");
+    assert_eq!(Some(cmd("foo")), p.complete_command().unwrap());
+
+    // The parser has peeked past the separator, so any buffered tokens are copied as well
+    let mut copy = p.clone();
+
+    p.complete_command().unwrap();
+    assert_eq!(Some(cmd("qux")), p.complete_command().unwrap());
+    assert_eq!(None, p.complete_command().unwrap());
+
+    assert_eq!(src(5, 1, 6), copy.pos());
+    let bar = make_parser("bar $(baz)").complete_command().unwrap();
+    assert_eq!(bar, copy.complete_command().unwrap());
+    assert_eq!(Some(cmd("qux")), copy.complete_command().unwrap());
+    assert_eq!(None, copy.complete_command().unwrap());
+}
+
+#[test]
+fn test_parser_from_lazily_decoded_reader_matches_str() {
+    let src = "for f in *.txt; do\n  echo \"caf\u{e9}: $f\" >> \u{2603}.log\ndone\ncat <<EOF\n\u{1F600}\nEOF\n";
+
+    // A one byte buffer splits every multi-byte character across reads
+    let reader = std::io::BufReader::with_capacity(1, src.as_bytes());
+    let chars = Utf8Chars::new(reader).map(Result::unwrap);
+
+    let from_reader = DefaultParser::new(Lexer::new(chars))
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let from_str = make_parser(src)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    assert_eq!(from_str, from_reader);
+    assert_eq!(2, from_reader.len());
+}
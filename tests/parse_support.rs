@@ -3,6 +3,11 @@
 // see our intent
 #![allow(dead_code)]
 
+use conch_parser::ast::builder::{
+    ArithForFragments, Builder, CaseFragments, CommandGroup, ComplexWordKind, ForFragments,
+    FunctionDeclarationKind, GuardBodyPairGroup, IfFragments, LoopKind, Newline, RedirectKind,
+    SeparatorKind, StringBuilder,
+};
 use conch_parser::ast::Command::*;
 use conch_parser::ast::ComplexWord::*;
 use conch_parser::ast::PipeableCommand::*;
@@ -52,8 +57,15 @@ pub fn make_parser(src: &str) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
     DefaultParser::new(Lexer::new(src.chars()))
 }
 
+pub fn make_parser_with_config(
+    src: &str,
+    cfg: ParserConfig,
+) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    DefaultParser::with_config(Lexer::new(src.chars()), Default::default(), cfg)
+}
+
 pub fn make_parser_from_tokens(src: Vec<Token>) -> DefaultParser<std::vec::IntoIter<Token>> {
-    DefaultParser::new(src.into_iter())
+    DefaultParser::new(src)
 }
 
 pub fn cmd_args_simple(cmd: &str, args: &[&str]) -> Box<DefaultSimpleCommand> {
@@ -92,3 +104,229 @@ pub fn cmd_from_simple(cmd: DefaultSimpleCommand) -> TopLevelCommand<String> {
 pub fn src(byte: usize, line: usize, col: usize) -> SourcePos {
     SourcePos { byte, line, col }
 }
+
+pub type DefaultRedirectOrEnvVar = RedirectOrEnvVar<DefaultRedirect, String, TopLevelWord<String>>;
+pub type DefaultRedirectOrCmdWord =
+    RedirectOrCmdWord<DefaultRedirect, String, TopLevelWord<String>>;
+
+/// Hooks which are handed the arguments of some of the `Builder` methods of a
+/// `HookedBuilder`, before the call is delegated to a `StringBuilder`.
+/// Returning an error rejects the call. By default every hook accepts anything.
+pub trait BuilderHooks {
+    fn complete_command(
+        &mut self,
+        _pre_cmd_comments: &[Newline],
+        _separator: SeparatorKind,
+        _cmd_comment: Option<&Newline>,
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn pipeline(
+        &mut self,
+        _bang: bool,
+        _cmds: &[(Vec<Newline>, DefaultPipeableCommand)],
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn simple_command(
+        &mut self,
+        _redirects_or_env_vars: &[DefaultRedirectOrEnvVar],
+        _redirects_or_cmd_words: &[DefaultRedirectOrCmdWord],
+    ) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn comments(&mut self, _comments: &[Newline]) -> Result<(), String> {
+        Ok(())
+    }
+
+    fn arithmetic(&mut self, _expr: &DefaultArithmetic) -> Result<(), String> {
+        Ok(())
+    }
+}
+
+/// A `Builder` which delegates every method to a `StringBuilder`,
+/// after giving its hooks a chance to observe or reject the call.
+#[derive(Default)]
+pub struct HookedBuilder<H> {
+    inner: StringBuilder,
+    pub hooks: H,
+}
+
+impl<H> HookedBuilder<H> {
+    pub fn new(hooks: H) -> Self {
+        HookedBuilder {
+            inner: StringBuilder::new(),
+            hooks,
+        }
+    }
+}
+
+fn infallible<T>(result: Result<T, void::Void>) -> Result<T, String> {
+    Ok(result.unwrap_or_else(|void| match void {}))
+}
+
+impl<H: BuilderHooks> Builder for HookedBuilder<H> {
+    type Command = <StringBuilder as Builder>::Command;
+    type CommandList = <StringBuilder as Builder>::CommandList;
+    type ListableCommand = <StringBuilder as Builder>::ListableCommand;
+    type PipeableCommand = <StringBuilder as Builder>::PipeableCommand;
+    type CompoundCommand = <StringBuilder as Builder>::CompoundCommand;
+    type Word = <StringBuilder as Builder>::Word;
+    type Redirect = <StringBuilder as Builder>::Redirect;
+    type Arithmetic = <StringBuilder as Builder>::Arithmetic;
+    type Error = String;
+
+    fn complete_command(
+        &mut self,
+        pre_cmd_comments: Vec<Newline>,
+        list: Self::CommandList,
+        separator: SeparatorKind,
+        cmd_comment: Option<Newline>,
+    ) -> Result<Self::Command, Self::Error> {
+        self.hooks
+            .complete_command(&pre_cmd_comments, separator, cmd_comment.as_ref())?;
+        infallible(
+            self.inner
+                .complete_command(pre_cmd_comments, list, separator, cmd_comment),
+        )
+    }
+
+    fn and_or_list(
+        &mut self,
+        first: Self::ListableCommand,
+        rest: Vec<(Vec<Newline>, AndOr<Self::ListableCommand>)>,
+    ) -> Result<Self::CommandList, Self::Error> {
+        infallible(self.inner.and_or_list(first, rest))
+    }
+
+    fn pipeline(
+        &mut self,
+        bang: bool,
+        cmds: Vec<(Vec<Newline>, Self::PipeableCommand)>,
+    ) -> Result<Self::ListableCommand, Self::Error> {
+        self.hooks.pipeline(bang, &cmds)?;
+        infallible(self.inner.pipeline(bang, cmds))
+    }
+
+    fn simple_command(
+        &mut self,
+        redirects_or_env_vars: Vec<RedirectOrEnvVar<Self::Redirect, String, Self::Word>>,
+        redirects_or_cmd_words: Vec<RedirectOrCmdWord<Self::Redirect, String, Self::Word>>,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        self.hooks
+            .simple_command(&redirects_or_env_vars, &redirects_or_cmd_words)?;
+        infallible(
+            self.inner
+                .simple_command(redirects_or_env_vars, redirects_or_cmd_words),
+        )
+    }
+
+    fn brace_group(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.brace_group(cmds, redirects))
+    }
+
+    fn subshell(
+        &mut self,
+        cmds: CommandGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.subshell(cmds, redirects))
+    }
+
+    fn loop_command(
+        &mut self,
+        kind: LoopKind,
+        guard_body_pair: GuardBodyPairGroup<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.loop_command(kind, guard_body_pair, redirects))
+    }
+
+    fn if_command(
+        &mut self,
+        fragments: IfFragments<Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.if_command(fragments, redirects))
+    }
+
+    fn for_command(
+        &mut self,
+        fragments: ForFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.for_command(fragments, redirects))
+    }
+
+    fn arith_for_command(
+        &mut self,
+        fragments: ArithForFragments<Self::Arithmetic, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.arith_for_command(fragments, redirects))
+    }
+
+    fn case_command(
+        &mut self,
+        fragments: CaseFragments<Self::Word, Self::Command>,
+        redirects: Vec<Self::Redirect>,
+    ) -> Result<Self::CompoundCommand, Self::Error> {
+        infallible(self.inner.case_command(fragments, redirects))
+    }
+
+    fn compound_command_into_pipeable(
+        &mut self,
+        cmd: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        infallible(self.inner.compound_command_into_pipeable(cmd))
+    }
+
+    fn function_declaration(
+        &mut self,
+        kind: FunctionDeclarationKind,
+        name: String,
+        post_name_comments: Vec<Newline>,
+        body: Self::CompoundCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        infallible(
+            self.inner
+                .function_declaration(kind, name, post_name_comments, body),
+        )
+    }
+
+    fn coproc(
+        &mut self,
+        name: Option<String>,
+        body: Self::PipeableCommand,
+    ) -> Result<Self::PipeableCommand, Self::Error> {
+        infallible(self.inner.coproc(name, body))
+    }
+
+    fn comments(&mut self, comments: Vec<Newline>) -> Result<(), Self::Error> {
+        self.hooks.comments(&comments)?;
+        infallible(self.inner.comments(comments))
+    }
+
+    fn word(
+        &mut self,
+        kind: ComplexWordKind<Self::Command, Self::Arithmetic>,
+    ) -> Result<Self::Word, Self::Error> {
+        infallible(self.inner.word(kind))
+    }
+
+    fn arithmetic(&mut self, expr: DefaultArithmetic) -> Result<Self::Arithmetic, Self::Error> {
+        self.hooks.arithmetic(&expr)?;
+        infallible(self.inner.arithmetic(expr))
+    }
+
+    fn redirect(&mut self, kind: RedirectKind<Self::Word>) -> Result<Self::Redirect, Self::Error> {
+        infallible(self.inner.redirect(kind))
+    }
+}
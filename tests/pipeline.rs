@@ -1,7 +1,10 @@
 #![deny(rust_2018_idioms)]
+use conch_parser::ast::ComplexWord::*;
 use conch_parser::ast::PipeableCommand::*;
 use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
 use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{DefaultParser, ParserConfig};
 use conch_parser::token::Token;
 
 mod parse_support;
@@ -43,6 +46,137 @@ fn test_pipeline_valid_bangs_in_and_or() {
     assert_eq!(correct, p.and_or_list().unwrap());
 }
 
+#[test]
+fn test_pipeline_bang_scoped_to_pipeline_after_and_or_operator() {
+    let bar = SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("bar")),
+            RedirectOrCmdWord::Redirect(Redirect::Write(None, word("out"))),
+        ],
+    };
+    let correct = CommandList {
+        first: ListableCommand::Single(Simple(cmd_simple("foo"))),
+        rest: vec![AndOr::And(ListableCommand::Pipe(
+            true,
+            vec![Simple(Box::new(bar))],
+        ))],
+    };
+
+    for src in &[
+        "foo && ! bar >out",
+        "foo &&\n\n  ! bar > out",
+        "foo && # c\n! bar >out",
+    ] {
+        assert_eq!(correct, make_parser(src).and_or_list().unwrap(), "{}", src);
+    }
+
+    let correct = CommandList {
+        first: ListableCommand::Pipe(true, vec![Simple(cmd_simple("a")), Simple(cmd_simple("b"))]),
+        rest: vec![AndOr::And(ListableCommand::Pipe(
+            true,
+            vec![Simple(cmd_simple("c")), Simple(cmd_simple("d"))],
+        ))],
+    };
+    assert_eq!(
+        correct,
+        make_parser("! a | b && ! c | d").and_or_list().unwrap()
+    );
+}
+
+#[test]
+fn test_pipeline_valid_bang_with_redirect_on_first_command() {
+    let mut p = make_parser("! foo > out | bar");
+    let foo = SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("foo")),
+            RedirectOrCmdWord::Redirect(Redirect::Write(None, word("out"))),
+        ],
+    };
+    let correct =
+        ListableCommand::Pipe(true, vec![Simple(Box::new(foo)), Simple(cmd_simple("bar"))]);
+    assert_eq!(correct, p.pipeline().unwrap());
+}
+
+#[test]
+fn test_pipeline_redirects_attach_to_their_own_segment() {
+    let mut p = make_parser("a >o | b <i | c 2>e");
+    let segment = |name: &str, redirect: DefaultRedirect| {
+        Simple(Box::new(SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: vec![
+                RedirectOrCmdWord::CmdWord(word(name)),
+                RedirectOrCmdWord::Redirect(redirect),
+            ],
+        }))
+    };
+
+    let correct = ListableCommand::Pipe(
+        false,
+        vec![
+            segment("a", Redirect::Write(None, word("o"))),
+            segment("b", Redirect::Read(None, word("i"))),
+            segment("c", Redirect::Write(Some(2), word("e"))),
+        ],
+    );
+    assert_eq!(correct, p.pipeline().unwrap());
+}
+
+fn test_command(words: Vec<TopLevelWord<String>>) -> DefaultPipeableCommand {
+    Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: words.into_iter().map(RedirectOrCmdWord::CmdWord).collect(),
+    }))
+}
+
+#[test]
+fn test_pipeline_bang_negates_single_bracket_test() {
+    // `[` is a regular command (the `test` builtin) whose name and final
+    // argument are parsed like any other lone pattern bracket.
+    let square_open = TopLevelWord(Single(Word::Simple(SimpleWord::SquareOpen)));
+    let square_close = TopLevelWord(Single(Word::Simple(SimpleWord::SquareClose)));
+    let test = test_command(vec![
+        square_open.clone(),
+        word("-f"),
+        word("x"),
+        square_close.clone(),
+    ]);
+
+    assert_eq!(
+        ListableCommand::Single(test.clone()),
+        make_parser("[ -f x ]").pipeline().unwrap()
+    );
+    assert_eq!(
+        ListableCommand::Pipe(true, vec![test]),
+        make_parser("! [ -f x ]").pipeline().unwrap()
+    );
+}
+
+#[test]
+fn test_pipeline_bang_within_double_bracket_test_is_an_argument() {
+    let double_open = TopLevelWord(Concat(vec![
+        Word::Simple(SimpleWord::SquareOpen),
+        Word::Simple(SimpleWord::SquareOpen),
+    ]));
+    let double_close = TopLevelWord(Concat(vec![
+        Word::Simple(SimpleWord::SquareClose),
+        Word::Simple(SimpleWord::SquareClose),
+    ]));
+    let test = test_command(vec![
+        double_open,
+        word("!"),
+        word("-f"),
+        word("x"),
+        double_close,
+    ]);
+
+    assert_eq!(
+        ListableCommand::Single(test),
+        make_parser("[[ ! -f x ]]").pipeline().unwrap()
+    );
+}
+
 #[test]
 fn test_pipeline_no_bang_single_cmd_optimize_wrapper_out() {
     let mut p = make_parser("foo");
@@ -57,3 +191,116 @@ fn test_pipeline_invalid_multiple_bangs_in_same_pipeline() {
     let mut p = make_parser("! foo | bar | ! baz");
     assert_eq!(Err(Unexpected(Token::Bang, src(14, 1, 15))), p.pipeline());
 }
+
+#[test]
+fn test_pipeline_valid_bang_before_compound_commands() {
+    use conch_parser::ast::CompoundCommandKind::*;
+
+    let negated = |kind| {
+        ListableCommand::Pipe(
+            true,
+            vec![Compound(Box::new(CompoundCommand { kind, io: vec![] }))],
+        )
+    };
+
+    let cases = vec![
+        ("! { false; }", negated(Brace(vec![cmd("false")]))),
+        (
+            "! (exit 1)",
+            negated(Subshell(vec![cmd_args("exit", &["1"])])),
+        ),
+        (
+            "! if true; then false; fi",
+            negated(If {
+                conditionals: vec![GuardBodyPair {
+                    guard: vec![cmd("true")],
+                    body: vec![cmd("false")],
+                }],
+                else_branch: None,
+            }),
+        ),
+    ];
+
+    for (src, correct) in cases {
+        assert_eq!(correct, make_parser(src).pipeline().unwrap(), "{}", src);
+    }
+}
+
+#[test]
+fn test_pipeline_time_is_parsed_as_a_regular_command() {
+    // `time` is not a reserved word here, so it never wraps the pipeline
+    // and never consumes a following `!`
+    let time = |args: &[&str]| Simple(cmd_args_simple("time", args));
+
+    let cases = vec![
+        ("time foo", ListableCommand::Single(time(&["foo"]))),
+        ("time ! foo", ListableCommand::Single(time(&["!", "foo"]))),
+        (
+            "! time foo",
+            ListableCommand::Pipe(true, vec![time(&["foo"])]),
+        ),
+        (
+            "time foo | bar",
+            ListableCommand::Pipe(false, vec![time(&["foo"]), Simple(cmd_simple("bar"))]),
+        ),
+    ];
+
+    for (src, correct) in cases {
+        assert_eq!(
+            correct,
+            make_parser(src).pipeline().unwrap(),
+            "source: {}",
+            src
+        );
+    }
+}
+
+fn make_caret_parser(src: &str) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    let cfg = ParserConfig {
+        caret_is_pipe: true,
+        ..ParserConfig::default()
+    };
+    make_parser_with_config(src, cfg)
+}
+
+#[test]
+fn test_pipeline_caret_is_pipe_when_enabled() {
+    let cases = vec!["a | b", "a ^ b", "a ^\nb", "a >out ^ b", "{ a; } ^ b >out"];
+    for src in cases {
+        assert_eq!(
+            make_parser(&src.replace('^', "|")).pipeline().unwrap(),
+            make_caret_parser(src).pipeline().unwrap(),
+            "source: {}",
+            src
+        );
+    }
+
+    // Carets within a word remain literals
+    let correct = ListableCommand::Single(Simple(cmd_args_simple("a^", &["b^c", "^^", "^d"])));
+    assert_eq!(
+        correct,
+        make_caret_parser("a^ b^c ^^ ^d").pipeline().unwrap()
+    );
+
+    let correct = ListableCommand::Single(Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(word_subst(ParameterSubstitution::Default(
+                true,
+                Parameter::Var("x".to_owned()),
+                Some(word("^")),
+            ))),
+        ],
+    })));
+    assert_eq!(
+        correct,
+        make_caret_parser("echo ${x:-^}").pipeline().unwrap()
+    );
+}
+
+#[test]
+fn test_pipeline_caret_is_literal_by_default() {
+    let correct = ListableCommand::Single(Simple(cmd_args_simple("a", &["^", "b"])));
+    assert_eq!(correct, make_parser("a ^ b").pipeline().unwrap());
+}
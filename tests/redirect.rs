@@ -4,6 +4,7 @@ use conch_parser::ast::PipeableCommand::*;
 use conch_parser::ast::SimpleWord::*;
 use conch_parser::ast::*;
 use conch_parser::parse::ParseError::*;
+use conch_parser::parse::ParserConfig;
 use conch_parser::token::Token;
 
 mod parse_support;
@@ -120,6 +121,36 @@ fn test_redirect_valid_dup_return_redirect_if_dst_fd_is_possibly_numeric() {
     assert_eq!(Some(Ok(correct)), p.redirect().unwrap());
 }
 
+#[test]
+fn test_redirect_valid_target_can_be_substitution_or_parameter() {
+    let correct = Redirect::Write(
+        None,
+        word_subst(ParameterSubstitution::Command(vec![cmd("mktemp")])),
+    );
+    assert_eq!(
+        Some(Ok(correct)),
+        make_parser("> $(mktemp)").redirect().unwrap()
+    );
+
+    let correct = Redirect::Append(None, word_param(Parameter::Var(String::from("log"))));
+    assert_eq!(
+        Some(Ok(correct)),
+        make_parser(">> ${log}").redirect().unwrap()
+    );
+
+    let correct = Redirect::Read(
+        None,
+        TopLevelWord(Single(Word::DoubleQuoted(vec![
+            Param(Parameter::Var(String::from("dir"))),
+            Literal(String::from("/file")),
+        ]))),
+    );
+    assert_eq!(
+        Some(Ok(correct)),
+        make_parser("< \"$dir/file\"").redirect().unwrap()
+    );
+}
+
 #[test]
 fn test_redirect_invalid_close_without_whitespace() {
     assert_eq!(
@@ -136,6 +167,43 @@ fn test_redirect_invalid_close_with_whitespace() {
     );
 }
 
+#[test]
+fn test_redirect_invalid_src_fd_out_of_range() {
+    assert_eq!(
+        Err(BadFd(src(0, 1, 1), src(5, 1, 6))),
+        make_parser("99999>out").redirect()
+    );
+    assert_eq!(
+        Err(BadFd(src(0, 1, 1), src(5, 1, 6))),
+        make_parser("65536<<EOF\nbody\nEOF").redirect()
+    );
+    assert_eq!(
+        Err(BadFd(src(5, 1, 6), src(10, 1, 11))),
+        make_parser("echo 99999>out").complete_command()
+    );
+}
+
+#[test]
+fn test_redirect_valid_src_fd_in_range() {
+    let correct = Redirect::Write(Some(9), word("out"));
+    assert_eq!(Some(Ok(correct)), make_parser("9>out").redirect().unwrap());
+
+    let correct = Redirect::Write(Some(0), word("out"));
+    assert_eq!(Some(Ok(correct)), make_parser("0>out").redirect().unwrap());
+
+    let correct = Redirect::Write(Some(65535), word("out"));
+    assert_eq!(
+        Some(Ok(correct)),
+        make_parser("65535>out").redirect().unwrap()
+    );
+}
+
+#[test]
+fn test_redirect_valid_return_word_if_large_number_not_followed_by_redirection() {
+    let mut p = make_parser("99999 >out");
+    assert_eq!(Some(Err(word("99999"))), p.redirect().unwrap());
+}
+
 #[test]
 fn test_redirect_fd_immediately_preceeding_redirection() {
     let mut p = make_parser("foo 1>>out");
@@ -161,6 +229,47 @@ fn test_redirect_fd_must_immediately_preceed_redirection() {
     assert_eq!(p.simple_command().unwrap(), correct);
 }
 
+#[test]
+fn test_redirect_strict_fds_reject_static_non_numeric_words() {
+    let cfg = ParserConfig {
+        strict_redirect_fds: true,
+        ..ParserConfig::default()
+    };
+    let strict = |src: &'static str| make_parser_with_config(src, cfg.clone());
+    let read_write = |fd| Some(Ok(Redirect::ReadWrite(fd, word("rw"))));
+
+    assert_eq!(
+        read_write(Some(3)),
+        make_parser("3<>rw").redirect().unwrap()
+    );
+    assert_eq!(read_write(Some(3)), strict("3<>rw").redirect().unwrap());
+
+    let mut p = make_parser("abc<>rw");
+    assert_eq!(Some(Err(word("abc"))), p.redirect().unwrap());
+    assert_eq!(read_write(None), p.redirect().unwrap());
+
+    assert_eq!(
+        Err(BadFd(src(0, 1, 1), src(3, 1, 4))),
+        strict("abc<>rw").redirect()
+    );
+    assert_eq!(
+        Err(BadFd(src(0, 1, 1), src(6, 1, 7))),
+        strict("'3'a\\4<>rw").redirect()
+    );
+    assert_eq!(
+        Err(BadFd(src(5, 1, 6), src(8, 1, 9))),
+        strict("echo abc<>rw").complete_command()
+    );
+
+    // Words which are separated from the redirect or can't be checked until
+    // they are expanded are kept as separate words
+    for src in &["abc <>rw", "$x<>rw"] {
+        let mut p = strict(src);
+        assert!(matches!(p.redirect(), Ok(Some(Err(_)))), "{}", src);
+        assert_eq!(read_write(None), p.redirect().unwrap(), "{}", src);
+    }
+}
+
 #[test]
 fn test_redirect_valid_dup_with_fd() {
     let mut p = make_parser("foo 1>&2");
@@ -196,6 +305,52 @@ fn test_redirect_valid_dup_with_whitespace() {
     );
 }
 
+#[test]
+fn test_redirect_valid_move_with_trailing_dash() {
+    let mut p = make_parser("exec 3>&1-");
+    assert_eq!(
+        p.simple_command().unwrap(),
+        simple_command_with_redirect("exec", Redirect::MoveWrite(Some(3), word("1")))
+    );
+
+    assert_eq!(
+        Some(Ok(Redirect::MoveRead(None, word("4")))),
+        make_parser("<&4-").redirect().unwrap()
+    );
+
+    let correct = Redirect::MoveWrite(
+        None,
+        TopLevelWord(Single(Word::Simple(Param(Parameter::Var(String::from(
+            "fd",
+        )))))),
+    );
+    assert_eq!(
+        Some(Ok(correct)),
+        make_parser(">& $fd-").redirect().unwrap()
+    );
+}
+
+#[test]
+fn test_redirect_valid_dup_followed_by_separate_dash_is_not_move() {
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("foo")),
+            RedirectOrCmdWord::Redirect(Redirect::DupWrite(None, word("2"))),
+            RedirectOrCmdWord::CmdWord(word("-")),
+        ],
+    }));
+    assert_eq!(correct, make_parser("foo >&2 -").simple_command().unwrap());
+}
+
+#[test]
+fn test_redirect_invalid_move_with_non_numeric_fd() {
+    assert_eq!(
+        Err(BadFd(src(2, 1, 3), src(5, 1, 6))),
+        make_parser(">&1--").redirect()
+    );
+}
+
 #[test]
 fn test_redirect_valid_single_quoted_dup_fd() {
     let correct = Redirect::DupWrite(Some(1), single_quoted("2"));
@@ -285,3 +440,107 @@ fn test_redirect_list_rejects_non_fd_words() {
         make_parser("1>>out abc <in 2>&-").redirect_list()
     );
 }
+
+#[test]
+fn test_redirect_invalid_process_substitution_target() {
+    // Process substitutions are not supported, so their leading `<` or `>`
+    // is seen as a redirection without a target.
+    assert_eq!(
+        Err(Unexpected(Token::Great, src(8, 1, 9))),
+        make_parser("exec 3> >(cat)").complete_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Great, src(6, 1, 7))),
+        make_parser("cat > >(cat)").complete_command()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Less, src(6, 1, 7))),
+        make_parser("cat < <(echo hi)").complete_command()
+    );
+}
+
+#[test]
+fn test_redirect_amp_great_is_a_job_followed_by_a_redirect() {
+    // Unless the bash `&>` and `&>|` redirections are enabled, the `&`
+    // backgrounds the command before it, as in POSIX.
+    let job = TopLevelCommand(Command::Job(CommandList {
+        first: ListableCommand::Single(Simple(cmd_args_simple("echo", &["x"]))),
+        rest: vec![],
+    }));
+
+    let mut p = make_parser("echo x &>| out");
+    assert_eq!(Some(job.clone()), p.complete_command().unwrap());
+    let correct = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::Redirect(Redirect::Clobber(
+            None,
+            word("out"),
+        ))],
+        redirects_or_cmd_words: vec![],
+    });
+    assert_eq!(Some(correct), p.complete_command().unwrap());
+    assert_eq!(None, p.complete_command().unwrap());
+
+    let mut p = make_parser("echo x &> | cat");
+    assert_eq!(Some(job), p.complete_command().unwrap());
+    assert_eq!(
+        Err(Unexpected(Token::Pipe, src(10, 1, 11))),
+        p.complete_command()
+    );
+}
+
+#[test]
+fn test_redirect_ampersand_redirects_both_streams_if_enabled() {
+    let cfg = ParserConfig {
+        ampersand_redirects: true,
+        ..ParserConfig::default()
+    };
+    let parser = |src: &'static str| make_parser_with_config(src, cfg.clone());
+
+    let cases = vec![
+        ("echo &> out", Redirect::WriteBoth(word("out"))),
+        ("echo &>>out", Redirect::AppendBoth(word("out"))),
+        ("echo &>| out", Redirect::ClobberBoth(word("out"))),
+    ];
+
+    for (s, redirect) in cases {
+        let correct = TopLevelCommand(Command::List(CommandList {
+            first: ListableCommand::Single(simple_command_with_redirect("echo", redirect)),
+            rest: vec![],
+        }));
+        assert_eq!(Some(correct), parser(s).complete_command().unwrap());
+    }
+
+    // A source descriptor is never taken, thus it remains an argument
+    let correct = cmd_from_simple(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("echo")),
+            RedirectOrCmdWord::CmdWord(word("2")),
+            RedirectOrCmdWord::Redirect(Redirect::WriteBoth(word("out"))),
+        ],
+    });
+    assert_eq!(
+        Some(correct),
+        parser("echo 2&>out").complete_command().unwrap()
+    );
+
+    // With a space, `&> |` is a `&>` redirection followed by a pipe
+    let correct = TopLevelCommand(Command::List(CommandList {
+        first: ListableCommand::Pipe(
+            false,
+            vec![
+                simple_command_with_redirect("echo", Redirect::WriteBoth(word("out"))),
+                Simple(cmd_args_simple("cat", &[])),
+            ],
+        ),
+        rest: vec![],
+    }));
+    assert_eq!(
+        Some(correct),
+        parser("echo &> out | cat").complete_command().unwrap()
+    );
+    assert_eq!(
+        Err(Unexpected(Token::Pipe, src(8, 1, 9))),
+        parser("echo &> | cat").complete_command()
+    );
+}
@@ -2,6 +2,8 @@
 use conch_parser::ast::PipeableCommand::*;
 use conch_parser::ast::Redirect::*;
 use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::*;
 
 mod parse_support;
 use crate::parse_support::*;
@@ -44,6 +46,66 @@ fn test_simple_command_assignments_after_start_of_command_should_be_args() {
     assert_eq!(correct, p.simple_command().unwrap());
 }
 
+#[test]
+fn test_simple_command_assignment_values_mixing_quoting_styles() {
+    let value = TopLevelWord(ComplexWord::Concat(vec![
+        Word::DoubleQuoted(vec![SimpleWord::Literal(String::from("a"))]),
+        Word::SingleQuoted(String::from("b")),
+        Word::Simple(SimpleWord::Param(Parameter::Var(String::from("c")))),
+    ]));
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar("x".to_owned(), Some(value))],
+        redirects_or_cmd_words: vec![RedirectOrCmdWord::CmdWord(word("foo"))],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("x=\"a\"'b'$c foo").simple_command().unwrap()
+    );
+
+    // Whitespace directly after the `=` leaves the value empty
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar("x".to_owned(), None)],
+        redirects_or_cmd_words: vec![RedirectOrCmdWord::CmdWord(word("foo"))],
+    }));
+    assert_eq!(correct, make_parser("x= foo").simple_command().unwrap());
+}
+
+#[test]
+fn test_simple_command_quoted_or_escaped_names_are_not_assignments() {
+    let quoted = TopLevelWord(ComplexWord::Concat(vec![
+        Word::DoubleQuoted(vec![SimpleWord::Literal(String::from("var"))]),
+        Word::Simple(SimpleWord::Literal(String::from("=val"))),
+    ]));
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(quoted),
+            RedirectOrCmdWord::CmdWord(word("foo")),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("\"var\"=val foo").simple_command().unwrap()
+    );
+
+    let escaped = TopLevelWord(ComplexWord::Concat(vec![
+        lit("var"),
+        escaped("="),
+        lit("val"),
+    ]));
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(escaped),
+            RedirectOrCmdWord::CmdWord(word("foo")),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("var\\=val foo").simple_command().unwrap()
+    );
+}
+
 #[test]
 fn test_simple_command_redirections_at_start_of_command() {
     let mut p = make_parser("2>|clob 3<>rw <in var=val ENV=true BLANK= foo bar baz");
@@ -108,3 +170,360 @@ fn test_simple_command_redirections_throughout_the_command() {
 
     assert_eq!(correct, p.simple_command().unwrap());
 }
+
+#[test]
+fn test_simple_command_redirect_between_assignments_keeps_scanning_assignments() {
+    let mut p = make_parser("a=1 2>err b=2 cmd arg");
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![
+            RedirectOrEnvVar::EnvVar("a".to_owned(), Some(word("1"))),
+            RedirectOrEnvVar::Redirect(Write(Some(2), word("err"))),
+            RedirectOrEnvVar::EnvVar("b".to_owned(), Some(word("2"))),
+        ],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("cmd")),
+            RedirectOrCmdWord::CmdWord(word("arg")),
+        ],
+    }));
+
+    assert_eq!(correct, p.simple_command().unwrap());
+}
+
+#[test]
+fn test_simple_command_assignment_builtin_arguments_are_assignments() {
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("export")),
+            RedirectOrCmdWord::Assignment("A".to_owned(), Some(word("1"))),
+            RedirectOrCmdWord::Assignment("B".to_owned(), Some(word("2"))),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("export A=1 B=2").simple_command().unwrap()
+    );
+
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar("X".to_owned(), None)],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("readonly")),
+            RedirectOrCmdWord::Declaration("FLAG".to_owned()),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+            RedirectOrCmdWord::Assignment("EMPTY".to_owned(), None),
+            RedirectOrCmdWord::CmdWord(word("-p")),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("X= readonly FLAG >out EMPTY= -p")
+            .simple_command()
+            .unwrap()
+    );
+}
+
+#[test]
+fn test_simple_command_assignment_builtin_names_without_values_are_declarations() {
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("export")),
+            RedirectOrCmdWord::Declaration("FOO".to_owned()),
+        ],
+    }));
+    assert_eq!(correct, make_parser("export FOO").simple_command().unwrap());
+
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("local")),
+            RedirectOrCmdWord::Declaration("a".to_owned()),
+            RedirectOrCmdWord::Assignment("b".to_owned(), Some(word("2"))),
+            RedirectOrCmdWord::Declaration("c".to_owned()),
+            RedirectOrCmdWord::Assignment("d".to_owned(), None),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("local a b=2 c d=").simple_command().unwrap()
+    );
+
+    // Only bare names are declarations, anything else is still a regular word
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("export")),
+            RedirectOrCmdWord::CmdWord(word("-n")),
+            RedirectOrCmdWord::CmdWord(TopLevelWord(ComplexWord::Concat(vec![
+                lit("a"),
+                Word::Simple(SimpleWord::Param(Parameter::Var("b".to_owned()))),
+            ]))),
+            RedirectOrCmdWord::Declaration("c".to_owned()),
+            RedirectOrCmdWord::Redirect(Write(None, word("out"))),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("export -n a$b c>out").simple_command().unwrap()
+    );
+
+    // Regular commands never have declarations
+    let correct = Simple(cmd_args_simple("echo", &["FOO"]));
+    assert_eq!(correct, make_parser("echo FOO").simple_command().unwrap());
+}
+
+#[test]
+fn test_simple_command_assignment_builtin_attribute_options() {
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("declare")),
+            RedirectOrCmdWord::Attributes(VarAttributes {
+                integer: true,
+                ..VarAttributes::default()
+            }),
+            RedirectOrCmdWord::Assignment("x".to_owned(), Some(word("5"))),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("declare -i x=5").simple_command().unwrap()
+    );
+
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("declare")),
+            RedirectOrCmdWord::Attributes(VarAttributes {
+                readonly: true,
+                export: true,
+                ..VarAttributes::default()
+            }),
+            RedirectOrCmdWord::Assignment("Y".to_owned(), Some(word("1"))),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("declare -rx Y=1").simple_command().unwrap()
+    );
+
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("local")),
+            RedirectOrCmdWord::Attributes(VarAttributes {
+                indexed_array: true,
+                ..VarAttributes::default()
+            }),
+            RedirectOrCmdWord::Declaration("arr".to_owned()),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("local -a arr").simple_command().unwrap()
+    );
+}
+
+#[test]
+fn test_simple_command_assignment_builtin_attribute_options_combine() {
+    let cmd = match make_parser("typeset -l -u -A -p m >out")
+        .simple_command()
+        .unwrap()
+    {
+        Simple(cmd) => cmd,
+        c => panic!("expected a simple command, found {:?}", c),
+    };
+
+    let correct = VarAttributes {
+        lowercase: true,
+        uppercase: true,
+        associative_array: true,
+        ..VarAttributes::default()
+    };
+    assert_eq!(correct, cmd.attributes());
+    // Options which are not attributes are left as regular words
+    assert_eq!(
+        RedirectOrCmdWord::CmdWord(word("-p")),
+        cmd.redirects_or_cmd_words[4]
+    );
+
+    // Options are only recognized before any other argument
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: vec![
+            RedirectOrCmdWord::CmdWord(word("declare")),
+            RedirectOrCmdWord::Declaration("x".to_owned()),
+            RedirectOrCmdWord::CmdWord(word("-i")),
+        ],
+    }));
+    assert_eq!(
+        correct,
+        make_parser("declare x -i").simple_command().unwrap()
+    );
+
+    // Unknown flags invalidate the whole option word
+    let correct = Simple(cmd_args_simple("declare", &["-iz"]));
+    assert_eq!(
+        correct,
+        make_parser("declare -iz").simple_command().unwrap()
+    );
+
+    // Regular commands never have attributes
+    let correct = Simple(cmd_args_simple("echo", &["-i"]));
+    assert_eq!(correct, make_parser("echo -i").simple_command().unwrap());
+
+    assert_eq!(None, VarAttributes::from_flags(""));
+}
+
+#[test]
+fn test_simple_command_unicode_names_are_configurable() {
+    use conch_parser::lexer::NameChars;
+
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar("café".to_owned(), Some(word("1")))],
+        redirects_or_cmd_words: vec![],
+    }));
+    let lex = Lexer::with_name_chars("café=1".chars(), NameChars::Unicode);
+    assert_eq!(correct, DefaultParser::new(lex).simple_command().unwrap());
+    assert_eq!(correct, make_parser("café=1").simple_command().unwrap());
+
+    let correct = Simple(cmd_simple("café=1"));
+    let lex = Lexer::with_name_chars("café=1".chars(), NameChars::Posix);
+    assert_eq!(correct, DefaultParser::new(lex).simple_command().unwrap());
+}
+
+#[test]
+fn test_simple_command_non_assignment_builtin_arguments_are_words() {
+    let correct = Simple(cmd_args_simple("printf", &["A=1"]));
+    assert_eq!(correct, make_parser("printf A=1").simple_command().unwrap());
+
+    let correct = Simple(cmd_args_simple("exported", &["A=1"]));
+    assert_eq!(
+        correct,
+        make_parser("exported A=1").simple_command().unwrap()
+    );
+
+    let mut cfg = ParserConfig::default();
+    cfg.assignment_builtins.clear();
+    let mut p = make_parser_with_config("export A=1", cfg);
+    let correct = Simple(cmd_args_simple("export", &["A=1"]));
+    assert_eq!(correct, p.simple_command().unwrap());
+}
+
+#[test]
+fn test_simple_command_assignment_and_redirect_only_classification() {
+    let cases = vec![
+        ("var=1", true, false),
+        ("var=1 other=", true, false),
+        (">out", false, true),
+        (">out 2>&1", false, true),
+        ("var=1 >out", false, false),
+        ("echo", false, false),
+        ("var=1 echo", false, false),
+        ("echo >out", false, false),
+    ];
+
+    for (src, assignment_only, redirect_only) in cases {
+        let cmd = match make_parser(src).simple_command() {
+            Ok(Simple(cmd)) => cmd,
+            other => panic!("failed to parse {:?} as simple command: {:?}", src, other),
+        };
+
+        assert_eq!(
+            assignment_only,
+            cmd.is_assignment_only(),
+            "source: {:?}",
+            src
+        );
+        assert_eq!(redirect_only, cmd.is_redirect_only(), "source: {:?}", src);
+    }
+}
+
+#[test]
+fn test_simple_command_assignment_tilde_expansion_only_at_start_or_after_colon() {
+    use conch_parser::ast::ComplexWord::*;
+    use conch_parser::ast::SimpleWord::*;
+
+    fn assign(value: Vec<DefaultSimpleWord>) -> DefaultSimpleCommand {
+        let value = value.into_iter().map(Word::Simple).collect();
+        SimpleCommand {
+            redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar(
+                String::from("p"),
+                Some(TopLevelWord(Concat(value))),
+            )],
+            redirects_or_cmd_words: vec![],
+        }
+    }
+
+    fn lit(s: &str) -> DefaultSimpleWord {
+        Literal(String::from(s))
+    }
+
+    let cases = vec![
+        ("p=~/bin", assign(vec![Tilde, lit("/bin")])),
+        (
+            "p=~/a:~/b",
+            assign(vec![Tilde, lit("/a"), Colon, Tilde, lit("/b")]),
+        ),
+        ("p=a:b~", assign(vec![lit("a"), Colon, lit("b~")])),
+        ("p=a:b", assign(vec![lit("a"), Colon, lit("b")])),
+    ];
+
+    for (src, correct) in cases {
+        assert_eq!(
+            Simple(Box::new(correct)),
+            make_parser(src).simple_command().unwrap(),
+            "source: {:?}",
+            src
+        );
+    }
+
+    // A literal `~` is merged with its surrounding literals
+    let correct = SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar(
+            String::from("p"),
+            Some(word("a~b")),
+        )],
+        redirects_or_cmd_words: vec![],
+    };
+    assert_eq!(
+        Simple(Box::new(correct)),
+        make_parser("p=a~b").simple_command().unwrap()
+    );
+}
+
+#[test]
+fn test_simple_command_colons_only_structured_in_assignments() {
+    let correct = SimpleCommand {
+        redirects_or_env_vars: vec![RedirectOrEnvVar::EnvVar(
+            String::from("PATH"),
+            Some(TopLevelWord(ComplexWord::Single(Word::Simple(
+                SimpleWord::Colon,
+            )))),
+        )],
+        redirects_or_cmd_words: vec![RedirectOrCmdWord::CmdWord(word("a:b"))],
+    };
+    assert_eq!(
+        Simple(Box::new(correct)),
+        make_parser("PATH=: a:b").simple_command().unwrap()
+    );
+}
+
+#[test]
+fn test_simple_command_double_brackets_are_regular_words() {
+    // `[[ ]]` is not yet recognized as a conditional command, so its operands
+    // are regular words and a quoted `]]` remains part of its own word.
+    let words = ["[[", "\"a]]b\"", "==", "*", "]]"];
+    let correct = Simple(Box::new(SimpleCommand {
+        redirects_or_env_vars: vec![],
+        redirects_or_cmd_words: words
+            .iter()
+            .map(|w| RedirectOrCmdWord::CmdWord(make_parser(w).word().unwrap().unwrap()))
+            .collect(),
+    }));
+
+    assert_eq!(
+        correct,
+        make_parser("[[ \"a]]b\" == * ]]").simple_command().unwrap()
+    );
+}
@@ -34,6 +34,33 @@ fn test_subshell_valid_separator_not_needed() {
     );
 }
 
+#[test]
+fn test_subshell_trailing_amp_before_close_paren_is_a_job() {
+    use conch_parser::ast::Command::Job;
+    use conch_parser::ast::PipeableCommand::Simple;
+    use conch_parser::ast::{CommandList, ListableCommand, TopLevelCommand};
+
+    let job = |name| {
+        TopLevelCommand(Job(CommandList {
+            first: ListableCommand::Single(Simple(cmd_simple(name))),
+            rest: vec![],
+        }))
+    };
+
+    let correct = CommandGroup {
+        commands: vec![job("foo"), job("bar")],
+        trailing_comments: vec![],
+    };
+    assert_eq!(correct, make_parser("(foo & bar &)").subshell().unwrap());
+    assert_eq!(correct, make_parser("( foo& bar& )").subshell().unwrap());
+
+    let correct = CommandGroup {
+        commands: vec![job("foo")],
+        trailing_comments: vec![],
+    };
+    assert_eq!(correct, make_parser("(foo &)").subshell().unwrap());
+}
+
 #[test]
 fn test_subshell_space_between_parens_not_needed() {
     let mut p = make_parser("(foo )");
@@ -581,6 +581,48 @@ fn test_parameter_substitution_words_can_be_parameters_or_substitutions_as_well(
     }
 }
 
+#[test]
+fn test_parameter_substitution_words_can_be_nested_to_arbitrary_depth() {
+    let nested = |param: &str, inner| {
+        TopLevelWord(Single(subst(Default(
+            false,
+            Var(param.to_owned()),
+            Some(inner),
+        ))))
+    };
+
+    let correct = nested("a", nested("b", nested("c", nested("d", word("x")))));
+    let mut p = make_parser("${a-${b-${c-${d-x}}}}");
+    assert_eq!(correct, p.parameter().unwrap());
+    assert_eq!(Err(UnexpectedEOF), p.parameter()); // Stream should be exhausted
+
+    // Mixing operators at each level, with the innermost level being a bare parameter
+    let correct = word_subst(Alternative(
+        true,
+        Var(String::from("a")),
+        Some(word_subst(RemoveSmallestPrefix(
+            Var(String::from("b")),
+            Some(word_subst(RemoveLargestSuffix(
+                Var(String::from("c")),
+                Some(word_subst(Assign(
+                    true,
+                    Var(String::from("d")),
+                    Some(word_param(Var(String::from("e")))),
+                ))),
+            ))),
+        ))),
+    ));
+    let mut p = make_parser("${a:+${b#${c%%${d:=${e}}}}}");
+    assert_eq!(correct, p.parameter().unwrap());
+    assert_eq!(Err(UnexpectedEOF), p.parameter()); // Stream should be exhausted
+
+    // Many levels deep still produce the full tree
+    let depth = 16;
+    let src = format!("{}x{}", "${v-".repeat(depth), "}".repeat(depth));
+    let correct = (0..depth).fold(word("x"), |inner, _| nested("v", inner));
+    assert_eq!(correct, make_parser(&src).parameter().unwrap());
+}
+
 #[test]
 fn test_parameter_substitution_command_close_paren_need_not_be_followed_by_word_delimeter() {
     let correct = Some(cmd_from_simple(SimpleCommand {
@@ -752,3 +794,126 @@ fn test_parameter_substitution_special_tokens_in_words_become_literals() {
     let mut p = make_parser("${foo:-#(bar);&|&&||;; << >> <& >& <<- \\\n\n\t}");
     assert_eq!(Ok(correct), p.parameter());
 }
+
+#[test]
+fn test_parameter_substitution_transform() {
+    let cases = vec![
+        ("${x@Q}", Transform(Var(String::from("x")), 'Q')),
+        ("${x@P}", Transform(Var(String::from("x")), 'P')),
+        ("${foo_bar@a}", Transform(Var(String::from("foo_bar")), 'a')),
+        ("${1@E}", Transform(Positional(1), 'E')),
+        ("${@@Q}", Transform(At, 'Q')),
+    ];
+
+    for (s, correct) in cases {
+        assert_eq!(word_subst(correct), make_parser(s).parameter().unwrap());
+    }
+
+    // `${@}` is still the parameter holding all positional arguments
+    assert_eq!(
+        TopLevelWord(Single(Word::Simple(SimpleWord::Param(At)))),
+        make_parser("${@}").parameter().unwrap()
+    );
+
+    // The operator must be a single letter
+    assert_eq!(
+        Err(BadSubst(Token::Name(String::from("QQ")), src(4, 1, 5))),
+        make_parser("${x@QQ}").parameter()
+    );
+    assert_eq!(
+        Err(BadSubst(Token::CurlyClose, src(4, 1, 5))),
+        make_parser("${x@}").parameter()
+    );
+    assert_eq!(
+        Err(Unmatched(Token::CurlyOpen, src(1, 1, 2))),
+        make_parser("${x@").parameter()
+    );
+}
+
+#[test]
+fn test_parameter_substitution_read_file() {
+    let cases = vec![
+        ("$(< file)", word("file")),
+        ("$(<file)", word("file")),
+        ("$( < file )", word("file")),
+        ("$(< file\n)", word("file")),
+        ("$(<$var)", word_param(Var(String::from("var")))),
+        (
+            "$(< \"$dir/a b\")",
+            TopLevelWord(Single(Word::DoubleQuoted(vec![
+                SimpleWord::Param(Var(String::from("dir"))),
+                SimpleWord::Literal(String::from("/a b")),
+            ]))),
+        ),
+    ];
+
+    for (s, path) in cases {
+        let correct = word_subst(ReadFile(path));
+        assert_eq!(
+            correct,
+            make_parser(s).parameter().unwrap(),
+            "source: {}",
+            s
+        );
+    }
+
+    // Anything beyond a lone input redirection is a regular command substitution
+    let cases = vec![
+        "$(< file cat)",
+        "$(< a; echo)",
+        "$(< a > b)",
+        "$(< $(echo a))",
+        "$(< ${x:-)} cat)",
+    ];
+
+    for s in cases {
+        let mut p = make_parser(&s[2..s.len() - 1]);
+        let mut correct = vec![];
+        while let Some(cmd) = p.complete_command().unwrap() {
+            correct.push(cmd);
+        }
+
+        assert_eq!(
+            word_subst(Command(correct)),
+            make_parser(s).parameter().unwrap(),
+            "source: {}",
+            s
+        );
+    }
+
+    assert_eq!(
+        Err(Unmatched(Token::ParenOpen, src(1, 1, 2))),
+        make_parser("$(< file").parameter()
+    );
+}
+
+#[test]
+fn test_parameter_substitution_names_matching_prefix() {
+    let cases = vec![
+        (
+            "${!BASH_*}",
+            NamesMatchingPrefix(String::from("BASH_"), false),
+        ),
+        ("${!v@}", NamesMatchingPrefix(String::from("v"), true)),
+    ];
+
+    for (s, correct) in cases {
+        assert_eq!(word_subst(correct), make_parser(s).parameter().unwrap());
+    }
+
+    // `${!}` is still the parameter holding the last background job's pid
+    assert_eq!(
+        TopLevelWord(Single(Word::Simple(SimpleWord::Param(Bang)))),
+        make_parser("${!}").parameter().unwrap()
+    );
+
+    // Indirection is not supported, and only a trailing `*` or `@` denotes prefix matching
+    assert_eq!(
+        Err(BadSubst(Token::Name(String::from("var")), src(3, 1, 4))),
+        make_parser("${!var}").parameter()
+    );
+    assert_eq!(
+        Err(BadSubst(Token::Name(String::from("v")), src(3, 1, 4))),
+        make_parser("${!v*x}").parameter()
+    );
+}
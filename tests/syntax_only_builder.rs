@@ -0,0 +1,139 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::builder::{
+    ElidedArithmetic, ElidedWord, SyntaxOnlyBuilder, SyntaxOnlyCommand,
+};
+use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
+use conch_parser::parse::*;
+
+mod parse_support;
+use crate::parse_support::*;
+
+const SCRIPT: &str = r#"
+# a comment
+FOO=bar echo "hello $USER" > out.txt
+if [ -f "$file" ]; then cat "$file" | grep -v '^#'; else echo missing >&2; fi
+for f in *.rs; do wc -l "$f" && echo "${f%.rs}"; done
+while read line; do echo "$line"; done < input
+case "$1" in start) run &;; stop|halt) kill $(cat pid);; esac
+greet() { echo "hi $1"; }
+(cd /tmp; ls -la) || true
+cat <<EOF
+body $HOME
+EOF
+"#;
+
+fn script() -> String {
+    SCRIPT.repeat(50)
+}
+
+type Shape = (bool, usize, Vec<&'static str>);
+type ShapeCommand<N, S, C, F> = Command<AndOrList<ListableCommand<PipeableCommand<N, S, C, F>>>>;
+
+fn shapes<N, S, C, F>(cmds: &[ShapeCommand<N, S, C, F>]) -> Vec<Shape> {
+    cmds.iter()
+        .map(|cmd| {
+            let (is_job, list) = match *cmd {
+                Command::Job(ref list) => (true, list),
+                Command::List(ref list) => (false, list),
+            };
+
+            let listables =
+                std::iter::once(&list.first).chain(list.rest.iter().map(|ao| match *ao {
+                    AndOr::And(ref c) | AndOr::Or(ref c) => c,
+                }));
+
+            let kinds = listables
+                .flat_map(|listable| match *listable {
+                    ListableCommand::Single(ref p) => vec![p],
+                    ListableCommand::Pipe(_, ref ps) => ps.iter().collect(),
+                })
+                .map(|pipeable| match *pipeable {
+                    PipeableCommand::Simple(_) => "simple",
+                    PipeableCommand::Compound(_) => "compound",
+                    PipeableCommand::FunctionDef(..) => "function",
+                    PipeableCommand::Coproc { .. } => "coproc",
+                })
+                .collect();
+
+            (is_job, list.rest.len(), kinds)
+        })
+        .collect()
+}
+
+#[test]
+fn test_syntax_only_builder_matches_default_structure() {
+    let source = script();
+
+    let default_cmds = make_parser(&source)
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    let lex = Lexer::new(source.chars());
+    let syntax_cmds = Parser::with_builder(lex, SyntaxOnlyBuilder::<String>::new())
+        .into_iter()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+
+    assert_eq!(default_cmds.len(), 8 * 50);
+    assert_eq!(default_cmds.len(), syntax_cmds.len());
+
+    let default_cmds = default_cmds.into_iter().map(|c| c.0).collect::<Vec<_>>();
+    let syntax_cmds = syntax_cmds.into_iter().map(|c| c.0).collect::<Vec<_>>();
+    assert_eq!(shapes(&default_cmds), shapes(&syntax_cmds));
+}
+
+#[test]
+fn test_syntax_only_builder_elides_words() {
+    let lex = Lexer::new("echo foo $bar \"baz\" > out".chars());
+    let cmd = Parser::with_builder(lex, SyntaxOnlyBuilder::<String>::new())
+        .complete_command()
+        .unwrap()
+        .unwrap();
+
+    let correct = SyntaxOnlyCommand(Command::List(CommandList {
+        first: ListableCommand::Single(PipeableCommand::Simple(Box::new(SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: vec![
+                RedirectOrCmdWord::CmdWord(ElidedWord),
+                RedirectOrCmdWord::CmdWord(ElidedWord),
+                RedirectOrCmdWord::CmdWord(ElidedWord),
+                RedirectOrCmdWord::CmdWord(ElidedWord),
+                RedirectOrCmdWord::Redirect(Redirect::Write(None, ElidedWord)),
+            ],
+        }))),
+        rest: vec![],
+    }));
+
+    assert_eq!(correct, cmd);
+}
+
+#[test]
+fn test_syntax_only_builder_keeps_placeholders_for_arith_for_expressions() {
+    let lex = Lexer::new("for ((; x; i++)); do :; done".chars());
+    let cmd = Parser::with_builder(lex, SyntaxOnlyBuilder::<String>::new())
+        .complete_command()
+        .unwrap()
+        .unwrap();
+
+    match cmd.0 {
+        Command::List(CommandList {
+            first: ListableCommand::Single(PipeableCommand::Compound(ref compound)),
+            ..
+        }) => match compound.kind {
+            CompoundCommandKind::ArithFor {
+                ref init,
+                ref cond,
+                ref update,
+                ..
+            } => {
+                assert_eq!(None, *init);
+                assert_eq!(Some(ElidedArithmetic), *cond);
+                assert_eq!(Some(ElidedArithmetic), *update);
+            }
+            ref kind => panic!("unexpected compound command: {:#?}", kind),
+        },
+        ref cmd => panic!("unexpected command: {:#?}", cmd),
+    }
+}
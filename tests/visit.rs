@@ -0,0 +1,94 @@
+#![deny(rust_2018_idioms)]
+use conch_parser::ast::visit::{self, Visitor};
+use conch_parser::ast::*;
+
+mod parse_support;
+use crate::parse_support::*;
+
+#[derive(Default)]
+struct Counter {
+    simple_commands: usize,
+    vars: Vec<String>,
+}
+
+impl Visitor<String> for Counter {
+    fn visit_simple_command(&mut self, cmd: &DefaultSimpleCommand) {
+        self.simple_commands += 1;
+        visit::walk_simple_command(self, cmd);
+    }
+
+    fn visit_parameter(&mut self, param: &DefaultParameter) {
+        if let Parameter::Var(ref name) = *param {
+            self.vars.push(name.clone());
+        }
+    }
+}
+
+fn count(src: &str) -> Counter {
+    let mut counter = Counter::default();
+    for cmd in make_parser(src).into_iter() {
+        counter.visit_command(&cmd.unwrap());
+    }
+    counter
+}
+
+#[test]
+fn test_visitor_counts_simple_commands_and_vars_in_nested_script() {
+    let src = r#"
+        a=$x echo "$y" | cat >"$out" && { b=1 true; }
+        f() {
+            if test -n "${z:-$w}"; then
+                for i in $list; do echo $(printf %s "$i"); done
+            fi
+        }
+        case $c in $p) (exit $@) ;; esac
+        coproc while read l; do :; done
+    "#;
+
+    let counter = count(src);
+    assert_eq!(counter.simple_commands, 9);
+    assert_eq!(
+        counter.vars,
+        vec!["x", "y", "out", "z", "w", "list", "i", "c", "p"]
+    );
+}
+
+#[test]
+fn test_visitor_overrides_can_skip_children() {
+    struct TopLevelOnly(usize);
+
+    impl Visitor<String> for TopLevelOnly {
+        fn visit_simple_command(&mut self, _cmd: &DefaultSimpleCommand) {
+            self.0 += 1;
+        }
+
+        fn visit_compound_command(&mut self, _cmd: &DefaultCompoundCommand) {}
+    }
+
+    let mut visitor = TopLevelOnly(0);
+    let cmd = make_parser("echo $(foo; bar) && { baz; }")
+        .complete_command()
+        .unwrap()
+        .unwrap();
+    visitor.visit_command(&cmd);
+    assert_eq!(visitor.0, 1);
+}
+
+#[test]
+fn test_atomic_visitor_walks_arithmetic() {
+    struct ArithVars(Vec<String>);
+
+    impl visit::atomic::Visitor<String> for ArithVars {
+        fn visit_arithmetic(&mut self, expr: &DefaultArithmetic) {
+            if let Arithmetic::Var(ref name) = *expr {
+                self.0.push(name.clone());
+            }
+            visit::atomic::walk_arithmetic(self, expr);
+        }
+    }
+
+    let cmd: AtomicTopLevelCommand<String> = "echo $(( a + b * (c ? d : 1) ))".parse().unwrap();
+    let mut visitor = ArithVars(vec![]);
+    visit::atomic::Visitor::visit_command(&mut visitor, &cmd);
+    assert_eq!(visitor.0, vec!["a", "b", "c", "d"]);
+}
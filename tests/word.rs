@@ -2,7 +2,9 @@
 use conch_parser::ast::ComplexWord::*;
 use conch_parser::ast::SimpleWord::*;
 use conch_parser::ast::*;
+use conch_parser::lexer::Lexer;
 use conch_parser::parse::ParseError::*;
+use conch_parser::parse::{DefaultParser, ParserConfig, QuoteKind};
 use conch_parser::token::Token;
 
 mod parse_support;
@@ -29,14 +31,161 @@ fn test_word_single_quote_valid_does_not_quote_single_quotes() {
     assert_eq!(Some(correct), make_parser("'hello \\'").word().unwrap());
 }
 
+#[test]
+fn test_word_single_quote_valid_never_expands_substitutions() {
+    let sources = [
+        "$foo",
+        "$1",
+        "$@$*$#$?$-$$$!",
+        "${bar}",
+        "${#bar}",
+        "${bar:-default}",
+        "${bar%%suffix}",
+        "${!prefix*}",
+        "$(cmd arg)",
+        "$((1 + 2))",
+        "`backtick`",
+        "~user/*?[a]:",
+        "$foo ${bar} $(cmd) `backtick`",
+    ];
+
+    for &src in &sources {
+        let quoted = format!("'{}'", src);
+        let correct = single_quoted(src);
+        assert_eq!(
+            Some(correct),
+            make_parser(&quoted).word().unwrap(),
+            "failed to keep {} literal",
+            quoted
+        );
+    }
+}
+
 #[test]
 fn test_word_single_quote_invalid_missing_close_quote() {
     assert_eq!(
-        Err(Unmatched(Token::SingleQuote, src(0, 1, 1))),
+        Err(UnterminatedQuote {
+            kind: QuoteKind::Single,
+            open: src(0, 1, 1),
+            end: src(6, 1, 7),
+        }),
         make_parser("'hello").word()
     );
 }
 
+#[test]
+fn test_word_dollar_single_quote_invalid_missing_close_quote() {
+    assert_eq!(
+        Err(UnterminatedQuote {
+            kind: QuoteKind::DollarSingle,
+            open: src(0, 1, 1),
+            end: src(11, 2, 4),
+        }),
+        make_parser("$'hello\nabc").word()
+    );
+}
+
+fn make_ansi_c_parser(src: &str) -> DefaultParser<Lexer<std::str::Chars<'_>>> {
+    let cfg = ParserConfig {
+        ansi_c_quoting: true,
+        ..ParserConfig::default()
+    };
+    make_parser_with_config(src, cfg)
+}
+
+#[test]
+fn test_word_dollar_single_quote_is_posix_by_default() {
+    let correct = TopLevelWord(Concat(vec![
+        lit("$"),
+        Word::SingleQuoted(String::from("\\n")),
+    ]));
+    assert_eq!(Some(correct), make_parser("$'\\n'").word().unwrap());
+}
+
+#[test]
+fn test_word_ansi_c_quote_valid() {
+    let correct = single_quoted("\n");
+    assert_eq!(Some(correct), make_ansi_c_parser("$'\\n'").word().unwrap());
+
+    let cases = [
+        ("$'a\\tb'", "a\tb"),
+        (
+            "$'\\a\\b\\e\\E\\f\\r\\v'",
+            "\u{07}\u{08}\u{1b}\u{1b}\u{0c}\r\u{0b}",
+        ),
+        ("$'\\\\ \\' \\\" \\?'", "\\ ' \" ?"),
+        ("$'\\101\\0\\1010'", "A\u{0}A0"),
+        ("$'\\x41\\x4a\\x4G'", "AJ\u{04}G"),
+        ("$'\\u263a\\U0001F600'", "\u{263a}\u{1F600}"),
+        ("$'\\cA\\c['", "\u{01}\u{1b}"),
+        ("$'\\c?'", "\u{7f}"),
+        ("$'\\q \\x $foo `bar`'", "\\q \\x $foo `bar`"),
+    ];
+
+    for &(src, unescaped) in &cases {
+        assert_eq!(
+            Some(single_quoted(unescaped)),
+            make_ansi_c_parser(src).word().unwrap(),
+            "failed to unescape {}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_word_ansi_c_quote_concatenates_with_other_words() {
+    let correct = TopLevelWord(Concat(vec![
+        lit("a"),
+        Word::SingleQuoted(String::from("b\nc")),
+    ]));
+    assert_eq!(
+        Some(correct),
+        make_ansi_c_parser("a$'b\\n''c'").word().unwrap()
+    );
+
+    // Only `$'` is special, other parameters are unaffected
+    assert_eq!(
+        Some(word_param(Parameter::Var(String::from("foo")))),
+        make_ansi_c_parser("$foo").word().unwrap()
+    );
+}
+
+#[test]
+fn test_word_ansi_c_quote_not_recognized_in_double_quotes() {
+    let correct = double_quoted("$'\\n'");
+    assert_eq!(
+        Some(correct),
+        make_ansi_c_parser("\"$'\\n'\"").word().unwrap()
+    );
+}
+
+#[test]
+fn test_word_ansi_c_quote_invalid_missing_close_quote() {
+    assert_eq!(
+        Err(UnterminatedQuote {
+            kind: QuoteKind::DollarSingle,
+            open: src(2, 1, 3),
+            end: src(8, 1, 9),
+        }),
+        make_ansi_c_parser("a $'b\\'c").complete_command()
+    );
+}
+
+#[test]
+fn test_word_unterminated_quote_span_and_display() {
+    let err = make_parser("x \"ab\ncd").complete_command().unwrap_err();
+    assert_eq!(
+        UnterminatedQuote {
+            kind: QuoteKind::Double,
+            open: src(2, 1, 3),
+            end: src(8, 2, 3),
+        },
+        err
+    );
+    assert_eq!(Some((src(2, 1, 3), src(8, 2, 3))), err.span());
+    assert_eq!("double quote opened at 1:3 never closed", err.to_string());
+}
+
 #[test]
 fn test_word_double_quote_valid() {
     let correct = TopLevelWord(Single(Word::DoubleQuoted(vec![Literal(String::from(
@@ -118,11 +267,9 @@ fn test_word_double_quote_valid_slash_escapes_double_quote() {
 }
 
 #[test]
-fn test_word_double_quote_valid_slash_escapes_newline() {
+fn test_word_double_quote_valid_slash_newline_continues_line() {
     let correct = TopLevelWord(Single(Word::DoubleQuoted(vec![
-        Literal(String::from("test")),
-        Escaped(String::from("\n")),
-        Literal(String::from(" ")),
+        Literal(String::from("test ")),
         Param(Parameter::Question),
         Literal(String::from("\n")),
     ])));
@@ -133,6 +280,14 @@ fn test_word_double_quote_valid_slash_escapes_newline() {
     );
 }
 
+#[test]
+fn test_word_double_quote_slash_newline_joins_literal() {
+    assert_eq!(
+        Some(double_quoted("foobar")),
+        make_parser("\"foo\\\nbar\"").word().unwrap()
+    );
+}
+
 #[test]
 fn test_word_double_quote_valid_slash_escapes_slash() {
     let correct = TopLevelWord(Single(Word::DoubleQuoted(vec![
@@ -161,11 +316,19 @@ fn test_word_double_quote_valid_slash_remains_literal_in_general_case() {
 #[test]
 fn test_word_double_quote_slash_invalid_missing_close_quote() {
     assert_eq!(
-        Err(Unmatched(Token::DoubleQuote, src(0, 1, 1))),
+        Err(UnterminatedQuote {
+            kind: QuoteKind::Double,
+            open: src(0, 1, 1),
+            end: src(6, 1, 7),
+        }),
         make_parser("\"hello").word()
     );
     assert_eq!(
-        Err(Unmatched(Token::DoubleQuote, src(0, 1, 1))),
+        Err(UnterminatedQuote {
+            kind: QuoteKind::Double,
+            open: src(0, 1, 1),
+            end: src(8, 1, 9),
+        }),
         make_parser("\"hello\\\"").word()
     );
 }
@@ -226,7 +389,7 @@ fn test_word_tokens_which_become_literal_words() {
     for w in &words {
         match make_parser(w).word() {
             Ok(Some(res)) => {
-                let correct = word(*w);
+                let correct = word(w);
                 if correct != res {
                     panic!(
                         "Unexpectedly parsed \"{}\": expected:\n{:#?}\ngot:\n{:#?}",
@@ -276,9 +439,139 @@ fn test_word_special_words_recognized_as_such() {
         Ok(Some(TopLevelWord(Single(Word::Simple(SquareClose))))),
         make_parser("]").word()
     );
+}
+
+#[test]
+fn test_word_bracket_class() {
+    let class = |negated, members: &str| Word::BracketClass(negated, String::from(members));
+
+    let cases = vec![
+        ("[a-z]", Single(class(false, "a-z"))),
+        ("[!0-9]", Single(class(true, "0-9"))),
+        ("[^0-9]", Single(class(true, "0-9"))),
+        ("[]x]", Single(class(false, "]x"))),
+        ("[!]x]", Single(class(true, "]x"))),
+        ("[[:alpha:]_]", Single(class(false, "[:alpha:]_"))),
+        ("[*?]", Single(class(false, "*?"))),
+        ("[a[bc]", Single(class(false, "a[bc"))),
+        (
+            "foo[0-9].txt",
+            Concat(vec![lit("foo"), class(false, "0-9"), lit(".txt")]),
+        ),
+        (
+            "[ab][cd]",
+            Concat(vec![class(false, "ab"), class(false, "cd")]),
+        ),
+    ];
+
+    for (src, correct) in cases {
+        assert_eq!(
+            Some(TopLevelWord(correct)),
+            make_parser(src).word().unwrap(),
+            "source: {}",
+            src
+        );
+    }
+}
+
+#[test]
+fn test_word_many_unterminated_brackets() {
+    let n = 100_000;
+    let correct = TopLevelWord(Concat(vec![Word::Simple(SquareOpen); n]));
+    let src = format!("echo {}", "[".repeat(n));
     assert_eq!(
-        Ok(Some(TopLevelWord(Single(Word::Simple(Colon))))),
-        make_parser(":").word()
+        Some(cmd_from_simple(SimpleCommand {
+            redirects_or_env_vars: vec![],
+            redirects_or_cmd_words: vec![
+                RedirectOrCmdWord::CmdWord(word("echo")),
+                RedirectOrCmdWord::CmdWord(correct),
+            ],
+        })),
+        make_parser(&src).complete_command().unwrap()
+    );
+}
+
+#[test]
+fn test_word_bracket_class_unterminated_stays_literal_pieces() {
+    let cases = vec![
+        ("[abc", Concat(vec![Word::Simple(SquareOpen), lit("abc")])),
+        (
+            "[[x",
+            Concat(vec![
+                Word::Simple(SquareOpen),
+                Word::Simple(SquareOpen),
+                lit("x"),
+            ]),
+        ),
+        (
+            "[]",
+            Concat(vec![Word::Simple(SquareOpen), Word::Simple(SquareClose)]),
+        ),
+        (
+            "[$x[bc]",
+            Concat(vec![
+                Word::Simple(SquareOpen),
+                Word::Simple(Param(Parameter::Var(String::from("x")))),
+                Word::BracketClass(false, String::from("bc")),
+            ]),
+        ),
+        // Only unquoted literals may make up a bracket expression
+        (
+            "[$x]",
+            Concat(vec![
+                Word::Simple(SquareOpen),
+                Word::Simple(Param(Parameter::Var(String::from("x")))),
+                Word::Simple(SquareClose),
+            ]),
+        ),
+        (
+            "['a']",
+            Concat(vec![
+                Word::Simple(SquareOpen),
+                Word::SingleQuoted(String::from("a")),
+                Word::Simple(SquareClose),
+            ]),
+        ),
+    ];
+
+    for (src, correct) in cases {
+        assert_eq!(
+            Some(TopLevelWord(correct)),
+            make_parser(src).word().unwrap(),
+            "source: {}",
+            src
+        );
+    }
+
+    // The closing bracket must be part of the same word
+    let mut p = make_parser("[a b]");
+    assert_eq!(
+        Some(TopLevelWord(Concat(vec![
+            Word::Simple(SquareOpen),
+            lit("a")
+        ]))),
+        p.word().unwrap()
+    );
+    assert_eq!(
+        Some(TopLevelWord(Concat(vec![
+            lit("b"),
+            Word::Simple(SquareClose)
+        ]))),
+        p.word().unwrap()
+    );
+}
+
+#[test]
+fn test_word_colon_is_literal_outside_assignments() {
+    assert_eq!(Ok(Some(word(":"))), make_parser(":").word());
+    assert_eq!(Ok(Some(word("a:b"))), make_parser("a:b").word());
+    assert_eq!(
+        Ok(Some(TopLevelWord(Concat(vec![
+            lit("a:"),
+            Word::Simple(Tilde),
+            lit("/b"),
+        ])))),
+        make_parser("a:~/b").word()
     );
 }
 
@@ -310,3 +603,99 @@ fn test_word_escaped_newline_becomes_whitespace() {
     assert_eq!(Ok(Some(word("foo"))), p.word());
     assert_eq!(Ok(Some(word("bar"))), p.word());
 }
+
+#[test]
+fn test_word_structurally_equal_words_hash_equally() {
+    use std::collections::HashSet;
+
+    let mut words = HashSet::new();
+    words.insert(make_parser("foo${bar:-baz}\"$(qux)\"").word().unwrap());
+    words.insert(make_parser("foo${bar:-baz}\"$(qux)\"").word().unwrap());
+    assert_eq!(1, words.len());
+
+    words.insert(make_parser("foo${bar:-baz}'$(qux)'").word().unwrap());
+    assert_eq!(2, words.len());
+
+    let mut cmds = HashSet::new();
+    cmds.insert(
+        make_parser("foo | bar > out && baz")
+            .complete_command()
+            .unwrap(),
+    );
+    cmds.insert(make_parser("foo|bar>out&&baz").complete_command().unwrap());
+    assert_eq!(1, cmds.len());
+}
+
+#[test]
+fn test_word_parse_option_cluster() {
+    let cluster = |src: &str| parse_option_cluster(&make_parser(src).word().unwrap().unwrap());
+
+    assert_eq!(Some(('-', vec!['e', 'u', 'o'])), cluster("-euo"));
+    assert_eq!(Some(('+', vec!['x'])), cluster("+x"));
+    assert_eq!(Some(('-', vec![])), cluster("--"));
+    assert_eq!(Some(('-', vec!['e'])), cluster("'-e'"));
+    assert_eq!(Some(('-', vec!['e', 'x'])), cluster("-\"e\"x"));
+
+    assert_eq!(None, cluster("pipefail"));
+    assert_eq!(None, cluster("-"));
+    assert_eq!(None, cluster("+"));
+    assert_eq!(None, cluster("--foo"));
+    assert_eq!(None, cluster("-e1"));
+    assert_eq!(None, cluster("-$x"));
+    assert_eq!(None, cluster("-[ab]"));
+}
+
+#[test]
+fn test_word_list_stops_at_separator() {
+    let mut p = make_parser("one two three;");
+    assert_eq!(
+        vec![word("one"), word("two"), word("three")],
+        p.word_list().unwrap()
+    );
+    // The separator is left for the caller
+    assert_eq!(Vec::<TopLevelWord<String>>::new(), p.word_list().unwrap());
+    assert_eq!(
+        Err(Unexpected(Token::Semi, src(13, 1, 14))),
+        p.complete_command()
+    );
+
+    let mut p = make_parser("\"$a\" $b 'c'\nd");
+    let correct = vec![
+        TopLevelWord(Single(Word::DoubleQuoted(vec![Param(Parameter::Var(
+            String::from("a"),
+        ))]))),
+        word_param(Parameter::Var(String::from("b"))),
+        single_quoted("c"),
+    ];
+    assert_eq!(correct, p.word_list().unwrap());
+    assert_eq!(Some(cmd("d")), p.complete_command().unwrap());
+
+    assert_eq!(
+        vec![word("a"), word("do"), word("b")],
+        make_parser("a do b").word_list().unwrap()
+    );
+    assert_eq!(
+        Vec::<TopLevelWord<String>>::new(),
+        make_parser("").word_list().unwrap()
+    );
+}
+
+#[test]
+fn test_word_nul_bytes_are_literals() {
+    assert_eq!(
+        Some(single_quoted("a\0b")),
+        make_parser("'a\0b'").word().unwrap()
+    );
+    assert_eq!(
+        Some(TopLevelWord(Single(Word::DoubleQuoted(vec![Literal(
+            String::from("a\0b")
+        )])))),
+        make_parser("\"a\0b\"").word().unwrap()
+    );
+
+    // A standalone NUL is neither whitespace nor a delimiter
+    assert_eq!(
+        Some(cmd_args("echo", &["\0", "a\0b"])),
+        make_parser("echo \0 a\0b").complete_command().unwrap()
+    );
+}